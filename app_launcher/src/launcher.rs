@@ -0,0 +1,154 @@
+use caro_client::{
+    client_app,
+    input_from_user::key_map,
+    output_to_user::{menu_entities, screen_entity}
+};
+use simple_caro_app::{server_app, server_config};
+
+use crate::launcher_entities;
+
+const LAUNCHER_PROMPT_POS: (usize, usize) = (17, 63);
+// the hosted server only listens on this machine
+const HOSTED_SERVER_INTERFACE: &str = "127.0.0.1";
+const HOSTED_SERVER_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LauncherCommand {
+    PlayLocal,
+    Connect(String),
+    Host(u16),
+    Exit,
+    Invalid(String),
+}
+
+impl From<&str> for LauncherCommand {
+    fn from(line: &str) -> Self {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["local"] => LauncherCommand::PlayLocal,
+            ["connect", address] => LauncherCommand::Connect(address.to_string()),
+            ["host", port] => match port.parse() {
+                Ok(port) => LauncherCommand::Host(port),
+                Err(_) => LauncherCommand::Invalid(format!("Invalid port: {}", port)),
+            },
+            ["exit"] => LauncherCommand::Exit,
+            _ => LauncherCommand::Invalid(format!("Invalid command: {}", line.trim())),
+        }
+    }
+}
+
+pub struct Launcher {
+    entities_vec: Vec<Box<dyn screen_entity::ScreenEntity>>,
+    log_entity: Box<dyn screen_entity::ScreenEntity>,
+    // handed to whatever is played from the launcher
+    key_map: key_map::KeyMap,
+}
+
+impl Launcher {
+    pub fn new() -> Self {
+        Self {
+            entities_vec: launcher_entities::get_launcher_entities(),
+            log_entity: Box::new(menu_entities::LogBox::new("".to_string())),
+            key_map: key_map::KeyMap::default(),
+        }
+    }
+
+    pub fn set_key_map(&mut self, key_map: key_map::KeyMap) {
+        self.key_map = key_map;
+    }
+
+    pub async fn run(&mut self) {
+        self.show_menu();
+        loop {
+            let line = match caro_console::input::get_user_input().await {
+                caro_console::input::InputType::Text(line) => line,
+                caro_console::input::InputType::Key(caro_console::input::KeyType::Exit) => break,
+                caro_console::input::InputType::Key(_) => continue,
+            };
+            if line.trim().is_empty() {
+                self.relocate_prompt();
+                continue;
+            }
+            match LauncherCommand::from(line.as_str()) {
+                LauncherCommand::PlayLocal => {
+                    let mut local_app = local_app::game_loop::LocalApp::new();
+                    local_app.set_key_map(self.key_map.clone());
+                    local_app.run().await;
+                    self.show_menu();
+                },
+                LauncherCommand::Connect(address) => {
+                    let settings = client_app::ClientSettings {
+                        server_address: address,
+                        key_map: self.key_map.clone(),
+                        ..Default::default()
+                    };
+                    client_app::run(settings).await;
+                    self.show_menu();
+                },
+                LauncherCommand::Host(port) => {
+                    let settings = server_config::ServerSettings {
+                        host: HOSTED_SERVER_INTERFACE.to_string(),
+                        port,
+                        ..Default::default()
+                    };
+                    match server_app::CaroServer::bind(settings).await {
+                        Ok(server) => {
+                            // the hosted server lives as long as the local player stays connected
+                            let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+                            let mut hosted = tokio::spawn(server.serve_until(async move {
+                                let _ = stopped.await;
+                            }));
+                            let settings = client_app::ClientSettings {
+                                server_address: format!("{}:{}", HOSTED_SERVER_INTERFACE, port),
+                                key_map: self.key_map.clone(),
+                                ..Default::default()
+                            };
+                            client_app::run(settings).await;
+                            let _ = stop_server.send(());
+                            // the menu comes back even if a remote player holds the drain up
+                            if tokio::time::timeout(HOSTED_SERVER_STOP_TIMEOUT, &mut hosted).await.is_err() {
+                                hosted.abort();
+                            }
+                            self.show_menu();
+                        },
+                        Err(err) => {
+                            self.log(format!("Cannot host on port {}: {}", port, err));
+                        },
+                    }
+                },
+                LauncherCommand::Exit => {
+                    break;
+                },
+                LauncherCommand::Invalid(reason) => {
+                    self.log(reason);
+                },
+            }
+        }
+        caro_console::output::clean_screen();
+        caro_console::input::enable_prompt_mode_at(0, 0);
+    }
+
+    fn show_menu(&self) {
+        caro_console::output::clean_screen();
+        for entity in self.entities_vec.iter() {
+            entity.display();
+        }
+        self.log_entity.display();
+        self.relocate_prompt();
+    }
+
+    fn log(&mut self, content: String) {
+        self.log_entity = Box::new(menu_entities::LogBox::new(content));
+        self.show_menu();
+    }
+
+    fn relocate_prompt(&self) {
+        caro_console::input::enable_prompt_mode_at(LAUNCHER_PROMPT_POS.0, LAUNCHER_PROMPT_POS.1);
+    }
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,52 @@
+use caro_console::artworks::ArtDimension;
+
+use caro_client::output_to_user::screen_entity;
+
+const LAUNCHER_INSTRUCTION_BOX_POS: (usize, usize) = (5, 15);
+pub struct InstructionBox {
+    entity: caro_console::output::DrawableBox,
+}
+
+impl InstructionBox {
+    pub fn new() -> Self {
+        let art = caro_console::artworks::LAUNCHER_INSTRUCTION.to_string();
+        Self {
+            entity: caro_console::output::DrawableBox {
+                coordinate: LAUNCHER_INSTRUCTION_BOX_POS,
+                constraint: (art.height(), art.width()),
+                offset: (0, 0),
+                show_boundary_line: true,
+                art,
+            }
+        }
+    }
+}
+
+impl Default for InstructionBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl screen_entity::ScreenEntity for InstructionBox {
+    fn display(&self) {
+        caro_console::output::set_pen_color(caro_console::output::Color::Cyan(100));
+        caro_console::output::draw(&self.entity);
+    }
+
+    fn get_position(&self) -> (screen_entity::Latitude, screen_entity::Longtitude) {
+        (self.entity.coordinate.0 as i64, self.entity.coordinate.1 as i64)
+    }
+
+    fn set_position(&mut self, latitude: screen_entity::Latitude, longtitude: screen_entity::Longtitude) {
+        self.entity.coordinate.0 = latitude as usize;
+        self.entity.coordinate.1 = longtitude as usize;
+    }
+}
+
+pub fn get_launcher_entities() -> Vec<Box<dyn screen_entity::ScreenEntity>> {
+    vec![
+        Box::new(InstructionBox::new()),
+        Box::new(caro_client::output_to_user::menu_entities::PromptBox::new()),
+    ]
+}
@@ -1,3 +1,21 @@
-fn main() {
-    println!("Hello, world!");
+use caro_client::input_from_user::key_map;
+
+mod launcher;
+mod launcher_entities;
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 3)]
+async fn main() {
+    caro_console::output::restore_terminal_on_panic();
+    caro_console::input::set_history_file(caro_console::line_editor::default_history_file());
+    let key_map = match key_map::KeyMap::load(std::env::var_os("CARO_KEY_MAP").map(std::path::PathBuf::from).as_deref()) {
+        Ok(key_map) => key_map,
+        Err(err) => {
+            eprintln!("cannot load key map: {}", err);
+            std::process::exit(1);
+        },
+    };
+    let mut launcher = launcher::Launcher::new();
+    launcher.set_key_map(key_map);
+    launcher.run().await;
+    caro_console::output::restore_terminal();
 }
@@ -83,8 +83,17 @@ pub const O_SYM: &'static str = "O";
 pub const MENU_INSTRUCTION: &'static str = concat!(
 "              Instructions              \n",
 "========================================\n",
-"  mkroom [3|4|5] : create a new room    \n",
-"  cdroom [rid] : join an existing room  \n",
+"  mkroom <3|4|5> [--size N] [--private] \n",
+"  join <rid|code> [pw] : join a room    \n",
+"  rooms : list open rooms               \n",
+"  help [cmd] : more, exit : quit        \n"
+);
+
+pub const CONNECT_INSTRUCTION: &'static str = concat!(
+"          Cannot reach server           \n",
+"========================================\n",
+"  retry : try to connect again          \n",
+"  server [host:port] : change server    \n",
 "  exit : exit the application           \n"
 );
 
@@ -96,7 +105,55 @@ pub const ROOM_INSTRUCTION: &'static str = concat!(
 "                                        \n"
 );
 
-pub const PROMPT_BOX: &'static str = concat!(
+pub const GAME_INSTRUCTION: &'static str = concat!(
+"              Instructions              \n",
+"========================================\n",
+"  move <h8|lat long> : place your mark  \n",
+"  switch : toggle arrow key mode        \n",
+"  say <text> : talk to your opponent    \n",
+"  leave : give up and leave the room    \n"
+);
+
+pub const LOCAL_GAME_INSTRUCTION: &str = concat!(
+"              Instructions              \n",
+"========================================\n",
+"  move <h8|lat long> : place your mark  \n",
+"  switch : toggle arrow key mode        \n",
+"  hint : ask for a suggested move       \n",
+"  undo / redo : take back your move     \n",
+"  leave : give up and leave the game    \n"
+);
+
+pub const GAME_OVER_HEADER: &str = concat!(
+"               Game over                \n",
+"========================================\n"
+);
+
+pub const GAME_OVER_OPTIONS: &str = concat!(
+"  rematch : play again                  \n",
+"  lobby : back to the lobby             \n"
+);
+
+pub const LOCAL_INSTRUCTION: &str = concat!(
+"         Play against the computer      \n",
+"========================================\n",
+"  rule [3|4|5] : choose the game rule   \n",
+"  first [me|ai] : who moves first       \n",
+"  level [easy|normal|hard] : difficulty \n",
+"  play : start a new game               \n",
+"  exit : exit the application           \n"
+);
+
+pub const LAUNCHER_INSTRUCTION: &str = concat!(
+"             Caro Launcher              \n",
+"========================================\n",
+"  local : play against the computer     \n",
+"  connect [ip:port] : join a server     \n",
+"  host [port] : host a server and join  \n",
+"  exit : exit the application           \n"
+);
+
+pub const PROMPT_BOX: &str = concat!(
 "                PROMPT BOX              \n",
 "========================================\n",
 " >                                      \n",
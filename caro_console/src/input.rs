@@ -1,10 +1,14 @@
-use std::{io, sync::atomic::{AtomicBool, Ordering}};
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::{atomic::{AtomicBool, Ordering}, LazyLock, Mutex},
+};
 
 use tokio::{self, io::AsyncBufReadExt};
 
-use crate::types;
+use crate::{line_editor, types};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeyType {
     Up,
     Down,
@@ -12,6 +16,19 @@ pub enum KeyType {
     Right,
     Enter,
     Esc,
+    Tab,
+    Backspace,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    // as typed, shift gives the upper case letter
+    Char(char),
+    // a lower case letter, ctrl-c and ctrl-d are Exit instead
+    Ctrl(char),
+    // ctrl-c or ctrl-d, also given once the input is closed
+    Exit,
     Invalid,
 }
 
@@ -22,18 +39,39 @@ pub enum InputType {
 }
 
 static IS_PROMPT_MODE: AtomicBool = AtomicBool::new(true);
+// where the line being typed is drawn, the prompt moves with the screen
+static PROMPT_POS: Mutex<(u16, u16)> = Mutex::new((0, 0));
+// shared with whoever moves the prompt, so a redraw does not lose what was typed
+static LINE_EDITOR: LazyLock<Mutex<line_editor::LineEditor>> = LazyLock::new(|| Mutex::new(line_editor::LineEditor::new()));
+
+// piped input has no keys to read and is taken line by line as it comes
+fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+pub fn set_history_file(path: Option<PathBuf>) {
+    LINE_EDITOR.lock().unwrap().set_history_file(path);
+}
+
+pub fn set_completer(completer: Option<line_editor::Completer>) {
+    LINE_EDITOR.lock().unwrap().set_completer(completer);
+}
 
 pub fn enable_prompt_mode_at(latitude: types::Latitude, longtitude: types::Longtitude) {
     let mut stdout = io::stdout();
     let latitude = latitude.max(0) as u16;
     let longtitude = longtitude.max(0) as u16;
-    let _ = crossterm::terminal::disable_raw_mode();
-    let _ = crossterm::execute!(
-        stdout,
-        crossterm::cursor::Show,
-        crossterm::cursor::MoveTo(longtitude, latitude),
-    );
+    *PROMPT_POS.lock().unwrap() = (latitude, longtitude);
     IS_PROMPT_MODE.store(true, Ordering::Release);
+    if !is_interactive() {
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = crossterm::execute!(stdout, crossterm::cursor::Show, crossterm::cursor::MoveTo(longtitude, latitude));
+        return;
+    }
+    // the line editor reads keys one by one, so the terminal stays raw in prompt mode too
+    let _ = crossterm::terminal::enable_raw_mode();
+    let _ = crossterm::execute!(stdout, crossterm::cursor::Show);
+    LINE_EDITOR.lock().unwrap().render(latitude, longtitude);
 }
 
 pub fn disable_prompt_mode() {
@@ -47,35 +85,61 @@ pub fn is_prompt_mode() -> bool {
     IS_PROMPT_MODE.load(Ordering::Acquire)
 }
 
-pub async fn get_user_input() -> InputType {
-    if is_prompt_mode() {
-        let mut reader = tokio::io::BufReader::new(tokio::io::stdin()).lines();
-        if let Some(line) = reader.next_line().await.unwrap() {
-            InputType::Text(line)
+async fn next_key_press() -> crossterm::event::KeyEvent {
+    loop {
+        if crossterm::event::poll(std::time::Duration::from_millis(100)).unwrap_or(false) {
+            if let Ok(crossterm::event::Event::Key(key_event)) = crossterm::event::read()
+                && key_event.kind == crossterm::event::KeyEventKind::Press {
+                return key_event;
+            }
         } else {
-            InputType::Text("".to_string())
+            // Yield to the async runtime
+            tokio::task::yield_now().await;
         }
-    } else {
-        // Use crossterm to read a key event in raw mode
-        loop {
-            if crossterm::event::poll(std::time::Duration::from_millis(100)).unwrap_or(false) {
-                if let crossterm::event::Event::Key(key_event) = crossterm::event::read().unwrap() {
-                    if key_event.kind == crossterm::event::KeyEventKind::Press {
-                        return InputType::Key(match key_event.code {
-                            crossterm::event::KeyCode::Up => KeyType::Up,
-                            crossterm::event::KeyCode::Down => KeyType::Down,
-                            crossterm::event::KeyCode::Left => KeyType::Left,
-                            crossterm::event::KeyCode::Right => KeyType::Right,
-                            crossterm::event::KeyCode::Enter => KeyType::Enter,
-                            crossterm::event::KeyCode::Esc => KeyType::Esc,
-                            _ => KeyType::Invalid,
-                        });
-                    }
-                }
-            } else {
-                // Yield to the async runtime
-                tokio::task::yield_now().await;
+    }
+}
+
+pub async fn get_user_input() -> InputType {
+    if is_prompt_mode() && !is_interactive() {
+        let mut reader = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+        return match reader.next_line().await {
+            Ok(Some(line)) => InputType::Text(line),
+            _ => InputType::Key(KeyType::Exit),
+        };
+    }
+    loop {
+        let key_event = next_key_press().await;
+        // the mode may have been switched while waiting for the key
+        if is_prompt_mode() {
+            let mut line_editor = LINE_EDITOR.lock().unwrap();
+            let result = line_editor.handle_key(key_event);
+            let (latitude, longtitude) = *PROMPT_POS.lock().unwrap();
+            line_editor.render(latitude, longtitude);
+            match result {
+                line_editor::EditResult::Editing => continue,
+                line_editor::EditResult::Submitted(line) => return InputType::Text(line),
+                line_editor::EditResult::Key(key) => return InputType::Key(key),
             }
         }
+        let ctrl = key_event.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+        return InputType::Key(match key_event.code {
+            crossterm::event::KeyCode::Char('c') | crossterm::event::KeyCode::Char('d') if ctrl => KeyType::Exit,
+            crossterm::event::KeyCode::Char(c) if ctrl => KeyType::Ctrl(c.to_ascii_lowercase()),
+            crossterm::event::KeyCode::Char(c) => KeyType::Char(c),
+            crossterm::event::KeyCode::Up => KeyType::Up,
+            crossterm::event::KeyCode::Down => KeyType::Down,
+            crossterm::event::KeyCode::Left => KeyType::Left,
+            crossterm::event::KeyCode::Right => KeyType::Right,
+            crossterm::event::KeyCode::Enter => KeyType::Enter,
+            crossterm::event::KeyCode::Esc => KeyType::Esc,
+            crossterm::event::KeyCode::Tab => KeyType::Tab,
+            crossterm::event::KeyCode::Backspace => KeyType::Backspace,
+            crossterm::event::KeyCode::Delete => KeyType::Delete,
+            crossterm::event::KeyCode::Home => KeyType::Home,
+            crossterm::event::KeyCode::End => KeyType::End,
+            crossterm::event::KeyCode::PageUp => KeyType::PageUp,
+            crossterm::event::KeyCode::PageDown => KeyType::PageDown,
+            _ => KeyType::Invalid,
+        });
     }
-}
\ No newline at end of file
+}
@@ -3,4 +3,5 @@ pub mod artworks;
 pub mod caro_art_tools;
 pub mod output;
 pub mod input;
+pub mod line_editor;
 pub mod types;
\ No newline at end of file
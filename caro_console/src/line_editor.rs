@@ -0,0 +1,326 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::input::KeyType;
+
+// only the newest lines are kept, in memory and in the file
+pub const MAX_HISTORY: usize = 500;
+// what fits behind the > of the prompt boxes
+pub const PROMPT_WIDTH: usize = 36;
+
+// given the line up to the cursor, returns what the word being typed could become
+pub type Completer = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditResult {
+    // the line changed or nothing happened, keep reading
+    Editing,
+    Submitted(String),
+    // a key the editor does not handle itself
+    Key(KeyType),
+}
+
+pub struct LineEditor {
+    buffer: Vec<char>,
+    // in chars, 0 is before the first one
+    cursor: usize,
+    history: Vec<String>,
+    // the history entry shown while browsing, the typed line is kept in draft meanwhile
+    browsing: Option<usize>,
+    draft: Vec<char>,
+    history_file: Option<PathBuf>,
+    completer: Option<Completer>,
+    // what tab cycles through after a completion was ambiguous, and which one is shown
+    completion: Option<(Vec<String>, usize)>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            cursor: 0,
+            history: Vec::new(),
+            browsing: None,
+            draft: Vec::new(),
+            history_file: None,
+            completer: None,
+            completion: None,
+        }
+    }
+
+    pub fn get_line(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn get_history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn set_completer(&mut self, completer: Option<Completer>) {
+        self.completer = completer;
+        self.completion = None;
+    }
+
+    // replaces the history with what the file holds, a missing file is an empty history
+    pub fn set_history_file(&mut self, path: Option<PathBuf>) {
+        self.history = match &path {
+            Some(path) => load_history(path),
+            None => Vec::new(),
+        };
+        self.history_file = path;
+        self.browsing = None;
+    }
+
+    pub fn handle_key(&mut self, key_event: KeyEvent) -> EditResult {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        if key_event.code != KeyCode::Tab {
+            self.completion = None;
+        }
+        match key_event.code {
+            KeyCode::Char('c') if ctrl => return EditResult::Key(KeyType::Exit),
+            // like a shell, ctrl-d only quits on an empty line
+            KeyCode::Char('d') if ctrl && self.buffer.is_empty() => return EditResult::Key(KeyType::Exit),
+            KeyCode::Char('d') if ctrl => self.delete(),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.buffer.len(),
+            KeyCode::Char('u') if ctrl => self.kill_to_start(),
+            KeyCode::Char('k') if ctrl => self.buffer.truncate(self.cursor),
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char(_) if ctrl => {},
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.buffer.len(),
+            KeyCode::Up => self.history_previous(),
+            KeyCode::Down => self.history_next(),
+            KeyCode::Tab => self.complete(),
+            KeyCode::Enter => return EditResult::Submitted(self.submit()),
+            KeyCode::Esc => return EditResult::Key(KeyType::Esc),
+            _ => {},
+        }
+        EditResult::Editing
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.insert(c);
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn kill_to_start(&mut self) {
+        self.buffer.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    // the spaces before the word go with it, like ctrl-w in a shell
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.buffer[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.buffer[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.buffer.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn show(&mut self, line: Vec<char>) {
+        self.cursor = line.len();
+        self.buffer = line;
+    }
+
+    fn history_previous(&mut self) {
+        let index = match self.browsing {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.buffer.clone();
+                self.history.len() - 1
+            },
+        };
+        self.browsing = Some(index);
+        self.show(self.history[index].chars().collect());
+    }
+
+    fn history_next(&mut self) {
+        match self.browsing {
+            None => {},
+            Some(index) if index + 1 < self.history.len() => {
+                self.browsing = Some(index + 1);
+                self.show(self.history[index + 1].chars().collect());
+            },
+            // past the newest entry is the line that was being typed
+            Some(_) => {
+                self.browsing = None;
+                let draft = std::mem::take(&mut self.draft);
+                self.show(draft);
+            },
+        }
+    }
+
+    // returns where the word before the cursor starts
+    fn word_start(&self) -> usize {
+        let mut start = self.cursor;
+        while start > 0 && !self.buffer[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        start
+    }
+
+    fn replace_word(&mut self, replacement: &str) {
+        let start = self.word_start();
+        self.buffer.drain(start..self.cursor);
+        self.cursor = start;
+        self.insert_str(replacement);
+    }
+
+    // a single match is completed with a space after it, several are completed as far as they agree,
+    // then tab goes through them one by one
+    fn complete(&mut self) {
+        if let Some((candidates, index)) = self.completion.take() {
+            let next = (index + 1) % candidates.len();
+            self.replace_word(&candidates[next]);
+            self.completion = Some((candidates, next));
+            return;
+        }
+        let Some(completer) = self.completer.clone() else {
+            return;
+        };
+        let before_cursor: String = self.buffer[..self.cursor].iter().collect();
+        let word: String = self.buffer[self.word_start()..self.cursor].iter().collect();
+        let mut candidates: Vec<String> = completer(&before_cursor).into_iter()
+            .filter(|candidate| candidate.starts_with(&word))
+            .collect();
+        candidates.dedup();
+        match candidates.len() {
+            0 => {},
+            1 => {
+                self.replace_word(&candidates[0]);
+                if self.buffer.get(self.cursor) != Some(&' ') {
+                    self.insert(' ');
+                }
+            },
+            _ => {
+                let prefix = common_prefix(&candidates);
+                if prefix.chars().count() > word.chars().count() {
+                    self.replace_word(&prefix);
+                } else {
+                    self.replace_word(&candidates[0]);
+                    self.completion = Some((candidates, 0));
+                }
+            },
+        }
+    }
+
+    // the line is remembered unless it is empty or the same as the one before
+    fn submit(&mut self) -> String {
+        let line = self.get_line();
+        self.buffer.clear();
+        self.cursor = 0;
+        self.browsing = None;
+        self.draft.clear();
+        if line.trim().is_empty() || self.history.last() == Some(&line) {
+            return line;
+        }
+        self.history.push(line.clone());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.history_file {
+            // losing the history is not worth bothering the player about
+            let _ = append_history(path, &line);
+        }
+        line
+    }
+
+    // draws the part of the line around the cursor, the rest of the prompt is blanked
+    pub fn render(&self, latitude: u16, longtitude: u16) {
+        let scroll = (self.cursor + 1).saturating_sub(PROMPT_WIDTH);
+        let visible: String = self.buffer.iter().skip(scroll).take(PROMPT_WIDTH).collect();
+        let mut stdout = io::stdout();
+        let _ = crossterm::queue!(
+            stdout,
+            crossterm::cursor::MoveTo(longtitude, latitude),
+            crossterm::style::Print(format!("{:<width$}", visible, width = PROMPT_WIDTH)),
+            crossterm::cursor::MoveTo(longtitude + (self.cursor - scroll) as u16, latitude),
+        );
+        let _ = stdout.flush();
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix: Vec<char> = candidates[0].chars().collect();
+    for candidate in candidates.iter().skip(1) {
+        let same = prefix.iter().zip(candidate.chars()).take_while(|(a, b)| **a == *b).count();
+        prefix.truncate(same);
+    }
+    prefix.into_iter().collect()
+}
+
+// a file grown past the limit is cut back to what is kept
+pub fn load_history(path: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let lines: Vec<String> = content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    let skip = lines.len().saturating_sub(MAX_HISTORY);
+    let history: Vec<String> = lines.into_iter().skip(skip).collect();
+    if skip > 0 {
+        let _ = fs::write(path, history.iter().map(|line| format!("{}\n", line)).collect::<String>());
+    }
+    history
+}
+
+fn append_history(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+// $HOME/.caro_history, or nothing if there is no home to keep it in
+pub fn default_history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".caro_history"))
+}
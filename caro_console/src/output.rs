@@ -81,6 +81,26 @@ pub fn clean_screen() {
     );
 }
 
+// undoes whatever the application did to the terminal: raw mode, colours and a hidden cursor
+pub fn restore_terminal() {
+    let mut stdout = io::stdout();
+    let _ = crossterm::terminal::disable_raw_mode();
+    let _ = crossterm::execute!(
+        stdout,
+        crossterm::style::ResetColor,
+        crossterm::cursor::Show
+    );
+}
+
+// a panic message is printed on a usable terminal, the previous hook still runs after
+pub fn restore_terminal_on_panic() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        restore_terminal();
+        previous_hook(panic_info);
+    }));
+}
+
 pub fn set_pen_color(color: Color) {
     let mut stdout = io::stdout();
     let _ = match color {
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use caro_console::{
+    input::KeyType,
+    line_editor::{EditResult, LineEditor, MAX_HISTORY}
+};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn ctrl(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn type_text(line_editor: &mut LineEditor, text: &str) {
+    for c in text.chars() {
+        assert_eq!(line_editor.handle_key(key(KeyCode::Char(c))), EditResult::Editing);
+    }
+}
+
+fn history_file(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("caro_history_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn the_cursor_moves_and_edits_in_place() {
+    let mut line_editor = LineEditor::new();
+    type_text(&mut line_editor, "move 7");
+    line_editor.handle_key(key(KeyCode::Left));
+    line_editor.handle_key(key(KeyCode::Left));
+    type_text(&mut line_editor, " 8");
+    assert_eq!((line_editor.get_line().as_str(), line_editor.get_cursor()), ("move 8 7", 6));
+
+    line_editor.handle_key(key(KeyCode::Home));
+    line_editor.handle_key(key(KeyCode::Delete));
+    line_editor.handle_key(key(KeyCode::End));
+    line_editor.handle_key(key(KeyCode::Backspace));
+    assert_eq!(line_editor.get_line(), "ove 8 ");
+
+    line_editor.handle_key(ctrl('w'));
+    assert_eq!(line_editor.get_line(), "ove ");
+    line_editor.handle_key(ctrl('a'));
+    line_editor.handle_key(ctrl('k'));
+    assert_eq!(line_editor.get_line(), "");
+}
+
+#[test]
+fn enter_hands_the_line_over_and_starts_a_new_one() {
+    let mut line_editor = LineEditor::new();
+    type_text(&mut line_editor, "rooms");
+    assert_eq!(line_editor.handle_key(key(KeyCode::Enter)), EditResult::Submitted("rooms".to_string()));
+    assert_eq!(line_editor.get_line(), "");
+    assert_eq!(line_editor.handle_key(key(KeyCode::Esc)), EditResult::Key(KeyType::Esc));
+}
+
+#[test]
+fn ctrl_d_only_exits_on_an_empty_line() {
+    let mut line_editor = LineEditor::new();
+    type_text(&mut line_editor, "ab");
+    line_editor.handle_key(key(KeyCode::Left));
+    assert_eq!(line_editor.handle_key(ctrl('d')), EditResult::Editing);
+    assert_eq!(line_editor.get_line(), "a");
+    line_editor.handle_key(ctrl('u'));
+    assert_eq!(line_editor.handle_key(ctrl('d')), EditResult::Key(KeyType::Exit));
+    assert_eq!(line_editor.handle_key(ctrl('c')), EditResult::Key(KeyType::Exit));
+}
+
+#[test]
+fn up_and_down_browse_the_history_and_come_back_to_the_draft() {
+    let mut line_editor = LineEditor::new();
+    for line in ["rooms", "join 3", "join 3", "   "] {
+        type_text(&mut line_editor, line);
+        line_editor.handle_key(key(KeyCode::Enter));
+    }
+    // repeated and blank lines are not remembered
+    assert_eq!(line_editor.get_history(), ["rooms", "join 3"]);
+
+    type_text(&mut line_editor, "mk");
+    line_editor.handle_key(key(KeyCode::Up));
+    assert_eq!(line_editor.get_line(), "join 3");
+    line_editor.handle_key(key(KeyCode::Up));
+    line_editor.handle_key(key(KeyCode::Up));
+    assert_eq!(line_editor.get_line(), "rooms");
+    line_editor.handle_key(key(KeyCode::Down));
+    assert_eq!(line_editor.get_line(), "join 3");
+    line_editor.handle_key(key(KeyCode::Down));
+    assert_eq!((line_editor.get_line().as_str(), line_editor.get_cursor()), ("mk", 2));
+}
+
+#[test]
+fn the_history_is_kept_in_its_file() {
+    let path = history_file("kept");
+    let mut line_editor = LineEditor::new();
+    line_editor.set_history_file(Some(path.clone()));
+    type_text(&mut line_editor, "mkroom 5");
+    line_editor.handle_key(key(KeyCode::Enter));
+
+    let mut next_session = LineEditor::new();
+    next_session.set_history_file(Some(path.clone()));
+    assert_eq!(next_session.get_history(), ["mkroom 5"]);
+
+    // an old file is cut back to the newest lines
+    let lines: String = (0..MAX_HISTORY + 10).map(|i| format!("say {}\n", i)).collect();
+    std::fs::write(&path, lines).unwrap();
+    next_session.set_history_file(Some(path.clone()));
+    assert_eq!(next_session.get_history().len(), MAX_HISTORY);
+    assert_eq!(next_session.get_history()[0], "say 10");
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), MAX_HISTORY);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn tab_completes_what_the_completer_offers() {
+    let mut line_editor = LineEditor::new();
+    line_editor.set_completer(Some(Arc::new(|line: &str| match line.split_whitespace().count() {
+        0 | 1 if !line.ends_with(' ') => vec!["mkroom".to_string(), "move".to_string(), "rooms".to_string()],
+        _ => vec!["12".to_string(), "13".to_string()],
+    })));
+
+    // a single match is finished with a space
+    type_text(&mut line_editor, "r");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "rooms ");
+    line_editor.handle_key(ctrl('u'));
+
+    type_text(&mut line_editor, "mo");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "move ");
+    line_editor.handle_key(ctrl('u'));
+
+    // several that agree on nothing more are gone through one by one
+    type_text(&mut line_editor, "m");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "mkroom");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "move");
+    line_editor.handle_key(ctrl('u'));
+    type_text(&mut line_editor, "join 1");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "join 12");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "join 13");
+    line_editor.handle_key(key(KeyCode::Tab));
+    assert_eq!(line_editor.get_line(), "join 12");
+}
@@ -237,6 +237,58 @@ impl SimpleCaro {
         moves_history
     }
 
+    // the tiles that won the game, from one end of the line to the other, empty while nobody won.
+    // the library does not keep the line, it is found again the same way its judge does,
+    // through the winner's latest move
+    pub fn get_winning_line(&self, rule: RuleType) -> Vec<Coordinate> {
+        let (winner, tile) = match self.get_state() {
+            GameState::Player1Won => (Participant::Player1, CARO_TILE_STATE_CARO_TILE_PLAYER1),
+            GameState::Player2Won => (Participant::Player2, CARO_TILE_STATE_CARO_TILE_PLAYER2),
+            _ => return Vec::new(),
+        };
+        let latest_move = match self.get_moves_history(winner).pop() {
+            Some(latest_move) => latest_move,
+            None => return Vec::new(),
+        };
+        let (length, max_blocked_ends) = match rule {
+            RuleType::TicTacToe => (3, None),
+            RuleType::FourBlockOne => (4, Some(0)),
+            RuleType::FiveBlockTwo => (5, Some(1)),
+        };
+        let height = self.get_board_height() as i64;
+        let width = self.get_board_width() as i64;
+        let tile_at = |latitude: i64, longtitude: i64| -> Option<CARO_TILE_STATE> {
+            if latitude < 0 || longtitude < 0 || latitude >= height || longtitude >= width {
+                return None;
+            }
+            Some(unsafe {caro_get_tile_state(self.gid, latitude as usize, longtitude as usize)})
+        };
+
+        for (dx, dy) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+            let mut start = (latest_move.latitude, latest_move.longtitude);
+            while tile_at(start.0 - dx, start.1 - dy) == Some(tile) {
+                start = (start.0 - dx, start.1 - dy);
+            }
+            let mut line = Vec::new();
+            let mut end = start;
+            while tile_at(end.0, end.1) == Some(tile) {
+                line.push(Coordinate {latitude: end.0, longtitude: end.1});
+                end = (end.0 + dx, end.1 + dy);
+            }
+            if line.len() < length {
+                continue;
+            }
+            // an end is blocked by the opponent's mark, not by the edge of the board
+            let blocked = |latitude, longtitude| matches!(tile_at(latitude, longtitude), Some(other) if other != tile && other != CARO_TILE_STATE_CARO_TILE_EMPTY);
+            let blocked_ends = [blocked(start.0 - dx, start.1 - dy), blocked(end.0, end.1)].iter().filter(|is_blocked| **is_blocked).count();
+            match max_blocked_ends {
+                Some(max_blocked_ends) if blocked_ends > max_blocked_ends => continue,
+                _ => return line,
+            }
+        }
+        Vec::new()
+    }
+
     pub fn get_undone_moves(&self, who: Participant) -> Vec<Coordinate> {
         let mut c_undone_moves = std::mem::MaybeUninit::<CARO_Moves_Set>::uninit();
         match who {
@@ -0,0 +1,303 @@
+use std::sync::Arc;
+
+use clap::Parser;
+use tokio::sync::{Notify, RwLock};
+use tracing::Instrument;
+
+use crate::{
+    caro_protocol,
+    client_endpoint::{self, Receiver, Requester, ResponseGetter, Sender},
+    global_state,
+    input_from_user,
+    logging,
+    make_input_action,
+    make_response_action,
+    output_to_user::{self, entities_factory, screen_entity},
+    server_response_executor,
+    user_command_executor
+};
+
+const CONNECT_ATTEMPTS: u32 = 4;
+const FIRST_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+const CONNECT_PROMPT_POS: (usize, usize) = (17, 63);
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct ClientSettings {
+    pub server_address: String,
+    // typed commands are remembered here across sessions, not at all if None
+    pub history_file: Option<std::path::PathBuf>,
+    // what the keys do in arrow mode
+    pub key_map: input_from_user::key_map::KeyMap,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            server_address: caro_protocol::SERVER_ADDRESS.to_string(),
+            history_file: caro_console::line_editor::default_history_file(),
+            key_map: input_from_user::key_map::KeyMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "caro_client", about = "Caro game client")]
+pub struct ClientArgs {
+    /// Host name or IP address of the server
+    #[arg(long, env = "CARO_SERVER_HOST")]
+    pub host: Option<String>,
+    /// TCP port the server listens on
+    #[arg(short, long, env = "CARO_SERVER_PORT")]
+    pub port: Option<u16>,
+    /// Append a log to this file, nothing is logged without it
+    #[arg(long, value_name = "FILE", env = "CARO_LOG_FILE")]
+    pub log_file: Option<std::path::PathBuf>,
+    #[arg(long, value_enum, default_value = "info")]
+    pub log_level: logging::LogLevel,
+    #[arg(long, value_enum, default_value = "pretty")]
+    pub log_format: logging::LogFormat,
+    /// Remember typed commands in this file, defaults to ~/.caro_history
+    #[arg(long, value_name = "FILE", env = "CARO_HISTORY_FILE")]
+    pub history_file: Option<std::path::PathBuf>,
+    /// Read the arrow mode key bindings from this file, defaults to ~/.caro_keys.toml if there is one
+    #[arg(long, value_name = "FILE", env = "CARO_KEY_MAP")]
+    pub key_map: Option<std::path::PathBuf>,
+}
+
+impl ClientArgs {
+    pub fn log_settings(&self) -> Option<logging::LogSettings> {
+        self.log_file.as_ref().map(|file| logging::LogSettings {
+            file: file.clone(),
+            level: self.log_level,
+            format: self.log_format,
+        })
+    }
+
+    pub fn into_settings(self) -> Result<ClientSettings, input_from_user::key_map::KeyMapError> {
+        let key_map = input_from_user::key_map::KeyMap::load(self.key_map.as_deref())?;
+        let (default_host, default_port) = caro_protocol::SERVER_ADDRESS.rsplit_once(':').unwrap();
+        let host = self.host.unwrap_or(default_host.to_string());
+        let port = match self.port {
+            Some(port) => port.to_string(),
+            None => default_port.to_string(),
+        };
+        Ok(ClientSettings {
+            server_address: format!("{}:{}", host, port),
+            history_file: self.history_file.or_else(caro_console::line_editor::default_history_file),
+            key_map,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConnectCommand {
+    Retry,
+    ChangeServer(String),
+    Exit,
+    Invalid(String),
+}
+
+impl From<&str> for ConnectCommand {
+    fn from(line: &str) -> Self {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["retry"] => ConnectCommand::Retry,
+            ["server", address] => ConnectCommand::ChangeServer(address.to_string()),
+            ["exit"] => ConnectCommand::Exit,
+            _ => ConnectCommand::Invalid(format!("Invalid command: {}", line.trim())),
+        }
+    }
+}
+
+fn show_connect_screen(entities_vec: &[Box<dyn screen_entity::ScreenEntity>], content: String) {
+    caro_console::output::clean_screen();
+    for entity in entities_vec.iter() {
+        entity.display();
+    }
+    entities_factory::EntitiesFactory::get_log_entity(content, entities_factory::ScreenType::Connect).display();
+    caro_console::input::enable_prompt_mode_at(CONNECT_PROMPT_POS.0, CONNECT_PROMPT_POS.1);
+}
+
+// keeps trying with a growing delay, then lets the user retry, pick another server or give up
+async fn connect_with_retry(server_address: &mut String) -> Option<(Receiver, Sender)> {
+    let entities_vec = entities_factory::EntitiesFactory::get_screen_entities(entities_factory::ScreenType::Connect);
+    loop {
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut attempt = 1;
+        let error = loop {
+            match client_endpoint::connect_to(server_address).await {
+                Ok(endpoints) => return Some(endpoints),
+                Err(err) if attempt >= CONNECT_ATTEMPTS => break err,
+                Err(err) => {
+                    tracing::debug!(%server_address, attempt, %err, "cannot connect yet");
+                    show_connect_screen(&entities_vec,
+                        format!("Connecting to {} ({}/{})", server_address, attempt + 1, CONNECT_ATTEMPTS));
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                },
+            }
+        };
+        tracing::warn!(%server_address, %error, "cannot connect");
+        show_connect_screen(&entities_vec, format!("Cannot connect to {}: {}", server_address, error));
+
+        loop {
+            let line = match caro_console::input::get_user_input().await {
+                caro_console::input::InputType::Text(line) => line,
+                caro_console::input::InputType::Key(caro_console::input::KeyType::Exit) => return None,
+                caro_console::input::InputType::Key(_) => continue,
+            };
+            match ConnectCommand::from(line.as_str()) {
+                ConnectCommand::Retry => break,
+                ConnectCommand::ChangeServer(address) => {
+                    *server_address = address;
+                    break;
+                },
+                ConnectCommand::Exit => return None,
+                ConnectCommand::Invalid(reason) => {
+                    show_connect_screen(&entities_vec, reason);
+                },
+            }
+        }
+        show_connect_screen(&entities_vec, format!("Connecting to {}", server_address));
+    }
+}
+
+// the game screen stays up meanwhile, so this never asks the user anything
+async fn reconnect(server_address: &str) -> (Receiver, Sender) {
+    loop {
+        match client_endpoint::connect_to(server_address).await {
+            Ok(endpoints) => return endpoints,
+            Err(err) => {
+                tracing::debug!(%err, "cannot reconnect yet");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            },
+        }
+    }
+}
+
+pub async fn run(settings: ClientSettings) {
+    let global_state = Arc::new(RwLock::new(global_state::GlobalState::new()));
+    caro_console::input::set_history_file(settings.history_file);
+    let completion_state = global_state.clone();
+    caro_console::input::set_completer(Some(Arc::new(move |line: &str| {
+        // the completer runs on the input task, a state being written right now just offers nothing
+        match completion_state.try_read() {
+            Ok(global_state) => input_from_user::completion::complete(line, global_state.get_player_state(), &global_state.get_listed_rooms()),
+            Err(_) => Vec::new(),
+        }
+    })));
+
+    let mut server_address = settings.server_address;
+    let connected = tokio::select! {
+        endpoints = connect_with_retry(&mut server_address) => endpoints,
+        _ = tokio::signal::ctrl_c() => None,
+    };
+    let Some((receiver, sender)) = connected else {
+        caro_console::input::set_completer(None);
+        caro_console::output::clean_screen();
+        caro_console::output::restore_terminal();
+        return;
+    };
+    global_state.write().await.set_server_address(server_address.clone());
+    let span = tracing::info_span!("connection", server = %server_address);
+    span.in_scope(|| tracing::info!("connected"));
+
+    let requester = Arc::new(RwLock::new(Requester::new(sender)));
+    let response_getter = Arc::new(RwLock::new(ResponseGetter::new(receiver)));
+
+    global_state.write().await.set_connection_state(caro_protocol::ConnectState::Connected);
+
+    let screen_manager = Arc::new(RwLock::new(output_to_user::ScreenManager::new(global_state.clone())));
+
+    let response_executor = Arc::new(RwLock::new(server_response_executor::ResponseExecutor::new(global_state.clone(), screen_manager.clone(), requester.clone())));
+    let exit_requested = Arc::new(Notify::new());
+    let command_executor = Arc::new(RwLock::new(user_command_executor::CommandExecutor::new(global_state.clone(), screen_manager.clone(), requester.clone(), exit_requested.clone())));
+
+    screen_manager.write().await.clean();
+    screen_manager.write().await.update().await;
+    screen_manager.write().await.enable_prompt_mode().await;
+
+    let response_executor_clone = response_executor.clone();
+    let response_action = make_response_action!(move |msg: caro_protocol::MessagePacket| {
+        let response_executor = response_executor_clone.clone();
+        let future = async move {
+            tracing::trace!(code = ?msg.code(), "received");
+            if let caro_protocol::GenericCode::Server(code) = msg.code() {
+                response_executor.write().await.execute_response(msg.id(), code).await;
+            }
+        };
+        Box::pin(future) as futures::future::BoxFuture<'static, ()>
+    });
+    response_getter.write().await.set_action_on_response(response_action.clone());
+
+    let mut response_handler = ResponseGetter::handling_response(response_getter).instrument(span.clone()).await;
+
+    let input_reader = input_from_user::get_input_reader();
+    let command_getter = Arc::new(RwLock::new(input_from_user::CommandGetter::new(input_reader)));
+    command_getter.write().await.set_key_map(settings.key_map);
+
+    let command_executor_clone = command_executor.clone();
+    command_getter.write().await.set_action_on_input(make_input_action!(move |cmd: input_from_user::UserCommand| {
+        let command_executor = command_executor_clone.clone();
+        let future = async move {
+            command_executor.write().await.execute_command(cmd).await;
+        };
+        Box::pin(future) as futures::future::BoxFuture<'static, ()>
+    }));
+
+    let input_handler = input_from_user::CommandGetter::handling_input(command_getter).await;
+
+    loop {
+        // the response handler only finishes once the server closes the connection
+        tokio::select! {
+            _ = &mut response_handler => {},
+            _ = exit_requested.notified() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        global_state.write().await.set_connection_state(caro_protocol::ConnectState::Disconnected);
+        screen_manager.write().await.update().await;
+        screen_manager.write().await.log("Lost connection to the server, reconnecting".to_string()).await;
+        span.in_scope(|| tracing::warn!("lost connection"));
+
+        // read before the new connection hands out a token of its own
+        let session_token = global_state.read().await.get_session_token();
+        let (receiver, sender) = tokio::select! {
+            endpoints = reconnect(&server_address) => endpoints,
+            _ = exit_requested.notified() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        *requester.write().await = Requester::new(sender);
+        global_state.write().await.clear_pending_moves();
+        global_state.write().await.clear_game_context();
+        let response_getter = Arc::new(RwLock::new(ResponseGetter::new(receiver)));
+        response_getter.write().await.set_action_on_response(response_action.clone());
+
+        // the server only knows the new connection as a fresh player until the session is resumed
+        global_state.write().await.set_player_state(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected));
+        screen_manager.write().await.clean();
+        screen_manager.write().await.update().await;
+        screen_manager.write().await.log("Reconnected".to_string()).await;
+        screen_manager.write().await.enable_prompt_mode().await;
+        response_handler = ResponseGetter::handling_response(response_getter).instrument(span.clone()).await;
+        span.in_scope(|| tracing::info!(resuming = session_token.is_some(), "reconnected"));
+
+        if let Some(session_token) = session_token {
+            let code = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::ResumeSession(session_token));
+            requester.write().await.send_request(caro_protocol::MessagePacket::new_player_packet(code)).await;
+        }
+    }
+
+    // the seat is given up right away instead of being held for a reconnect
+    if global_state.read().await.get_connection_state() == caro_protocol::ConnectState::Connected {
+        let code = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerExitApplication);
+        requester.write().await.send_request(caro_protocol::MessagePacket::new_player_packet(code)).await;
+    }
+    span.in_scope(|| tracing::info!("exited"));
+    input_from_user::CommandGetter::stop_handling_input(input_handler);
+    response_handler.abort();
+    caro_console::input::set_completer(None);
+    caro_console::output::clean_screen();
+    caro_console::output::restore_terminal();
+}
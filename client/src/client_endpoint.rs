@@ -5,8 +5,9 @@ use tokio::net::TcpStream;
 use futures::future::BoxFuture;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
+use tracing::Instrument;
 
-use crate::caro_protocol::{self, ToMessagePacket};
+use crate::caro_protocol;
 
 pub type HandleAction = Arc<tokio::sync::RwLock<dyn FnMut(caro_protocol::MessagePacket) -> BoxFuture<'static, ()> + Send + Sync + 'static>>;
 
@@ -25,47 +26,67 @@ pub struct Sender {
 
 impl Sender {
     async fn send(&mut self, message: Vec<u8>) {
-        self.sender.write_all(&message).await.unwrap();
-        self.sender.flush().await.unwrap();
+        // a broken connection is noticed by the receiving side
+        if self.sender.write_all(&message).await.is_ok() {
+            let _ = self.sender.flush().await;
+        }
     }
 }
 
 pub struct Receiver {
     receiver: OwnedReadHalf,
     buffer: [u8; 1024],
+    frames: caro_protocol::FrameBuffer,
 }
 
 impl Receiver {
-    async fn receive(&mut self) -> (Vec<u8>, usize) {
-        let bytesread = self.receiver.read(&mut self.buffer).await.unwrap();
-        (self.buffer[..bytesread].to_vec(), bytesread)
+    // the bytes are kept until they make up whole messages
+    async fn receive(&mut self) -> usize {
+        // a reset connection is treated the same as a closed one
+        let bytesread = self.receiver.read(&mut self.buffer).await.unwrap_or(0);
+        self.frames.push(&self.buffer[..bytesread]);
+        bytesread
     }
 }
 
-pub async fn connect_to(dest: &str) -> (Receiver, Sender) {
-    let (receiver, sender) = TcpStream::connect(dest).await.unwrap().into_split();
-    (
+pub const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+pub async fn connect_to(dest: &str) -> std::io::Result<(Receiver, Sender)> {
+    let stream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(dest)).await {
+        Ok(stream) => stream?,
+        Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out")),
+    };
+    let (receiver, sender) = stream.into_split();
+    Ok((
         Receiver {
             receiver,
             buffer: [0; 1024],
+            frames: caro_protocol::FrameBuffer::new(),
         },
         Sender {sender}
-    )
+    ))
 }
 
 pub struct Requester {
     sender: Sender,
+    next_id: caro_protocol::RequestId,
 }
 
 impl Requester {
     pub fn new(sender: Sender) -> Self {
         Self {
             sender,
+            next_id: 1,
         }
     }
 
-    pub async fn send_request(&mut self, message: caro_protocol::MessagePacket) {
-        self.sender.send(message.to_serial()).await;
+    // the server answers with the same id, so the response can be matched to its request
+    pub async fn send_request(&mut self, message: caro_protocol::MessagePacket) -> caro_protocol::RequestId {
+        let id = self.next_id;
+        self.next_id += 1;
+        tracing::debug!(id, code = ?message.code(), "sending");
+        self.sender.send(message.with_id(Some(id)).to_serial()).await;
+        id
     }
 }
 
@@ -100,15 +121,34 @@ impl ResponseGetter {
         tokio::spawn(
             async move {
                 let target = target_clone.clone();
-                loop {
-                    let (msg, bytesread) = target.write().await.receiver.receive().await;
+                'receiving: loop {
+                    let bytesread = target.write().await.receiver.receive().await;
                     if bytesread == 0 {
                         break;
                     }
-                    let msg = msg.to_message_packet();
-                    tokio::spawn(target.read().await.action.write().await(msg));
+                    loop {
+                        let frame = target.write().await.receiver.frames.next_frame();
+                        let msg = match frame {
+                            None => break,
+                            Some(Ok(frame)) => match caro_protocol::MessagePacket::from_serial(&frame) {
+                                Ok(msg) => msg,
+                                Err(err) => {
+                                    tracing::warn!(%err, "cannot decode message from server");
+                                    continue;
+                                },
+                            },
+                            Some(Err(dropped)) => {
+                                tracing::warn!(dropped, "server sent a message without an end");
+                                continue 'receiving;
+                            },
+                        };
+                        // handled one after another, so the responses apply in the order they were sent
+                        let action = target.read().await.action.clone();
+                        let handling = action.write().await(msg);
+                        handling.await;
+                    }
                 }
-            }
+            }.instrument(tracing::Span::current())
         )
     }
 
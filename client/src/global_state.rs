@@ -1,8 +1,21 @@
+use std::collections::HashMap;
+
 use crate::caro_protocol;
 
 pub struct GlobalState {
     player_state: caro_protocol::PlayerState,
     current_rid: caro_protocol::RoomId,
+    server_address: String,
+    // kept across reconnects so the server can give the seat back
+    session_token: Option<caro_protocol::SessionToken>,
+    // moves sent but not answered yet, by the id of their request
+    pending_moves: HashMap<caro_protocol::RequestId, caro_protocol::Coordinate>,
+    // the game as far as the deltas went, None until the full context came
+    game_context: Option<caro_protocol::GameContext>,
+    // the rooms of the last listing, offered when completing a join
+    listed_rooms: Vec<caro_protocol::RoomId>,
+    // chat lines are dropped instead of logged while set
+    chat_hidden: bool,
 }
 
 impl GlobalState {
@@ -10,6 +23,12 @@ impl GlobalState {
         Self {
             player_state: caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Disconnected),
             current_rid: -1,
+            server_address: caro_protocol::SERVER_ADDRESS.to_string(),
+            session_token: None,
+            pending_moves: HashMap::new(),
+            game_context: None,
+            listed_rooms: Vec::new(),
+            chat_hidden: false,
         }
     }
 
@@ -56,4 +75,65 @@ impl GlobalState {
     pub fn get_current_rid(&mut self) -> caro_protocol::RoomId {
         self.current_rid
     }
-}
\ No newline at end of file
+
+    pub fn set_server_address(&mut self, server_address: String) {
+        self.server_address = server_address;
+    }
+
+    pub fn get_server_address(&self) -> String {
+        self.server_address.clone()
+    }
+
+    pub fn set_session_token(&mut self, session_token: caro_protocol::SessionToken) {
+        self.session_token = Some(session_token);
+    }
+
+    pub fn get_session_token(&self) -> Option<caro_protocol::SessionToken> {
+        self.session_token.clone()
+    }
+
+    pub fn clear_session_token(&mut self) {
+        self.session_token = None;
+    }
+
+    pub fn add_pending_move(&mut self, request_id: caro_protocol::RequestId, coor: caro_protocol::Coordinate) {
+        self.pending_moves.insert(request_id, coor);
+    }
+
+    pub fn take_pending_move(&mut self, request_id: caro_protocol::RequestId) -> Option<caro_protocol::Coordinate> {
+        self.pending_moves.remove(&request_id)
+    }
+
+    // the ids start over with every connection
+    pub fn clear_pending_moves(&mut self) {
+        self.pending_moves.clear();
+    }
+
+    pub fn set_game_context(&mut self, game_context: caro_protocol::GameContext) {
+        self.game_context = Some(game_context);
+    }
+
+    pub fn get_game_context(&self) -> Option<caro_protocol::GameContext> {
+        self.game_context.clone()
+    }
+
+    pub fn clear_game_context(&mut self) {
+        self.game_context = None;
+    }
+
+    pub fn set_listed_rooms(&mut self, listed_rooms: Vec<caro_protocol::RoomId>) {
+        self.listed_rooms = listed_rooms;
+    }
+
+    pub fn get_listed_rooms(&self) -> Vec<caro_protocol::RoomId> {
+        self.listed_rooms.clone()
+    }
+
+    pub fn set_chat_hidden(&mut self, chat_hidden: bool) {
+        self.chat_hidden = chat_hidden;
+    }
+
+    pub fn is_chat_hidden(&self) -> bool {
+        self.chat_hidden
+    }
+}
@@ -2,21 +2,29 @@ use std::sync::Arc;
 
 use futures::future::BoxFuture;
 use tokio::{sync::RwLock, task::JoinHandle};
-use crate::{caro_protocol, input_from_user::command_parser::ToUserCommand};
+use crate::caro_protocol;
 use caro_console;
 
 pub mod command_parser;
+pub mod completion;
+pub mod key_map;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GeneralCommand {
     ExitApplication,
+    // the name of the command to explain, every command if none
+    Help(Option<&'static str>),
+    Say(String),
+    // a typed line that is not a command, the error says why
+    ParseFailed(command_parser::ParseError),
     Invalid,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LoggedCommand {
-    RequestNewRoom(caro_protocol::GameRule),
-    JoinRoom(caro_protocol::RoomId),
+    RequestNewRoom(caro_protocol::GameRule, caro_protocol::RoomOptions),
+    JoinRoom(caro_protocol::RoomKey, Option<String>),
+    ListRooms,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,10 +43,16 @@ pub enum InGameCommand {
     Undo,
     Redo,
     SwitchInputMode,
+    Hint,
+    Rematch,
     LeaveRoom,
+    // moves the board so the cursor is in the middle of it
+    CenterView,
+    JumpToLastMove,
+    ToggleChat,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UserCommand {
     General(GeneralCommand),
     Logged(LoggedCommand),
@@ -75,6 +89,7 @@ impl InputReader {
 
 pub struct CommandGetter {
     input_reader: InputReader,
+    key_map: key_map::KeyMap,
     action: HandleAction,
 }
 
@@ -87,10 +102,15 @@ impl CommandGetter {
         });
         Self {
             input_reader,
+            key_map: key_map::KeyMap::default(),
             action,
         }
     }
 
+    pub fn set_key_map(&mut self, key_map: key_map::KeyMap) {
+        self.key_map = key_map;
+    }
+
     pub fn set_action_on_input(&mut self, action: HandleAction) {
         self.action = action;
     }
@@ -106,7 +126,7 @@ impl CommandGetter {
                 let target = target_clone.clone();
                 loop {
                     let input_line = target.write().await.input_reader.get_input().await;
-                    let cmd = input_line.to_user_command();
+                    let cmd = target.read().await.key_map.to_user_command(input_line);
                     // tokio::spawn(target.read().await.action.write().await(cmd));
                     target.read().await.action.write().await(cmd).await;
                 }
@@ -1,54 +1,262 @@
-use crate::{caro_protocol, input_from_user::{GeneralCommand, InGameCommand, LoggedCommand, UserCommand}};
+use std::fmt;
+
+use crate::{caro_protocol, input_from_user::{key_map, GeneralCommand, InGameCommand, InRoomCommand, LoggedCommand, UserCommand}};
 
 pub trait ToUserCommand {
     fn to_user_command(self) -> UserCommand;
 }
 
+// why a typed line is not a command, shown in the log box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownCommand(String),
+    // command, the argument it is missing
+    MissingArgument(&'static str, &'static str),
+    // command, what was given, what was expected instead
+    InvalidArgument(&'static str, String, &'static str),
+    UnexpectedArgument(&'static str, String),
+    UnknownOption(&'static str, String),
+    // command, why it cannot be done that way
+    Unsupported(&'static str, &'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownCommand(word) => write!(f, "Unknown command '{}', type help", word),
+            ParseError::MissingArgument(command, argument) => write!(f, "{}: missing {}, see help {}", command, argument, command),
+            ParseError::InvalidArgument(command, value, expected) => write!(f, "{}: '{}' is not {}", command, value, expected),
+            ParseError::UnexpectedArgument(command, value) => write!(f, "{}: unexpected '{}'", command, value),
+            ParseError::UnknownOption(command, option) => write!(f, "{}: unknown option '{}'", command, option),
+            ParseError::Unsupported(command, reason) => write!(f, "{}: {}", command, reason),
+        }
+    }
+}
+
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub summary: &'static str,
+}
+
+pub const COMMANDS: &[CommandHelp] = &[
+    CommandHelp { name: "help", aliases: &["?"], usage: "help [command]", summary: "list the commands or explain one" },
+    CommandHelp { name: "exit", aliases: &["quit"], usage: "exit", summary: "leave the application" },
+    CommandHelp { name: "rooms", aliases: &["ls"], usage: "rooms", summary: "list the open rooms" },
+    CommandHelp {
+        name: "mkroom",
+        aliases: &["create"],
+        usage: "mkroom <3|4|5> [--size N] [--private] [--password PW]",
+        summary: "create a room, caro4 and caro5 also name the rules",
+    },
+    CommandHelp { name: "join", aliases: &["cdroom"], usage: "join <room id|code> [password]", summary: "join a room" },
+    CommandHelp { name: "leave", aliases: &["lobby", "forfeit", "resign"], usage: "leave", summary: "leave the room, a game in progress is lost" },
+    CommandHelp { name: "move", aliases: &["mv"], usage: "move <h8|lat long>", summary: "place your mark, letters count columns from a" },
+    CommandHelp { name: "undo", aliases: &[], usage: "undo", summary: "take your last move back" },
+    CommandHelp { name: "redo", aliases: &[], usage: "redo", summary: "play the move you took back again" },
+    CommandHelp { name: "rematch", aliases: &[], usage: "rematch", summary: "ask to play again once the game is over" },
+    CommandHelp { name: "say", aliases: &["chat"], usage: "say <text>", summary: "talk to the players in your room" },
+    CommandHelp { name: "hint", aliases: &[], usage: "hint", summary: "ask for a suggested move" },
+    CommandHelp { name: "switch", aliases: &[], usage: "switch", summary: "toggle arrow key mode, Esc does the same" },
+];
+
+pub fn find_command(word: &str) -> Option<&'static CommandHelp> {
+    let word = word.to_lowercase();
+    COMMANDS.iter().find(|command| command.name == word || command.aliases.contains(&word.as_str()))
+}
+
+// what help prints, for every command or just the named one
+pub fn help_text(topic: Option<&str>) -> String {
+    match topic.and_then(find_command) {
+        Some(command) if command.aliases.is_empty() => format!("{} : {}", command.usage, command.summary),
+        Some(command) => format!("{} : {} (also {})", command.usage, command.summary, command.aliases.join(", ")),
+        None => {
+            let names: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+            format!("Commands: {}. Type help <command> for more", names.join(", "))
+        },
+    }
+}
+
+pub fn parse_command(line: &str) -> Result<UserCommand, ParseError> {
+    let line = line.trim();
+    let Some(word) = line.split_whitespace().next() else {
+        // nothing typed, nothing to complain about
+        return Ok(UserCommand::General(GeneralCommand::Invalid));
+    };
+    let Some(command) = find_command(word) else {
+        return Err(ParseError::UnknownCommand(word.to_string()));
+    };
+    let rest = line[word.len()..].trim_start();
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let name = command.name;
+    match name {
+        "help" => {
+            let topic = match args.as_slice() {
+                [] => None,
+                [topic] => match find_command(topic) {
+                    Some(command) => Some(command.name),
+                    None => return Err(ParseError::UnknownCommand(topic.to_string())),
+                },
+                [_, extra, ..] => return Err(ParseError::UnexpectedArgument(name, extra.to_string())),
+            };
+            Ok(UserCommand::General(GeneralCommand::Help(topic)))
+        },
+        "exit" => no_arguments(name, &args, UserCommand::General(GeneralCommand::ExitApplication)),
+        "rooms" => no_arguments(name, &args, UserCommand::Logged(LoggedCommand::ListRooms)),
+        "mkroom" => parse_mkroom(&args),
+        "join" => parse_join(&args),
+        "leave" => no_arguments(name, &args, UserCommand::InRoom(InRoomCommand::LeaveRoom)),
+        "move" => parse_move(&args).map(|coordinate| UserCommand::InGame(InGameCommand::Move(coordinate))),
+        "undo" => no_arguments(name, &args, UserCommand::InGame(InGameCommand::Undo)),
+        "redo" => no_arguments(name, &args, UserCommand::InGame(InGameCommand::Redo)),
+        "rematch" => no_arguments(name, &args, UserCommand::InGame(InGameCommand::Rematch)),
+        // the text is sent as typed, only the ends are trimmed
+        "say" if rest.is_empty() => Err(ParseError::MissingArgument(name, "<text>")),
+        "say" => Ok(UserCommand::General(GeneralCommand::Say(rest.to_string()))),
+        "hint" => no_arguments(name, &args, UserCommand::InGame(InGameCommand::Hint)),
+        "switch" => no_arguments(name, &args, UserCommand::InGame(InGameCommand::SwitchInputMode)),
+        _ => Err(ParseError::UnknownCommand(word.to_string())),
+    }
+}
+
+fn no_arguments(name: &'static str, args: &[&str], command: UserCommand) -> Result<UserCommand, ParseError> {
+    match args.first() {
+        Some(extra) => Err(ParseError::UnexpectedArgument(name, extra.to_string())),
+        None => Ok(command),
+    }
+}
+
+fn parse_rule(word: &str) -> Option<caro_protocol::GameRule> {
+    match &*word.to_lowercase() {
+        "3" | "tictactoe" | "ttt" => Some(caro_protocol::GameRule::TicTacToe),
+        "4" | "caro4" => Some(caro_protocol::GameRule::FourBlockOne),
+        "5" | "caro5" | "caro" => Some(caro_protocol::GameRule::FiveBlockTwo),
+        _ => None,
+    }
+}
+
+// boards are square, 15 and 15x15 are the same size
+fn parse_board_size(word: &str) -> Result<usize, ParseError> {
+    let invalid = || ParseError::InvalidArgument("mkroom", word.to_string(), "a board size like 15 or 15x15");
+    let (height, width) = match word.to_lowercase().split_once('x') {
+        Some((height, width)) => (height.parse::<usize>().map_err(|_| invalid())?, width.parse::<usize>().map_err(|_| invalid())?),
+        None => {
+            let size = word.parse::<usize>().map_err(|_| invalid())?;
+            (size, size)
+        },
+    };
+    if height != width {
+        return Err(ParseError::Unsupported("mkroom", "boards are always square"));
+    }
+    Ok(height)
+}
+
+// mkroom <rule> [--size N|NxN] [--private] [--password PW], options in any order
+fn parse_mkroom(args: &[&str]) -> Result<UserCommand, ParseError> {
+    let mut rule = None;
+    let mut options = caro_protocol::RoomOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // --size=19 reads the same as --size 19
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) if arg.starts_with("--") => (option, Some(value.to_string())),
+            _ => (*arg, None),
+        };
+        let mut value = |argument: &'static str| match inline_value.clone() {
+            Some(value) => Ok(value),
+            None => args.next().map(|value| value.to_string()).ok_or(ParseError::MissingArgument("mkroom", argument)),
+        };
+        match option {
+            "--size" | "-s" => options.board_size = Some(parse_board_size(&value("a size after --size")?)?),
+            "--password" | "-p" => options.password = Some(value("a password after --password")?),
+            "--private" if inline_value.is_none() => options.private = true,
+            _ if option.starts_with('-') => return Err(ParseError::UnknownOption("mkroom", arg.to_string())),
+            _ if rule.is_none() => match parse_rule(arg) {
+                Some(parsed) => rule = Some(parsed),
+                None => return Err(ParseError::InvalidArgument("mkroom", arg.to_string(), "a rule, try 3, 4 or 5")),
+            },
+            _ => return Err(ParseError::UnexpectedArgument("mkroom", arg.to_string())),
+        }
+    }
+    match rule {
+        None => Err(ParseError::MissingArgument("mkroom", "<3|4|5>")),
+        Some(caro_protocol::GameRule::TicTacToe) if options.board_size.is_some() => {
+            Err(ParseError::Unsupported("mkroom", "tic tac toe is always played on 3x3"))
+        },
+        Some(rule) => Ok(UserCommand::Logged(LoggedCommand::RequestNewRoom(rule, options))),
+    }
+}
+
+// join <room id|join code> [password]
+fn parse_join(args: &[&str]) -> Result<UserCommand, ParseError> {
+    let (key, password) = match args {
+        [] => return Err(ParseError::MissingArgument("join", "<room id|code>")),
+        [key] => (*key, None),
+        [key, password] => (*key, Some(password.to_string())),
+        [_, _, extra, ..] => return Err(ParseError::UnexpectedArgument("join", extra.to_string())),
+    };
+    let key = match key.parse() {
+        Ok(rid) => caro_protocol::RoomKey::Id(rid),
+        Err(_) if key.chars().all(|c| c.is_ascii_alphanumeric()) => caro_protocol::RoomKey::Code(key.to_uppercase()),
+        Err(_) => return Err(ParseError::InvalidArgument("join", key.to_string(), "a room id or join code")),
+    };
+    Ok(UserCommand::Logged(LoggedCommand::JoinRoom(key, password)))
+}
+
+// columns a..z then aa, ab.. like a spreadsheet, rows as the board numbers them
+fn parse_column(letters: &str) -> Option<caro_protocol::Longtitude> {
+    let mut column: caro_protocol::Longtitude = 0;
+    for letter in letters.chars() {
+        column = column.checked_mul(26)?.checked_add(letter as caro_protocol::Longtitude - 'a' as caro_protocol::Longtitude + 1)?;
+    }
+    Some(column - 1)
+}
+
+fn parse_index(word: &str) -> Option<i64> {
+    word.parse::<i64>().ok().filter(|index| *index >= 0)
+}
+
+// move h8, move 8 7 and move 8,7 all name row 8, column 7
+fn parse_move(args: &[&str]) -> Result<caro_protocol::Coordinate, ParseError> {
+    let joined = args.join(" ");
+    let invalid = || ParseError::InvalidArgument("move", joined.clone(), "a tile like h8 or 8 7");
+    let (latitude, longtitude) = match args {
+        [] => return Err(ParseError::MissingArgument("move", "<h8|lat long>")),
+        [latitude, longtitude] => (*latitude, *longtitude),
+        [tile] => match tile.split_once(',') {
+            Some((latitude, longtitude)) => (latitude, longtitude),
+            None => {
+                let tile = tile.to_lowercase();
+                let split = tile.find(|c: char| !c.is_ascii_lowercase()).ok_or_else(invalid)?;
+                let (letters, digits) = tile.split_at(split);
+                if letters.is_empty() {
+                    return Err(invalid());
+                }
+                let longtitude = parse_column(letters).ok_or_else(invalid)?;
+                let latitude = parse_index(digits).ok_or_else(invalid)?;
+                return Ok((latitude, longtitude));
+            },
+        },
+        [_, _, extra, ..] => return Err(ParseError::UnexpectedArgument("move", extra.to_string())),
+    };
+    match (parse_index(latitude.trim()), parse_index(longtitude.trim())) {
+        (Some(latitude), Some(longtitude)) => Ok((latitude, longtitude)),
+        _ => Err(invalid()),
+    }
+}
+
 impl ToUserCommand for caro_console::input::InputType {
     fn to_user_command(self) -> UserCommand {
         match self {
             caro_console::input::InputType::Text(line) => {
-                let words: Vec<String>
-                = line.to_string()
-                    .trim()
-                    .split_whitespace()
-                    .map(|s| s.to_string())
-                    .collect();
-                match &*words[0] {
-                    "switch" => {
-                        UserCommand::InGame(InGameCommand::SwitchInputMode)
-                    },
-                    "mkroom" => {
-                        match &*words[1] {
-                            "3" => UserCommand::Logged(LoggedCommand::RequestNewRoom(caro_protocol::GameRule::TicTacToe)),
-                            "4" => UserCommand::Logged(LoggedCommand::RequestNewRoom(caro_protocol::GameRule::FourBlockOne)),
-                            "5" => UserCommand::Logged(LoggedCommand::RequestNewRoom(caro_protocol::GameRule::FiveBlockTwo)),
-                            _ => UserCommand::General(GeneralCommand::Invalid),
-                        }
-                    },
-                    "cdroom" => {
-                        let rid = words[1].parse().unwrap();
-                        UserCommand::Logged(LoggedCommand::JoinRoom(rid))
-                    },
-                    "move" => {
-                        let latitude = words[1].parse().unwrap();
-                        let longtitude = words[2].parse().unwrap();
-                        UserCommand::InGame(InGameCommand::Move((latitude, longtitude)))
-                    },
-                    _ => UserCommand::General(GeneralCommand::Invalid),
-                }
-            },
-            caro_console::input::InputType::Key(key) => {
-                match key {
-                    caro_console::input::KeyType::Up => UserCommand::InGame(InGameCommand::Up),
-                    caro_console::input::KeyType::Down => UserCommand::InGame(InGameCommand::Down),
-                    caro_console::input::KeyType::Left => UserCommand::InGame(InGameCommand::Left),
-                    caro_console::input::KeyType::Right => UserCommand::InGame(InGameCommand::Right),
-                    caro_console::input::KeyType::Enter => UserCommand::InGame(InGameCommand::Enter),
-                    caro_console::input::KeyType::Esc => UserCommand::InGame(InGameCommand::SwitchInputMode),
-                    caro_console::input::KeyType::Invalid => UserCommand::General(GeneralCommand::Invalid),
+                match parse_command(&line) {
+                    Ok(command) => command,
+                    Err(error) => UserCommand::General(GeneralCommand::ParseFailed(error)),
                 }
             },
+            // keys are read as the default preset has them, see KeyMap for other bindings
+            caro_console::input::InputType::Key(key) => key_map::KeyMap::default().get_command(key),
         }
     }
-}
\ No newline at end of file
+}
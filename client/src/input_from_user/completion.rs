@@ -0,0 +1,42 @@
+use crate::{caro_protocol, input_from_user::command_parser};
+
+const RULES: [&str; 6] = ["3", "4", "5", "tictactoe", "caro4", "caro5"];
+const MKROOM_OPTIONS: [&str; 3] = ["--size", "--private", "--password"];
+
+// what can be typed in each state, help and exit always can
+fn commands_in(player_state: caro_protocol::PlayerState) -> &'static [&'static str] {
+    match player_state {
+        caro_protocol::PlayerState::Logged(_) => &["help", "exit", "rooms", "mkroom", "join"],
+        caro_protocol::PlayerState::InRoom(_) => &["help", "exit", "leave", "say"],
+        caro_protocol::PlayerState::InGame(_) => {
+            &["help", "exit", "move", "undo", "redo", "rematch", "leave", "say", "hint", "switch"]
+        },
+    }
+}
+
+// every word the line up to the cursor may continue with, the line editor picks those matching what was typed
+pub fn complete(line: &str, player_state: caro_protocol::PlayerState, listed_rooms: &[caro_protocol::RoomId]) -> Vec<String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    // a line ending in a space starts a new word
+    let position = match line.ends_with(char::is_whitespace) {
+        true => words.len(),
+        false => words.len().saturating_sub(1),
+    };
+    if position == 0 {
+        return commands_in(player_state).iter()
+            .filter_map(|name| command_parser::find_command(name))
+            .flat_map(|command| std::iter::once(command.name).chain(command.aliases.iter().copied()))
+            .map(|name| name.to_string())
+            .collect();
+    }
+    let Some(command) = command_parser::find_command(words[0]) else {
+        return Vec::new();
+    };
+    match (command.name, position) {
+        ("help", 1) => command_parser::COMMANDS.iter().map(|command| command.name.to_string()).collect(),
+        ("join", 1) => listed_rooms.iter().map(|rid| rid.to_string()).collect(),
+        ("mkroom", 1) => RULES.iter().map(|rule| rule.to_string()).collect(),
+        ("mkroom", _) => MKROOM_OPTIONS.iter().map(|option| option.to_string()).collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use caro_console::input::{InputType, KeyType};
+
+use crate::input_from_user::{command_parser::ToUserCommand, GeneralCommand, InGameCommand, UserCommand};
+
+// what a binding may be set to in the file, none removes the binding of the preset
+pub const COMMAND_NAMES: &[(&str, InGameCommand)] = &[
+    ("up", InGameCommand::Up),
+    ("down", InGameCommand::Down),
+    ("left", InGameCommand::Left),
+    ("right", InGameCommand::Right),
+    ("place", InGameCommand::Enter),
+    ("undo", InGameCommand::Undo),
+    ("redo", InGameCommand::Redo),
+    ("switch", InGameCommand::SwitchInputMode),
+    ("hint", InGameCommand::Hint),
+    ("rematch", InGameCommand::Rematch),
+    ("leave", InGameCommand::LeaveRoom),
+    ("center", InGameCommand::CenterView),
+    ("last-move", InGameCommand::JumpToLastMove),
+    ("chat", InGameCommand::ToggleChat),
+];
+
+// the keys every preset starts from
+const COMMON_BINDINGS: &[(KeyType, InGameCommand)] = &[
+    (KeyType::Up, InGameCommand::Up),
+    (KeyType::Down, InGameCommand::Down),
+    (KeyType::Left, InGameCommand::Left),
+    (KeyType::Right, InGameCommand::Right),
+    (KeyType::Enter, InGameCommand::Enter),
+    (KeyType::Char('?'), InGameCommand::Hint),
+    (KeyType::Char('Q'), InGameCommand::LeaveRoom),
+];
+
+const ARROWS_BINDINGS: &[(KeyType, InGameCommand)] = &[
+    (KeyType::Char('u'), InGameCommand::Undo),
+    (KeyType::Char('r'), InGameCommand::Redo),
+    (KeyType::Char('c'), InGameCommand::CenterView),
+    (KeyType::Char('l'), InGameCommand::JumpToLastMove),
+    (KeyType::Char('t'), InGameCommand::ToggleChat),
+];
+
+const WASD_BINDINGS: &[(KeyType, InGameCommand)] = &[
+    (KeyType::Char('w'), InGameCommand::Up),
+    (KeyType::Char('a'), InGameCommand::Left),
+    (KeyType::Char('s'), InGameCommand::Down),
+    (KeyType::Char('d'), InGameCommand::Right),
+    (KeyType::Char(' '), InGameCommand::Enter),
+    (KeyType::Char('z'), InGameCommand::Undo),
+    (KeyType::Char('x'), InGameCommand::Redo),
+    (KeyType::Char('c'), InGameCommand::CenterView),
+    (KeyType::Char('e'), InGameCommand::JumpToLastMove),
+    (KeyType::Char('t'), InGameCommand::ToggleChat),
+];
+
+const VIM_BINDINGS: &[(KeyType, InGameCommand)] = &[
+    (KeyType::Char('k'), InGameCommand::Up),
+    (KeyType::Char('h'), InGameCommand::Left),
+    (KeyType::Char('j'), InGameCommand::Down),
+    (KeyType::Char('l'), InGameCommand::Right),
+    (KeyType::Char(' '), InGameCommand::Enter),
+    (KeyType::Char('u'), InGameCommand::Undo),
+    (KeyType::Ctrl('r'), InGameCommand::Redo),
+    (KeyType::Char('z'), InGameCommand::CenterView),
+    (KeyType::Char('g'), InGameCommand::JumpToLastMove),
+    (KeyType::Char('t'), InGameCommand::ToggleChat),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyPreset {
+    #[default]
+    Arrows,
+    Wasd,
+    Vim,
+}
+
+// the file is a preset to start from and the keys changed on top of it:
+//   preset = "vim"
+//   [keys]
+//   x = "leave"
+//   u = "none"
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyMapFile {
+    #[serde(default)]
+    preset: KeyPreset,
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum KeyMapError {
+    Io(PathBuf, std::io::Error),
+    Parse(toml::de::Error),
+    UnknownKey(String),
+    // the key, what it was bound to
+    UnknownCommand(String, String),
+    // esc, ctrl-c and ctrl-d do the same in every mode so they cannot be bound
+    Reserved(String),
+}
+
+impl fmt::Display for KeyMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMapError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            KeyMapError::Parse(err) => write!(f, "{}", err),
+            KeyMapError::UnknownKey(key) => write!(f, "unknown key '{}'", key),
+            KeyMapError::UnknownCommand(key, command) => write!(f, "key '{}': unknown command '{}'", key, command),
+            KeyMapError::Reserved(key) => write!(f, "key '{}' cannot be bound", key),
+        }
+    }
+}
+
+impl std::error::Error for KeyMapError {}
+
+// the arrow mode keys, esc always switches back to typing and ctrl-c or ctrl-d always exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: HashMap<KeyType, InGameCommand>,
+}
+
+impl KeyMap {
+    pub fn new(preset: KeyPreset) -> Self {
+        let preset_bindings = match preset {
+            KeyPreset::Arrows => ARROWS_BINDINGS,
+            KeyPreset::Wasd => WASD_BINDINGS,
+            KeyPreset::Vim => VIM_BINDINGS,
+        };
+        Self {
+            bindings: COMMON_BINDINGS.iter().chain(preset_bindings.iter()).copied().collect(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, KeyMapError> {
+        let file: KeyMapFile = toml::from_str(content).map_err(KeyMapError::Parse)?;
+        let mut key_map = KeyMap::new(file.preset);
+        for (key, command) in file.keys {
+            key_map.bind(&key, &command)?;
+        }
+        Ok(key_map)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, KeyMapError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| KeyMapError::Io(path.to_path_buf(), err))?;
+        KeyMap::parse(&content)
+    }
+
+    // the given file must be there, the default one only if it was created
+    pub fn load(path: Option<&Path>) -> Result<Self, KeyMapError> {
+        match path {
+            Some(path) => KeyMap::from_file(path),
+            None => match default_key_map_file() {
+                Some(path) if path.exists() => KeyMap::from_file(&path),
+                _ => Ok(KeyMap::default()),
+            },
+        }
+    }
+
+    pub fn bind(&mut self, key: &str, command: &str) -> Result<(), KeyMapError> {
+        let key_type = parse_key(key)?;
+        if command == "none" {
+            self.bindings.remove(&key_type);
+            return Ok(());
+        }
+        let Some((_, command)) = COMMAND_NAMES.iter().find(|(name, _)| *name == command) else {
+            return Err(KeyMapError::UnknownCommand(key.to_string(), command.to_string()));
+        };
+        self.bindings.insert(key_type, *command);
+        Ok(())
+    }
+
+    pub fn get_binding(&self, key: KeyType) -> Option<InGameCommand> {
+        self.bindings.get(&key).copied()
+    }
+
+    pub fn get_command(&self, key: KeyType) -> UserCommand {
+        match key {
+            KeyType::Exit => UserCommand::General(GeneralCommand::ExitApplication),
+            KeyType::Esc => UserCommand::InGame(InGameCommand::SwitchInputMode),
+            key => match self.get_binding(key) {
+                Some(command) => UserCommand::InGame(command),
+                None => UserCommand::General(GeneralCommand::Invalid),
+            },
+        }
+    }
+
+    pub fn to_user_command(&self, input: InputType) -> UserCommand {
+        match input {
+            InputType::Key(key) => self.get_command(key),
+            text => text.to_user_command(),
+        }
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new(KeyPreset::Arrows)
+    }
+}
+
+// a single character is that key as typed, so "Q" is shift-q
+pub fn parse_key(name: &str) -> Result<KeyType, KeyMapError> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyType::Char(c));
+    }
+    let lower_name = name.to_ascii_lowercase();
+    let key_type = match lower_name.as_str() {
+        "up" => KeyType::Up,
+        "down" => KeyType::Down,
+        "left" => KeyType::Left,
+        "right" => KeyType::Right,
+        "enter" => KeyType::Enter,
+        "space" => KeyType::Char(' '),
+        "tab" => KeyType::Tab,
+        "backspace" => KeyType::Backspace,
+        "delete" => KeyType::Delete,
+        "home" => KeyType::Home,
+        "end" => KeyType::End,
+        "pageup" => KeyType::PageUp,
+        "pagedown" => KeyType::PageDown,
+        "esc" | "ctrl-c" | "ctrl-d" => return Err(KeyMapError::Reserved(name.to_string())),
+        _ => match lower_name.strip_prefix("ctrl-").map(|letter| letter.chars().collect::<Vec<char>>()).as_deref() {
+            Some([letter]) if letter.is_ascii_alphabetic() => KeyType::Ctrl(*letter),
+            _ => return Err(KeyMapError::UnknownKey(name.to_string())),
+        },
+    };
+    Ok(key_type)
+}
+
+// $HOME/.caro_keys.toml, or nothing if there is no home to keep it in
+pub fn default_key_map_file() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".caro_keys.toml"))
+}
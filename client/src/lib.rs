@@ -1,7 +1,9 @@
 pub mod caro_protocol;
+pub mod client_app;
 pub mod client_endpoint;
 pub mod global_state;
 pub mod input_from_user;
+pub mod logging;
 pub mod output_to_user;
 pub mod server_response_executor;
 pub mod user_command_executor;
\ No newline at end of file
@@ -0,0 +1,50 @@
+use std::{path::PathBuf, sync::Mutex};
+
+// the terminal belongs to the game screen, so the client only ever logs to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn to_level_filter(self) -> tracing::level_filters::LevelFilter {
+        match self {
+            LogLevel::Error => tracing::level_filters::LevelFilter::ERROR,
+            LogLevel::Warn => tracing::level_filters::LevelFilter::WARN,
+            LogLevel::Info => tracing::level_filters::LevelFilter::INFO,
+            LogLevel::Debug => tracing::level_filters::LevelFilter::DEBUG,
+            LogLevel::Trace => tracing::level_filters::LevelFilter::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    pub file: PathBuf,
+    pub level: LogLevel,
+    pub format: LogFormat,
+}
+
+// does nothing if the embedding program already installed a subscriber
+pub fn init(settings: &LogSettings) -> std::io::Result<()> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(&settings.file)?;
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(settings.level.to_level_filter())
+        .with_ansi(false)
+        .with_writer(Mutex::new(file));
+    let _already_set = match settings.format {
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).try_init(),
+    };
+    Ok(())
+}
@@ -6,6 +6,7 @@ use crate::{caro_protocol, global_state};
 
 pub mod screen_entity;
 pub mod entities_factory;
+pub mod connect_entities;
 pub mod menu_entities;
 pub mod room_entities;
 pub mod game_entities;
@@ -17,6 +18,7 @@ pub struct ScreenManager {
     room_entities_vec: Vec<Box<dyn screen_entity::ScreenEntity>>,
     game_entities_vec: Vec<Box<dyn screen_entity::ScreenEntity>>,
     board_entities: BoardManager,
+    game_result_entity: Option<Box<dyn screen_entity::ScreenEntity>>,
     log_entity: Box<dyn screen_entity::ScreenEntity>,
 }
 
@@ -32,10 +34,16 @@ impl ScreenManager {
             room_entities_vec,
             game_entities_vec,
             board_entities: BoardManager::new(),
+            game_result_entity: None,
             log_entity,
         }
     }
 
+    // local_app shows the same board with the instructions of a game against the computer
+    pub fn use_local_game_screen(&mut self) {
+        self.game_entities_vec = entities_factory::EntitiesFactory::get_screen_entities(entities_factory::ScreenType::LocalGame);
+    }
+
     pub fn clean(&self) {
         caro_console::output::clean_screen();
     }
@@ -75,6 +83,14 @@ impl ScreenManager {
         self.board_entities.get_cursor_pos()
     }
 
+    pub fn center_view(&mut self) {
+        self.board_entities.center_view();
+    }
+
+    pub fn get_last_move(&self) -> Option<caro_protocol::Coordinate> {
+        self.board_entities.get_last_move()
+    }
+
     pub fn update_game_context(&mut self, game_context: &caro_protocol::GameContext) {
         let player1_moves = game_context.player1_move_history
                                             .iter()
@@ -90,6 +106,21 @@ impl ScreenManager {
         self.board_entities.update_move_set(player1_moves, player2_moves);
     }
 
+    pub fn set_game_result(&mut self, game_result: &caro_protocol::GameResult) {
+        self.game_result_entity = Some(entities_factory::EntitiesFactory::get_game_result_entity(game_result, self.board_entities.player_order));
+        let winning_line = game_result.winning_line
+                                        .iter()
+                                        .map(|(x, y)| (*x as usize, *y as usize))
+                                        .collect();
+        let x_marks = game_result.game_state == caro_protocol::GameState::Player1Won;
+        self.board_entities.set_winning_line(winning_line, x_marks);
+    }
+
+    pub fn clear_game_result(&mut self) {
+        self.game_result_entity = None;
+        self.board_entities.set_winning_line(Vec::new(), true);
+    }
+
     pub async fn update(&self) {
         let player_state = self.global_state.read().await.get_player_state();
         match player_state {
@@ -97,6 +128,9 @@ impl ScreenManager {
                 for entity in self.menu_entities_vec.iter() {
                     entity.display();
                 }
+                let server_address = self.global_state.read().await.get_server_address();
+                let connect_state = self.global_state.read().await.get_connection_state();
+                entities_factory::EntitiesFactory::get_server_info_entity(server_address, connect_state).display();
             },
             caro_protocol::PlayerState::InRoom(_) =>  {
                 for entity in self.room_entities_vec.iter() {
@@ -108,6 +142,9 @@ impl ScreenManager {
                     entity.display();
                 }
                 self.board_entities.update();
+                if let Some(entity) = &self.game_result_entity {
+                    entity.display();
+                }
             }
         }
         self.log_entity.display();
@@ -167,10 +204,13 @@ struct BoardManager {
     player_cursor: Box<dyn screen_entity::ScreenEntity>,
     player1_moves_entities: Box<dyn screen_entity::ScreenEntity>,
     player2_moves_entities: Box<dyn screen_entity::ScreenEntity>,
+    winning_line_entity: Box<dyn screen_entity::ScreenEntity>,
 
     player_order: caro_protocol::PlayerOrder,
     player1_moves: Vec<(usize, usize)>,
     player2_moves: Vec<(usize, usize)>,
+    winning_line: Vec<(usize, usize)>,
+    winning_line_x_marks: bool,
 }
 
 impl BoardManager {
@@ -186,6 +226,8 @@ impl BoardManager {
             (vertical_range, horizontal_range, Vec::new(), false));
         let player2_moves_entities = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::OMoveSet
             (vertical_range, horizontal_range, Vec::new(), false));
+        let winning_line_entity = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::WinningLine
+            (vertical_range, horizontal_range, Vec::new(), true));
         Self {
             vertical_range,
             horizontal_range,
@@ -195,10 +237,13 @@ impl BoardManager {
             player_cursor,
             player1_moves_entities,
             player2_moves_entities,
+            winning_line_entity,
 
             player_order: caro_protocol::PlayerOrder::Player1,
             player1_moves: Vec::new(),
             player2_moves: Vec::new(),
+            winning_line: Vec::new(),
+            winning_line_x_marks: true,
         }
     }
 
@@ -240,12 +285,45 @@ impl BoardManager {
             (self.vertical_range, self.horizontal_range, (clamped_latitude as usize, clamped_longtitude as usize), true));
 
         if need_to_update_layout {
-            self.coordinate_layout = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::CoordinateLayout
-                (self.vertical_range, self.horizontal_range));
-            self.update_move_set(self.player1_moves.clone(), self.player2_moves.clone());
+            self.update_layout();
         }
     }
 
+    // puts the cursor in the middle of what is shown, as far as the edges of the board allow
+    fn center_view(&mut self) {
+        let (latitude, longtitude) = self.get_cursor_pos();
+        let vertical_start = (latitude.max(0) as usize).saturating_sub(BOARD_HEIGHT / 2).min(LATITUDE_LIMIT + 1 - BOARD_HEIGHT);
+        let horizontal_start = (longtitude.max(0) as usize).saturating_sub(BOARD_WIDTH / 2).min(LONGTITUDE_LIMIT + 1 - BOARD_WIDTH);
+        self.vertical_range = (vertical_start, vertical_start + BOARD_HEIGHT - 1);
+        self.horizontal_range = (horizontal_start, horizontal_start + BOARD_WIDTH - 1);
+        self.player_cursor = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::Cursor
+            (self.vertical_range, self.horizontal_range, (latitude.max(0) as usize, longtitude.max(0) as usize), true));
+        self.update_layout();
+    }
+
+    fn update_layout(&mut self) {
+        self.coordinate_layout = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::CoordinateLayout
+            (self.vertical_range, self.horizontal_range));
+        self.update_move_set(self.player1_moves.clone(), self.player2_moves.clone());
+    }
+
+    // player 1 moves first, so whoever has more moves made the last one
+    fn get_last_move(&self) -> Option<caro_protocol::Coordinate> {
+        let last_move = if self.player1_moves.len() > self.player2_moves.len() {
+            self.player1_moves.last()
+        } else {
+            self.player2_moves.last()
+        };
+        last_move.map(|(latitude, longtitude)| (*latitude as caro_protocol::Latitude, *longtitude as caro_protocol::Longtitude))
+    }
+
+    fn set_winning_line(&mut self, winning_line: Vec<(usize, usize)>, x_marks: bool) {
+        self.winning_line = winning_line;
+        self.winning_line_x_marks = x_marks;
+        self.winning_line_entity = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::WinningLine
+            (self.vertical_range, self.horizontal_range, self.winning_line.clone(), x_marks));
+    }
+
     fn get_cursor_pos(&self) -> caro_protocol::Coordinate {
         // (self.cursor_pos.0, self.cursor_pos.1);
         self.player_cursor.get_position()
@@ -276,6 +354,8 @@ impl BoardManager {
             (self.vertical_range, self.horizontal_range, player1_moves, is_player1));
         self.player2_moves_entities = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::OMoveSet
             (self.vertical_range, self.horizontal_range, player2_moves, !is_player1));
+        self.winning_line_entity = entities_factory::EntitiesFactory::get_board_entity(entities_factory::BoardEntityType::WinningLine
+            (self.vertical_range, self.horizontal_range, self.winning_line.clone(), self.winning_line_x_marks));
     }
 
     fn update(&self) {
@@ -290,5 +370,7 @@ impl BoardManager {
         // layer 4
         self.player1_moves_entities.display();
         self.player2_moves_entities.display();
+        // layer 5
+        self.winning_line_entity.display();
     }
 }
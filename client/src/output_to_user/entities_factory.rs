@@ -1,12 +1,15 @@
-use crate::output_to_user::{game_entities, menu_entities, room_entities};
+use crate::{caro_protocol, output_to_user::{connect_entities, game_entities, menu_entities, room_entities}};
 
 use super::screen_entity;
 
 #[derive(Debug, Clone, Copy)]
 pub enum ScreenType {
+    Connect,
     Menu,
     InRoom,
     InGame,
+    // the game screen of local_app, with its own instructions
+    LocalGame,
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +18,7 @@ pub enum BoardEntityType {
     Cursor((usize, usize), (usize, usize), (usize, usize), bool),
     XMoveSet((usize, usize), (usize, usize), Vec<(usize, usize)>, bool),
     OMoveSet((usize, usize), (usize, usize), Vec<(usize, usize)>, bool),
+    WinningLine((usize, usize), (usize, usize), Vec<(usize, usize)>, bool),
 }
 
 pub struct EntitiesFactory;
@@ -22,6 +26,14 @@ pub struct EntitiesFactory;
 impl EntitiesFactory {
     pub fn get_screen_entities(screen_type: ScreenType) -> Vec<Box<dyn screen_entity::ScreenEntity>> {
         match screen_type {
+            ScreenType::Connect => {
+                let connect_instruction_box = connect_entities::InstructionBox::new();
+                let menu_prompt_box = menu_entities::PromptBox::new();
+                vec![
+                    Box::new(connect_instruction_box),
+                    Box::new(menu_prompt_box)
+                ]
+            },
             ScreenType::Menu => {
                 let menu_instruction_box = menu_entities::InstructionBox::new();
                 let menu_prompt_box = menu_entities::PromptBox::new();
@@ -45,7 +57,15 @@ impl EntitiesFactory {
                     Box::new(game_instruction_box),
                     Box::new(game_prompt_box)
                 ]
-            }
+            },
+            ScreenType::LocalGame => {
+                let game_instruction_box = game_entities::InstructionBox::new_local();
+                let game_prompt_box = game_entities::PromptBox::new();
+                vec![
+                    Box::new(game_instruction_box),
+                    Box::new(game_prompt_box)
+                ]
+            },
         }
     }
 
@@ -63,20 +83,31 @@ impl EntitiesFactory {
             BoardEntityType::OMoveSet(vertical_range, horizontal_range, move_set, are_you) => {
                 Box::new(game_entities::OMoveSet::new(vertical_range, horizontal_range, move_set, are_you))
             },
+            BoardEntityType::WinningLine(vertical_range, horizontal_range, line, x_marks) => {
+                Box::new(game_entities::WinningLine::new(vertical_range, horizontal_range, line, x_marks))
+            },
         }
     }
 
     pub fn get_log_entity(content: String, screen_type: ScreenType) -> Box<dyn screen_entity::ScreenEntity> {
         match screen_type {
-            ScreenType::Menu => {
+            ScreenType::Connect | ScreenType::Menu => {
                 Box::new(menu_entities::LogBox::new(content))
             },
             ScreenType::InRoom => {
                 Box::new(room_entities::LogBox::new(content))
             },
-            ScreenType::InGame => {
+            ScreenType::InGame | ScreenType::LocalGame => {
                 Box::new(game_entities::LogBox::new(content))
             },
         }
     }
+
+    pub fn get_game_result_entity(game_result: &caro_protocol::GameResult, player_order: caro_protocol::PlayerOrder) -> Box<dyn screen_entity::ScreenEntity> {
+        Box::new(game_entities::ResultBox::new(game_result, player_order))
+    }
+
+    pub fn get_server_info_entity(server_address: String, connect_state: caro_protocol::ConnectState) -> Box<dyn screen_entity::ScreenEntity> {
+        Box::new(menu_entities::ServerInfoBox::new(server_address, connect_state))
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use caro_console::artworks::ArtDimension;
 
-use crate::output_to_user::screen_entity;
+use crate::{caro_protocol, output_to_user::screen_entity};
 
 const GAME_INSTRUCTION_BOX_POS: (usize, usize) = (5, 15);
 pub struct InstructionBox {
@@ -9,7 +9,16 @@ pub struct InstructionBox {
 
 impl InstructionBox {
     pub fn new() -> Self {
-        let art = caro_console::artworks::MENU_INSTRUCTION.to_string();
+        Self::with_art(caro_console::artworks::GAME_INSTRUCTION)
+    }
+
+    // against the computer there are hints but nobody to talk to
+    pub fn new_local() -> Self {
+        Self::with_art(caro_console::artworks::LOCAL_GAME_INSTRUCTION)
+    }
+
+    fn with_art(art: &str) -> Self {
+        let art = art.to_string();
         Self {
             entity: caro_console::output::DrawableBox {
                 coordinate: GAME_INSTRUCTION_BOX_POS,
@@ -237,6 +246,101 @@ impl screen_entity::ScreenEntity for OMoveSet {
     }
 }
 
+// the marks of the line that won, drawn over the winner's own marks
+pub struct WinningLine {
+    entities: Vec<caro_console::output::DrawableBox>,
+}
+
+impl WinningLine {
+    pub fn new(vertical_range: (usize, usize), horizontal_range: (usize, usize), line: Vec<(usize, usize)>, x_marks: bool) -> Self {
+        let board_pos = caro_console::caro_art_tools::BoardPosition {
+            base: GAME_BOARD_BASE_POS,
+            vertical_range,
+            horizontal_range,
+        };
+        let entities = if x_marks {
+            caro_console::caro_art_tools::get_drawable_x_moves(&board_pos, line)
+        } else {
+            caro_console::caro_art_tools::get_drawable_o_moves(&board_pos, line)
+        };
+        Self {
+            entities,
+        }
+    }
+}
+
+impl screen_entity::ScreenEntity for WinningLine {
+    fn display(&self) {
+        caro_console::output::set_pen_color(caro_console::output::Color::Yellow(100));
+        for entity in &self.entities {
+            caro_console::output::draw(entity);
+        }
+    }
+
+    fn get_position(&self) -> (screen_entity::Latitude, screen_entity::Longtitude) {
+        (0, 0)
+    }
+
+    fn set_position(&mut self, _latitude: screen_entity::Latitude, _longtitude: screen_entity::Longtitude) {
+        // locked
+    }
+}
+
+const GAME_RESULT_BOX_POS: (usize, usize) = (23, 60);
+pub struct ResultBox {
+    entity: caro_console::output::DrawableBox,
+}
+
+impl ResultBox {
+    pub fn new(game_result: &caro_protocol::GameResult, player_order: caro_protocol::PlayerOrder) -> Self {
+        let you_won = match (game_result.game_state, player_order) {
+            (caro_protocol::GameState::Player1Won, caro_protocol::PlayerOrder::Player1) |
+            (caro_protocol::GameState::Player2Won, caro_protocol::PlayerOrder::Player2) => Some(true),
+            (caro_protocol::GameState::Player1Won, _) |
+            (caro_protocol::GameState::Player2Won, _) => Some(false),
+            _ => None,
+        };
+        let (verdict, reason) = match (you_won, game_result.reason) {
+            (Some(true), caro_protocol::GameEndReason::LineCompleted) => ("You won!", "You completed a line"),
+            (Some(false), caro_protocol::GameEndReason::LineCompleted) => ("You lost", "Your opponent completed a line"),
+            (Some(true), caro_protocol::GameEndReason::Forfeit) => ("You won!", "Your opponent left the game"),
+            (Some(false), caro_protocol::GameEndReason::Forfeit) => ("You lost", "You left the game"),
+            (_, caro_protocol::GameEndReason::BoardFull) => ("Draw", "The board is full"),
+            (None, _) => ("Draw", ""),
+        };
+        let art = format!("{}  {:<38}\n  {:<38}\n{}",
+                          caro_console::artworks::GAME_OVER_HEADER,
+                          verdict,
+                          reason,
+                          caro_console::artworks::GAME_OVER_OPTIONS);
+        Self {
+            entity: caro_console::output::DrawableBox {
+                coordinate: GAME_RESULT_BOX_POS,
+                constraint: (art.height(), art.width()),
+                offset: (0, 0),
+                show_boundary_line: true,
+                art,
+            }
+        }
+    }
+}
+
+impl screen_entity::ScreenEntity for ResultBox {
+    fn display(&self) {
+        caro_console::output::set_pen_color(caro_console::output::Color::Magenta(100));
+        caro_console::output::draw(&self.entity);
+    }
+
+    fn get_position(&self) -> (screen_entity::Latitude, screen_entity::Longtitude) {
+        (self.entity.coordinate.0 as i64, self.entity.coordinate.1 as i64)
+    }
+
+    fn set_position(&mut self, latitude: screen_entity::Latitude, longtitude: screen_entity::Longtitude) {
+        self.entity.coordinate.0 = latitude as usize;
+        self.entity.constraint.1 = longtitude as usize;
+    }
+}
+
 const GAME_LOG_BOX_POS: (usize, usize) = (34, 61);
 const GAME_LOG_BOX_WIDTH: usize = 20;
 pub struct LogBox {
@@ -1,37 +1,44 @@
 use caro_console::artworks::ArtDimension;
 
-use crate::output_to_user::screen_entity;
+use crate::{caro_protocol, output_to_user::screen_entity};
 
+const SERVER_INFO_BOX_POS: (usize, usize) = (13, 16);
+const SERVER_INFO_BOX_WIDTH: usize = 40;
 pub struct ServerInfoBox {
-    ipaddress: String,
-    port: String,
-    latitude: screen_entity::Latitude,
-    longtitude: screen_entity::Longtitude,
+    entity: caro_console::output::DrawableBox,
+    connect_state: caro_protocol::ConnectState,
 }
 
 impl ServerInfoBox {
-    pub fn new(ipaddress: String, port: String) -> Self {
+    pub fn new(server_address: String, connect_state: caro_protocol::ConnectState) -> Self {
+        let status = match connect_state {
+            caro_protocol::ConnectState::Connected => "connected",
+            caro_protocol::ConnectState::Disconnected => "disconnected",
+        };
+        let content = format!("Server {} ({})", server_address, status);
         Self {
-            ipaddress,
-            port,
-            latitude: -1,
-            longtitude: -1,
+            entity: caro_console::output::DrawableBox::from((content, SERVER_INFO_BOX_WIDTH, SERVER_INFO_BOX_POS.0, SERVER_INFO_BOX_POS.1)),
+            connect_state,
         }
     }
 }
 
 impl screen_entity::ScreenEntity for ServerInfoBox {
     fn display(&self) {
-        
+        match self.connect_state {
+            caro_protocol::ConnectState::Connected => caro_console::output::set_pen_color(caro_console::output::Color::Green(100)),
+            caro_protocol::ConnectState::Disconnected => caro_console::output::set_pen_color(caro_console::output::Color::Red(100)),
+        }
+        caro_console::output::draw(&self.entity);
     }
 
     fn get_position(&self) -> (screen_entity::Latitude, screen_entity::Longtitude) {
-        (self.latitude, self.longtitude)
+        (self.entity.coordinate.0 as i64, self.entity.coordinate.1 as i64)
     }
 
     fn set_position(&mut self, latitude: screen_entity::Latitude, longtitude: screen_entity::Longtitude) {
-        self.latitude = latitude;
-        self.longtitude = longtitude;
+        self.entity.coordinate.0 = latitude as usize;
+        self.entity.coordinate.1 = longtitude as usize;
     }
 }
 
@@ -23,7 +23,8 @@ impl ResponseExecutor {
         }
     }
 
-    pub async fn execute_response(&mut self, response_type: caro_protocol::ServerCode) {
+    // request_id is set when the response answers one of our requests
+    pub async fn execute_response(&mut self, request_id: Option<caro_protocol::RequestId>, response_type: caro_protocol::ServerCode) {
         let current_state = self.global_state.read().await.get_player_state();
         match response_type {
             caro_protocol::ServerCode::General(code) => {
@@ -41,12 +42,31 @@ impl ResponseExecutor {
             },
             caro_protocol::ServerCode::InGame(code) => {
                 if current_state == caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected) {
-                    self.execute_ingame_response(code).await;
+                    self.execute_ingame_response(request_id, code).await;
                 }
             },
+            caro_protocol::ServerCode::Error(code) => {
+                self.execute_error_response(code).await;
+            },
         }
     }
 
+    async fn execute_error_response(&mut self, code: caro_protocol::ErrorResponse) {
+        let log_content = match code {
+            caro_protocol::ErrorResponse::UnknownPlayer => "The server does not know you".to_string(),
+            caro_protocol::ErrorResponse::NotInRoom => "You are not in a room".to_string(),
+            caro_protocol::ErrorResponse::NoSuchRoom(rid) => format!("Room {} does not exist", rid),
+            caro_protocol::ErrorResponse::NoGameInRoom(rid) => format!("Room {} has no game", rid),
+            caro_protocol::ErrorResponse::ServerFull => "The server is full".to_string(),
+            caro_protocol::ErrorResponse::RuleNotAllowed(rule) => format!("Rule {:?} is disabled on this server", rule),
+            caro_protocol::ErrorResponse::BoardSizeNotAllowed(board_size) => format!("The server does not allow {}x{} boards", board_size, board_size),
+            caro_protocol::ErrorResponse::NotAllowedInState(_state) => "Not allowed right now".to_string(),
+            caro_protocol::ErrorResponse::UnknownSession => "Your previous seat is gone".to_string(),
+            caro_protocol::ErrorResponse::RateLimited => "Slow down, the server ignored your last request".to_string(),
+        };
+        self.screen_manager.write().await.log(log_content).await;
+    }
+
     async fn execute_general_response(&mut self, code: caro_protocol::GeneralResponse) {
         match code {
             caro_protocol::GeneralResponse::State(your_state) => {
@@ -57,6 +77,48 @@ impl ResponseExecutor {
                 let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
                 self.requester.write().await.send_request(new_packet).await;
             },
+            caro_protocol::GeneralResponse::ServerShuttingDown => {
+                self.screen_manager.write().await.log("The server is shutting down".to_string()).await;
+            },
+            caro_protocol::GeneralResponse::Session(session_token) => {
+                self.global_state.write().await.set_session_token(session_token);
+            },
+            // the state came just before, the game context follows if a game is being played
+            caro_protocol::GeneralResponse::SessionResumed(rid, order) => {
+                self.global_state.write().await.set_current_rid(rid);
+                self.screen_manager.write().await.clean();
+                self.screen_manager.write().await.set_player_order(order);
+                self.screen_manager.write().await.update().await;
+                self.screen_manager.write().await.log(format!("Back in room {}", rid)).await;
+                self.screen_manager.write().await.enable_prompt_mode().await;
+            },
+            caro_protocol::GeneralResponse::Broadcast(text) => {
+                self.screen_manager.write().await.log(format!("[server] {}", text)).await;
+            },
+            // only our own lines come back before an opponent sat down
+            caro_protocol::GeneralResponse::Chat(_, _) if self.global_state.read().await.is_chat_hidden() => {},
+            caro_protocol::GeneralResponse::Chat(order, text) => {
+                let receiver_order = self.global_state.read().await.get_game_context().map(|game_context| game_context.receiver_order);
+                let speaker = match receiver_order {
+                    Some(receiver_order) if receiver_order != order => "Opponent",
+                    _ => "You",
+                };
+                self.screen_manager.write().await.log(format!("{}: {}", speaker, text)).await;
+            },
+            caro_protocol::GeneralResponse::Kicked => {
+                // the session is gone with the player, reconnecting must not try to resume it
+                self.global_state.write().await.clear_session_token();
+                self.screen_manager.write().await.log("You were removed from the server".to_string()).await;
+            },
+            caro_protocol::GeneralResponse::RoomClosed(rid) => {
+                self.global_state.write().await.set_player_state(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected));
+                self.global_state.write().await.clear_game_context();
+                self.screen_manager.write().await.clear_game_result();
+                self.screen_manager.write().await.clean();
+                self.screen_manager.write().await.update().await;
+                self.screen_manager.write().await.log(format!("Room {} was closed by the server", rid)).await;
+                self.screen_manager.write().await.enable_prompt_mode().await;
+            },
         }
     }
 
@@ -100,8 +162,30 @@ impl ResponseExecutor {
             caro_protocol::LoggedResponse::FailedToCreateRoom => {
                 self.screen_manager.write().await.log("Failed to create room".to_string()).await;
             },
-            caro_protocol::LoggedResponse::FailedToJoinRoom(rid) => {
-                self.screen_manager.write().await.log(format!("Failed to join room: {}", rid)).await;
+            caro_protocol::LoggedResponse::FailedToJoinRoom(key, reason) => {
+                let room = match key {
+                    caro_protocol::RoomKey::Id(rid) => rid.to_string(),
+                    caro_protocol::RoomKey::Code(join_code) => join_code,
+                };
+                let reason = match reason {
+                    caro_protocol::JoinFailure::NoSuchRoom => "no such room",
+                    caro_protocol::JoinFailure::RoomFull => "room is full",
+                    caro_protocol::JoinFailure::AlreadyInRoom => "already in a room",
+                    caro_protocol::JoinFailure::WrongPassword => "wrong password",
+                    caro_protocol::JoinFailure::TooManyAttempts => "too many attempts, wait a minute",
+                };
+                self.screen_manager.write().await.log(format!("Failed to join room {}: {}", room, reason)).await;
+            },
+            caro_protocol::LoggedResponse::RoomList(rooms) => {
+                self.global_state.write().await.set_listed_rooms(rooms.iter().map(|room| room.rid).collect());
+                if rooms.is_empty() {
+                    self.screen_manager.write().await.log("No open rooms, create one with mkroom".to_string()).await;
+                }
+                for room in rooms {
+                    let lock = if room.has_password { " (password)" } else { "" };
+                    let log_content = format!("Room {}: {:?}, {}/2 players{}", room.rid, room.rule, room.players, lock);
+                    self.screen_manager.write().await.log(log_content).await;
+                }
             },
         }
     }
@@ -114,6 +198,9 @@ impl ResponseExecutor {
                     caro_protocol::ConnectState::Connected => {
                         self.global_state.write().await.set_player_state(caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
                         self.global_state.write().await.set_current_rid(rid);
+                        // the new game starts with a full context of its own
+                        self.global_state.write().await.clear_game_context();
+                        self.screen_manager.write().await.clear_game_result();
                         self.screen_manager.write().await.clean();
                         self.screen_manager.write().await.update().await;
                         self.screen_manager.write().await.log("game is ready!".to_string()).await;
@@ -124,21 +211,85 @@ impl ResponseExecutor {
                     },
                 }
             },
+            caro_protocol::InRoomResponse::PrivateRoomCode(_rid, join_code) => {
+                let log_content = format!("Private room, share the join code {}", join_code);
+                self.screen_manager.write().await.log(log_content).await;
+            },
         }
     }
 
-    async fn execute_ingame_response(&mut self, code: caro_protocol::InGameResponse) {
+    async fn execute_ingame_response(&mut self, request_id: Option<caro_protocol::RequestId>, code: caro_protocol::InGameResponse) {
+        let pending_move = match request_id {
+            Some(request_id) => self.global_state.write().await.take_pending_move(request_id),
+            None => None,
+        };
         match code {
             caro_protocol::InGameResponse::MoveSuccess => {
-                self.screen_manager.write().await.log("Move Successful".to_string()).await;
+                let log_content = match pending_move {
+                    Some(coor) => format!("Move ({}, {}) accepted", coor.0, coor.1),
+                    None => "Move Successful".to_string(),
+                };
+                self.screen_manager.write().await.log(log_content).await;
             },
-            caro_protocol::InGameResponse::MoveUnsuccess => {
-                self.screen_manager.write().await.log("Move Unsuccessful".to_string()).await;
+            caro_protocol::InGameResponse::MoveRejected(reason) => {
+                let log_content = match reason {
+                    caro_protocol::MoveRejection::NotYourTurn => "Not your turn".to_string(),
+                    caro_protocol::MoveRejection::AlreadyOccupied(coor) => format!("Tile ({},{}) already occupied", coor.0, coor.1),
+                    caro_protocol::MoveRejection::OutOfBounds(coor) => format!("Tile ({},{}) is outside the board", coor.0, coor.1),
+                    caro_protocol::MoveRejection::NothingToUndo => "Nothing to undo".to_string(),
+                    caro_protocol::MoveRejection::NothingToRedo => "Nothing to redo".to_string(),
+                    caro_protocol::MoveRejection::GameNotInProgress => "The game is not in progress".to_string(),
+                    caro_protocol::MoveRejection::GameStillInProgress => "Finish this game first".to_string(),
+                };
+                self.screen_manager.write().await.log(log_content).await;
             },
             caro_protocol::InGameResponse::Context(game_context) => {
                 // screen_manager::print_caro_context(game_context);
                 self.screen_manager.write().await.update_game_context(&game_context);
                 self.screen_manager.write().await.update_board_only().await;
+                self.global_state.write().await.set_game_context(game_context);
+            },
+            caro_protocol::InGameResponse::Delta(delta) => {
+                // dropped while waiting for the full context
+                let mut game_context = match self.global_state.read().await.get_game_context() {
+                    Some(game_context) => game_context,
+                    None => return,
+                };
+                if game_context.apply_delta(&delta) {
+                    self.screen_manager.write().await.update_game_context(&game_context);
+                    self.screen_manager.write().await.update_board_only().await;
+                    self.global_state.write().await.set_game_context(game_context);
+                } else {
+                    // a delta went missing, start over from the whole game
+                    tracing::debug!(seq = delta.seq, last_seq = game_context.seq, "missed a game delta, resyncing");
+                    self.global_state.write().await.clear_game_context();
+                    let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRequestContext);
+                    let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                    self.requester.write().await.send_request(new_packet).await;
+                }
+            },
+            caro_protocol::InGameResponse::GameOver(game_result) => {
+                let log_content = match game_result.game_state {
+                    caro_protocol::GameState::Drew => "Game over, it is a draw".to_string(),
+                    _ => "Game over".to_string(),
+                };
+                self.screen_manager.write().await.set_game_result(&game_result);
+                self.screen_manager.write().await.update().await;
+                self.screen_manager.write().await.log(log_content).await;
+            },
+            caro_protocol::InGameResponse::OpponentLeft => {
+                self.screen_manager.write().await.log("Your opponent left the game".to_string()).await;
+            },
+            caro_protocol::InGameResponse::RematchOffered => {
+                self.screen_manager.write().await.log("Your opponent wants a rematch".to_string()).await;
+            },
+            caro_protocol::InGameResponse::RematchStarted => {
+                // the cleared board follows as a delta
+                self.screen_manager.write().await.clear_game_result();
+                self.screen_manager.write().await.clean();
+                self.screen_manager.write().await.update().await;
+                self.screen_manager.write().await.log("Rematch started".to_string()).await;
+                self.screen_manager.write().await.enable_prompt_mode().await;
             },
         }
     }
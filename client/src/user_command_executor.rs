@@ -1,7 +1,7 @@
 
 use std::sync::Arc;
 
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 
 use crate::
 {
@@ -16,16 +16,20 @@ pub struct CommandExecutor {
     global_state: Arc<RwLock<global_state::GlobalState>>,
     screen_manager: Arc<RwLock<output_to_user::ScreenManager>>,
     requester: Arc<RwLock<client_endpoint::Requester>>,
+    // the application winds down once this is notified
+    exit_requested: Arc<Notify>,
 }
 
 impl CommandExecutor {
     pub fn new(global_state: Arc<RwLock<global_state::GlobalState>>,
                 screen_manager: Arc<RwLock<output_to_user::ScreenManager>>,
-                requester: Arc<RwLock<client_endpoint::Requester>>) -> Self {
+                requester: Arc<RwLock<client_endpoint::Requester>>,
+                exit_requested: Arc<Notify>) -> Self {
         Self {
             global_state,
             screen_manager,
             requester,
+            exit_requested,
         }
     }
 
@@ -40,6 +44,11 @@ impl CommandExecutor {
                     self.execute_logged_command(command).await;
                 }
             },
+            // leave is typed the same way in a room and in a game
+            input_from_user::UserCommand::InRoom(input_from_user::InRoomCommand::LeaveRoom)
+                if current_state == caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected) => {
+                self.execute_ingame_command(input_from_user::InGameCommand::LeaveRoom).await;
+            },
             input_from_user::UserCommand::InRoom(command) => {
                 if current_state == caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected) {
                     self.execute_inroom_command(command).await;
@@ -55,8 +64,21 @@ impl CommandExecutor {
 
     async fn execute_general_command(&mut self, command: input_from_user::GeneralCommand) {
         match command {
+            // the server is told on the way out, whatever asked to exit
             input_from_user::GeneralCommand::ExitApplication => {
-
+                self.exit_requested.notify_one();
+            },
+            input_from_user::GeneralCommand::Help(topic) => {
+                let help = input_from_user::command_parser::help_text(topic);
+                self.screen_manager.write().await.log(help).await;
+            },
+            input_from_user::GeneralCommand::Say(text) => {
+                let code = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say(text));
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
+            },
+            input_from_user::GeneralCommand::ParseFailed(error) => {
+                self.screen_manager.write().await.log(error.to_string()).await;
             },
             input_from_user::GeneralCommand::Invalid => {
 
@@ -66,32 +88,19 @@ impl CommandExecutor {
 
     async fn execute_logged_command(&mut self, command: input_from_user::LoggedCommand) {
         match command {
-            input_from_user::LoggedCommand::RequestNewRoom(game_rule) => {
-                match game_rule {
-                    caro_protocol::GameRule::TicTacToe => {
-                        let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::TicTacToe));
-                        let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                        // println!("send: {:?}", new_packet);
-                        self.requester.write().await.send_request(new_packet).await;
-                    },
-                    caro_protocol::GameRule::FourBlockOne => {
-                        let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::FourBlockOne));
-                        let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                        // println!("send: {:?}", new_packet);
-                        self.requester.write().await.send_request(new_packet).await;
-                    },
-                    caro_protocol::GameRule::FiveBlockTwo => {
-                        let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::FiveBlockTwo));
-                        let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                        // println!("send: {:?}", new_packet);
-                        self.requester.write().await.send_request(new_packet).await;
-                    },
-                }
+            input_from_user::LoggedCommand::RequestNewRoom(game_rule, options) => {
+                let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(game_rule, options));
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
+            },
+            input_from_user::LoggedCommand::JoinRoom(key, password) => {
+                let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(key, password));
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
             },
-            input_from_user::LoggedCommand::JoinRoom(rid) => {
-                let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(rid));
+            input_from_user::LoggedCommand::ListRooms => {
+                let code = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms);
                 let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                // println!("send: {:?}", new_packet);
                 self.requester.write().await.send_request(new_packet).await;
             },
         }
@@ -100,7 +109,10 @@ impl CommandExecutor {
     async fn execute_inroom_command(&mut self, command: input_from_user::InRoomCommand) {
         match command {
             input_from_user::InRoomCommand::LeaveRoom => {
-
+                let code = caro_protocol::PlayerCode::InRoom(caro_protocol::InRoomRequest::PlayerLeaveRoom);
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
+                self.back_to_menu().await;
             },
         }
     }
@@ -112,8 +124,8 @@ impl CommandExecutor {
                 let coor = (coor.0, coor.1);
                 let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove(coor));
                 let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                // println!("send: {:?}", new_packet);
-                self.requester.write().await.send_request(new_packet).await;
+                let request_id = self.requester.write().await.send_request(new_packet).await;
+                self.global_state.write().await.add_pending_move(request_id, coor);
             },
             input_from_user::InGameCommand::Up => {
                 let new_position = (cursor_position.0 - 1, cursor_position.1);
@@ -138,14 +150,18 @@ impl CommandExecutor {
             input_from_user::InGameCommand::Enter => {
                 let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove(cursor_position));
                 let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
-                // println!("send: {:?}", new_packet);
-                self.requester.write().await.send_request(new_packet).await;
+                let request_id = self.requester.write().await.send_request(new_packet).await;
+                self.global_state.write().await.add_pending_move(request_id, cursor_position);
             },
             input_from_user::InGameCommand::Redo => {
-
+                let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRedo);
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
             }
             input_from_user::InGameCommand::Undo => {
-
+                let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerUndo);
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
             },
             input_from_user::InGameCommand::SwitchInputMode => {
                 let is_prompt_mode = self.screen_manager.read().await.is_prompt_mode();
@@ -155,9 +171,58 @@ impl CommandExecutor {
                     self.screen_manager.write().await.enable_prompt_mode().await;
                 }
             },
+            input_from_user::InGameCommand::Hint => {
+                self.screen_manager.write().await.log("Hints are only available against the computer".to_string()).await;
+            },
+            input_from_user::InGameCommand::Rematch => {
+                let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::RequestRematch);
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
+            },
+            // a game still in progress is lost by leaving it
             input_from_user::InGameCommand::LeaveRoom => {
-
-            }
+                let code = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom);
+                let new_packet = caro_protocol::MessagePacket::new_player_packet(code);
+                self.requester.write().await.send_request(new_packet).await;
+                self.back_to_menu().await;
+            },
+            input_from_user::InGameCommand::CenterView => {
+                self.screen_manager.write().await.center_view();
+                self.screen_manager.write().await.update_board_only().await;
+            },
+            input_from_user::InGameCommand::JumpToLastMove => {
+                let last_move = self.screen_manager.read().await.get_last_move();
+                match last_move {
+                    Some((latitude, longtitude)) => {
+                        self.screen_manager.write().await.set_cursor_pos(latitude, longtitude);
+                        self.screen_manager.write().await.update_board_only().await;
+                    },
+                    None => {
+                        self.screen_manager.write().await.log("No move yet".to_string()).await;
+                    },
+                }
+            },
+            input_from_user::InGameCommand::ToggleChat => {
+                let chat_hidden = !self.global_state.read().await.is_chat_hidden();
+                self.global_state.write().await.set_chat_hidden(chat_hidden);
+                let content = match chat_hidden {
+                    true => "Chat hidden",
+                    false => "Chat shown",
+                };
+                self.screen_manager.write().await.log(content.to_string()).await;
+            },
         }
     }
+
+    // the server only confirms the new state, the menu is shown without waiting for it
+    async fn back_to_menu(&mut self) {
+        self.global_state.write().await.set_player_state(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected));
+        self.global_state.write().await.clear_game_context();
+        self.global_state.write().await.clear_pending_moves();
+        self.screen_manager.write().await.clear_game_result();
+        self.screen_manager.write().await.clean();
+        self.screen_manager.write().await.update().await;
+        self.screen_manager.write().await.log("Back in the lobby".to_string()).await;
+        self.screen_manager.write().await.enable_prompt_mode().await;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,140 @@
+use caro_client::{
+    caro_protocol,
+    input_from_user::{
+        command_parser::{help_text, parse_command, ParseError, ToUserCommand},
+        GeneralCommand,
+        InGameCommand,
+        InRoomCommand,
+        LoggedCommand,
+        UserCommand
+    }
+};
+
+fn new_room(rule: caro_protocol::GameRule, private: bool, password: Option<&str>, board_size: Option<usize>) -> UserCommand {
+    let options = caro_protocol::RoomOptions { private, password: password.map(|password| password.to_string()), board_size };
+    UserCommand::Logged(LoggedCommand::RequestNewRoom(rule, options))
+}
+
+#[test]
+fn empty_lines_are_ignored() {
+    for line in ["", "   ", "\t"] {
+        assert_eq!(parse_command(line), Ok(UserCommand::General(GeneralCommand::Invalid)));
+    }
+}
+
+#[test]
+fn unknown_commands_are_reported() {
+    assert_eq!(parse_command("dance"), Err(ParseError::UnknownCommand("dance".to_string())));
+    assert_eq!(parse_command("dance").unwrap_err().to_string(), "Unknown command 'dance', type help");
+    // errors reach the executor instead of panicking the input task
+    let command = caro_console::input::InputType::Text("move".to_string()).to_user_command();
+    assert_eq!(command, UserCommand::General(GeneralCommand::ParseFailed(ParseError::MissingArgument("move", "<h8|lat long>"))));
+}
+
+#[test]
+fn help() {
+    assert_eq!(parse_command("help"), Ok(UserCommand::General(GeneralCommand::Help(None))));
+    assert_eq!(parse_command("?"), Ok(UserCommand::General(GeneralCommand::Help(None))));
+    // aliases are explained under the command they stand for
+    assert_eq!(parse_command("help cdroom"), Ok(UserCommand::General(GeneralCommand::Help(Some("join")))));
+    assert_eq!(parse_command("help fly"), Err(ParseError::UnknownCommand("fly".to_string())));
+    assert_eq!(parse_command("help move undo"), Err(ParseError::UnexpectedArgument("help", "undo".to_string())));
+
+    assert!(help_text(None).contains("mkroom"));
+    assert_eq!(help_text(Some("forfeit")), "leave : leave the room, a game in progress is lost (also lobby, forfeit, resign)");
+}
+
+#[test]
+fn exit() {
+    assert_eq!(parse_command("exit"), Ok(UserCommand::General(GeneralCommand::ExitApplication)));
+    assert_eq!(parse_command("QUIT"), Ok(UserCommand::General(GeneralCommand::ExitApplication)));
+    assert_eq!(parse_command("exit now"), Err(ParseError::UnexpectedArgument("exit", "now".to_string())));
+}
+
+#[test]
+fn rooms() {
+    assert_eq!(parse_command("rooms"), Ok(UserCommand::Logged(LoggedCommand::ListRooms)));
+    assert_eq!(parse_command("ls"), Ok(UserCommand::Logged(LoggedCommand::ListRooms)));
+}
+
+#[test]
+fn mkroom() {
+    assert_eq!(parse_command("mkroom 3"), Ok(new_room(caro_protocol::GameRule::TicTacToe, false, None, None)));
+    assert_eq!(parse_command("mkroom caro4"), Ok(new_room(caro_protocol::GameRule::FourBlockOne, false, None, None)));
+    assert_eq!(parse_command("mkroom caro5 --size 19x19 --private"),
+               Ok(new_room(caro_protocol::GameRule::FiveBlockTwo, true, None, Some(19))));
+    assert_eq!(parse_command("create --password=hunter2 --size 15 5"),
+               Ok(new_room(caro_protocol::GameRule::FiveBlockTwo, false, Some("hunter2"), Some(15))));
+
+    assert_eq!(parse_command("mkroom"), Err(ParseError::MissingArgument("mkroom", "<3|4|5>")));
+    assert_eq!(parse_command("mkroom 7"), Err(ParseError::InvalidArgument("mkroom", "7".to_string(), "a rule, try 3, 4 or 5")));
+    assert_eq!(parse_command("mkroom 5 4"), Err(ParseError::UnexpectedArgument("mkroom", "4".to_string())));
+    assert_eq!(parse_command("mkroom 5 --size"), Err(ParseError::MissingArgument("mkroom", "a size after --size")));
+    assert_eq!(parse_command("mkroom 5 --size big"),
+               Err(ParseError::InvalidArgument("mkroom", "big".to_string(), "a board size like 15 or 15x15")));
+    assert_eq!(parse_command("mkroom 5 --size 15x19"), Err(ParseError::Unsupported("mkroom", "boards are always square")));
+    assert_eq!(parse_command("mkroom 3 --size 9"), Err(ParseError::Unsupported("mkroom", "tic tac toe is always played on 3x3")));
+    assert_eq!(parse_command("mkroom 5 --fast"), Err(ParseError::UnknownOption("mkroom", "--fast".to_string())));
+}
+
+#[test]
+fn join() {
+    assert_eq!(parse_command("join 12"), Ok(UserCommand::Logged(LoggedCommand::JoinRoom(caro_protocol::RoomKey::Id(12), None))));
+    assert_eq!(parse_command("cdroom abc123 secret"),
+               Ok(UserCommand::Logged(LoggedCommand::JoinRoom(caro_protocol::RoomKey::Code("ABC123".to_string()), Some("secret".to_string())))));
+
+    assert_eq!(parse_command("cdroom"), Err(ParseError::MissingArgument("join", "<room id|code>")));
+    assert_eq!(parse_command("join a-b"), Err(ParseError::InvalidArgument("join", "a-b".to_string(), "a room id or join code")));
+    assert_eq!(parse_command("join 1 pw more"), Err(ParseError::UnexpectedArgument("join", "more".to_string())));
+}
+
+#[test]
+fn leave() {
+    assert_eq!(parse_command("leave"), Ok(UserCommand::InRoom(InRoomCommand::LeaveRoom)));
+    assert_eq!(parse_command("lobby"), Ok(UserCommand::InRoom(InRoomCommand::LeaveRoom)));
+}
+
+#[test]
+fn moves() {
+    let play = |coordinate| Ok(UserCommand::InGame(InGameCommand::Move(coordinate)));
+    assert_eq!(parse_command("move h8"), play((8, 7)));
+    assert_eq!(parse_command("mv A0"), play((0, 0)));
+    assert_eq!(parse_command("move aa3"), play((3, 26)));
+    assert_eq!(parse_command("move 8 7"), play((8, 7)));
+    assert_eq!(parse_command("move 8,7"), play((8, 7)));
+
+    let invalid = |value: &str| Err(ParseError::InvalidArgument("move", value.to_string(), "a tile like h8 or 8 7"));
+    assert_eq!(parse_command("move a b"), invalid("a b"));
+    assert_eq!(parse_command("move h"), invalid("h"));
+    assert_eq!(parse_command("move 8"), invalid("8"));
+    assert_eq!(parse_command("move -1 2"), invalid("-1 2"));
+    assert_eq!(parse_command("move 1 2 3"), Err(ParseError::UnexpectedArgument("move", "3".to_string())));
+}
+
+#[test]
+fn undo_and_redo() {
+    assert_eq!(parse_command("undo"), Ok(UserCommand::InGame(InGameCommand::Undo)));
+    assert_eq!(parse_command("redo"), Ok(UserCommand::InGame(InGameCommand::Redo)));
+    assert_eq!(parse_command("undo 2"), Err(ParseError::UnexpectedArgument("undo", "2".to_string())));
+}
+
+#[test]
+fn forfeit_and_resign_are_leaving() {
+    // leaving a game in progress is what gives it up
+    assert_eq!(parse_command("forfeit"), parse_command("leave"));
+    assert_eq!(parse_command("resign"), parse_command("leave"));
+}
+
+#[test]
+fn rematch_hint_and_switch() {
+    assert_eq!(parse_command("rematch"), Ok(UserCommand::InGame(InGameCommand::Rematch)));
+    assert_eq!(parse_command("hint"), Ok(UserCommand::InGame(InGameCommand::Hint)));
+    assert_eq!(parse_command("switch"), Ok(UserCommand::InGame(InGameCommand::SwitchInputMode)));
+}
+
+#[test]
+fn say() {
+    assert_eq!(parse_command("say  good  game "), Ok(UserCommand::General(GeneralCommand::Say("good  game".to_string()))));
+    assert_eq!(parse_command("chat hi"), Ok(UserCommand::General(GeneralCommand::Say("hi".to_string()))));
+    assert_eq!(parse_command("say"), Err(ParseError::MissingArgument("say", "<text>")));
+}
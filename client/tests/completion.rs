@@ -0,0 +1,39 @@
+use caro_client::{caro_protocol, input_from_user::completion::complete};
+
+const LOGGED: caro_protocol::PlayerState = caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected);
+const IN_ROOM: caro_protocol::PlayerState = caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected);
+const IN_GAME: caro_protocol::PlayerState = caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected);
+
+#[test]
+fn commands_depend_on_the_state() {
+    let in_lobby = complete("", LOGGED, &[]);
+    assert!(in_lobby.contains(&"mkroom".to_string()));
+    assert!(in_lobby.contains(&"cdroom".to_string()));
+    assert!(!in_lobby.contains(&"move".to_string()));
+
+    let in_room = complete("le", IN_ROOM, &[]);
+    assert!(in_room.contains(&"leave".to_string()));
+    assert!(!in_room.contains(&"rooms".to_string()));
+
+    let in_game = complete("", IN_GAME, &[]);
+    for command in ["move", "undo", "forfeit", "say", "help"] {
+        assert!(in_game.contains(&command.to_string()), "{} missing from {:?}", command, in_game);
+    }
+    assert!(!in_game.contains(&"mkroom".to_string()));
+}
+
+#[test]
+fn joining_offers_the_listed_rooms() {
+    assert_eq!(complete("cdroom ", LOGGED, &[3, 12]), vec!["3".to_string(), "12".to_string()]);
+    assert_eq!(complete("join 1", LOGGED, &[3, 12]), vec!["3".to_string(), "12".to_string()]);
+    // the password is not guessed
+    assert!(complete("join 3 ", LOGGED, &[3, 12]).is_empty());
+}
+
+#[test]
+fn arguments_of_mkroom_and_help() {
+    assert!(complete("mkroom ", LOGGED, &[]).contains(&"caro5".to_string()));
+    assert!(complete("mkroom 5 --p", LOGGED, &[]).contains(&"--private".to_string()));
+    assert!(complete("help ", IN_GAME, &[]).contains(&"rematch".to_string()));
+    assert!(complete("dance ", LOGGED, &[]).is_empty());
+}
@@ -0,0 +1,82 @@
+use caro_client::input_from_user::{
+    key_map::{parse_key, KeyMap, KeyMapError, KeyPreset},
+    GeneralCommand,
+    InGameCommand,
+    UserCommand
+};
+use caro_console::input::{InputType, KeyType};
+
+#[test]
+fn the_presets_move_with_their_own_keys() {
+    let arrows = KeyMap::default();
+    let wasd = KeyMap::new(KeyPreset::Wasd);
+    let vim = KeyMap::new(KeyPreset::Vim);
+    for key_map in [&arrows, &wasd, &vim] {
+        assert_eq!(key_map.get_binding(KeyType::Up), Some(InGameCommand::Up));
+        assert_eq!(key_map.get_binding(KeyType::Char('Q')), Some(InGameCommand::LeaveRoom));
+        assert_eq!(key_map.get_binding(KeyType::Char('t')), Some(InGameCommand::ToggleChat));
+    }
+    assert_eq!(wasd.get_binding(KeyType::Char('a')), Some(InGameCommand::Left));
+    assert_eq!(wasd.get_binding(KeyType::Char(' ')), Some(InGameCommand::Enter));
+    assert_eq!(vim.get_binding(KeyType::Char('j')), Some(InGameCommand::Down));
+    assert_eq!(vim.get_binding(KeyType::Ctrl('r')), Some(InGameCommand::Redo));
+    assert_eq!(arrows.get_binding(KeyType::Char('u')), Some(InGameCommand::Undo));
+    assert_eq!(arrows.get_binding(KeyType::Char('c')), Some(InGameCommand::CenterView));
+    assert_eq!(arrows.get_binding(KeyType::Char('l')), Some(InGameCommand::JumpToLastMove));
+    assert_eq!(arrows.get_binding(KeyType::Char('j')), None);
+}
+
+#[test]
+fn esc_and_exit_are_not_up_to_the_map() {
+    let key_map = KeyMap::new(KeyPreset::Vim);
+    assert_eq!(key_map.get_command(KeyType::Esc), UserCommand::InGame(InGameCommand::SwitchInputMode));
+    assert_eq!(key_map.get_command(KeyType::Exit), UserCommand::General(GeneralCommand::ExitApplication));
+    assert_eq!(key_map.get_command(KeyType::Char('y')), UserCommand::General(GeneralCommand::Invalid));
+    // typed lines are still parsed
+    assert_eq!(key_map.to_user_command(InputType::Text("undo".to_string())), UserCommand::InGame(InGameCommand::Undo));
+}
+
+#[test]
+fn the_file_changes_keys_on_top_of_its_preset() {
+    let key_map = KeyMap::parse(r#"
+        preset = "wasd"
+
+        [keys]
+        space = "none"
+        f = "place"
+        Q = "none"
+        ctrl-l = "center"
+        pagedown = "last-move"
+    "#).unwrap();
+    assert_eq!(key_map.get_binding(KeyType::Char(' ')), None);
+    assert_eq!(key_map.get_binding(KeyType::Char('f')), Some(InGameCommand::Enter));
+    assert_eq!(key_map.get_binding(KeyType::Char('Q')), None);
+    assert_eq!(key_map.get_binding(KeyType::Ctrl('l')), Some(InGameCommand::CenterView));
+    assert_eq!(key_map.get_binding(KeyType::PageDown), Some(InGameCommand::JumpToLastMove));
+    assert_eq!(key_map.get_binding(KeyType::Char('w')), Some(InGameCommand::Up));
+
+    // an empty file is the arrows preset
+    assert_eq!(KeyMap::parse("").unwrap(), KeyMap::default());
+}
+
+#[test]
+fn mistakes_in_the_file_are_reported() {
+    assert!(matches!(KeyMap::parse("preset = \"emacs\""), Err(KeyMapError::Parse(_))));
+    assert!(matches!(KeyMap::parse("[bindings]"), Err(KeyMapError::Parse(_))));
+    assert!(matches!(KeyMap::parse("[keys]\nf1 = \"undo\""), Err(KeyMapError::UnknownKey(key)) if key == "f1"));
+    assert!(matches!(KeyMap::parse("[keys]\nx = \"dance\""), Err(KeyMapError::UnknownCommand(key, command)) if key == "x" && command == "dance"));
+    assert!(matches!(KeyMap::parse("[keys]\nesc = \"undo\""), Err(KeyMapError::Reserved(_))));
+    assert!(matches!(KeyMap::parse("[keys]\nctrl-c = \"undo\""), Err(KeyMapError::Reserved(_))));
+    assert!(matches!(KeyMap::from_file(std::path::Path::new("/nonexistent/caro_keys.toml")), Err(KeyMapError::Io(..))));
+}
+
+#[test]
+fn key_names() {
+    assert_eq!(parse_key("x").unwrap(), KeyType::Char('x'));
+    assert_eq!(parse_key("X").unwrap(), KeyType::Char('X'));
+    assert_eq!(parse_key("Space").unwrap(), KeyType::Char(' '));
+    assert_eq!(parse_key("ctrl-R").unwrap(), KeyType::Ctrl('r'));
+    assert_eq!(parse_key("home").unwrap(), KeyType::Home);
+    assert!(parse_key("ctrl-1").is_err());
+    assert!(parse_key("").is_err());
+}
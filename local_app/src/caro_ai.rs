@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use caro_client::caro_protocol;
+use rand::Rng;
+
+const WIN_SCORE: i64 = 1_000_000;
+const SEARCH_RADIUS: i64 = 2;
+const HARD_REPLY_CANDIDATES: usize = 8;
+const EASY_TOP_CANDIDATES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Player1,
+    Player2,
+}
+
+impl Side {
+    pub fn opponent(&self) -> Side {
+        match self {
+            Side::Player1 => Side::Player2,
+            Side::Player2 => Side::Player1,
+        }
+    }
+}
+
+// mirrors the winning conditions of the rules implemented in simple_caro
+#[derive(Debug, Clone, Copy)]
+enum BlockRule {
+    Ignored,
+    NoBlockAllowed,
+    OneBlockAllowed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RuleProfile {
+    win_length: usize,
+    block_rule: BlockRule,
+}
+
+impl From<caro_protocol::GameRule> for RuleProfile {
+    fn from(rule: caro_protocol::GameRule) -> Self {
+        match rule {
+            caro_protocol::GameRule::TicTacToe => RuleProfile { win_length: 3, block_rule: BlockRule::Ignored },
+            caro_protocol::GameRule::FourBlockOne => RuleProfile { win_length: 4, block_rule: BlockRule::NoBlockAllowed },
+            caro_protocol::GameRule::FiveBlockTwo => RuleProfile { win_length: 5, block_rule: BlockRule::OneBlockAllowed },
+        }
+    }
+}
+
+pub struct BoardView {
+    height: usize,
+    width: usize,
+    tiles: HashMap<caro_protocol::Coordinate, Side>,
+}
+
+impl BoardView {
+    pub fn new(height: usize, width: usize,
+                player1_moves: &[caro_protocol::Coordinate],
+                player2_moves: &[caro_protocol::Coordinate]) -> Self {
+        let mut tiles = HashMap::new();
+        for coor in player1_moves {
+            tiles.insert(*coor, Side::Player1);
+        }
+        for coor in player2_moves {
+            tiles.insert(*coor, Side::Player2);
+        }
+        Self {
+            height,
+            width,
+            tiles,
+        }
+    }
+
+    fn on_board(&self, (latitude, longtitude): caro_protocol::Coordinate) -> bool {
+        latitude >= 0 && longtitude >= 0 && (latitude as usize) < self.height && (longtitude as usize) < self.width
+    }
+
+    fn tile(&self, coor: caro_protocol::Coordinate) -> Option<Side> {
+        self.tiles.get(&coor).copied()
+    }
+
+    fn place(&mut self, coor: caro_protocol::Coordinate, side: Side) {
+        self.tiles.insert(coor, side);
+    }
+
+    fn remove(&mut self, coor: caro_protocol::Coordinate) {
+        self.tiles.remove(&coor);
+    }
+
+    fn candidates(&self) -> Vec<caro_protocol::Coordinate> {
+        let mut candidates = Vec::new();
+        for &(latitude, longtitude) in self.tiles.keys() {
+            for d_lat in -SEARCH_RADIUS..=SEARCH_RADIUS {
+                for d_long in -SEARCH_RADIUS..=SEARCH_RADIUS {
+                    let coor = (latitude + d_lat, longtitude + d_long);
+                    if self.on_board(coor) && self.tile(coor).is_none() && !candidates.contains(&coor) {
+                        candidates.push(coor);
+                    }
+                }
+            }
+        }
+        candidates
+    }
+}
+
+pub struct CaroAi {
+    rule: RuleProfile,
+    difficulty: Difficulty,
+}
+
+impl CaroAi {
+    pub fn new(rule: caro_protocol::GameRule, difficulty: Difficulty) -> Self {
+        Self {
+            rule: RuleProfile::from(rule),
+            difficulty,
+        }
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn choose_move(&self, board: &BoardView, me: Side) -> Option<caro_protocol::Coordinate> {
+        self.search(board, me, self.difficulty)
+    }
+
+    // hints are always given at full strength
+    pub fn suggest_move(&self, board: &BoardView, me: Side) -> Option<caro_protocol::Coordinate> {
+        self.search(board, me, Difficulty::Hard)
+    }
+
+    fn search(&self, board: &BoardView, me: Side, difficulty: Difficulty) -> Option<caro_protocol::Coordinate> {
+        if board.tiles.is_empty() {
+            return self.opening_move(board);
+        }
+
+        let defense_weight = match difficulty {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal | Difficulty::Hard => 0.9,
+        };
+        let mut scored: Vec<(caro_protocol::Coordinate, i64)> = board.candidates()
+            .into_iter()
+            .map(|coor| {
+                let attack = self.evaluate(board, coor, me);
+                let defense = self.evaluate(board, coor, me.opponent());
+                let score = if attack >= WIN_SCORE {
+                    WIN_SCORE * 2
+                } else if defense >= WIN_SCORE {
+                    WIN_SCORE
+                } else {
+                    attack + (defense as f64 * defense_weight) as i64
+                };
+                (coor, score)
+            })
+            .collect();
+        scored.sort_by_key(|&(_coor, score)| std::cmp::Reverse(score));
+
+        match difficulty {
+            Difficulty::Easy => {
+                // a forced win or block is still taken, everything else is loosely picked
+                if let Some(&(coor, score)) = scored.first() && score >= WIN_SCORE {
+                    return Some(coor);
+                }
+                let top = scored.len().min(EASY_TOP_CANDIDATES);
+                if top == 0 {
+                    return None;
+                }
+                let pick = rand::rng().random_range(0..top);
+                Some(scored[pick].0)
+            },
+            Difficulty::Normal => {
+                scored.first().map(|&(coor, _score)| coor)
+            },
+            Difficulty::Hard => {
+                if let Some(&(coor, score)) = scored.first() && score >= WIN_SCORE {
+                    return Some(coor);
+                }
+                let mut lookahead_board = BoardView {
+                    height: board.height,
+                    width: board.width,
+                    tiles: board.tiles.clone(),
+                };
+                scored.into_iter()
+                    .take(HARD_REPLY_CANDIDATES)
+                    .map(|(coor, score)| {
+                        lookahead_board.place(coor, me);
+                        let best_reply = lookahead_board.candidates()
+                            .into_iter()
+                            .map(|reply| {
+                                self.evaluate(&lookahead_board, reply, me.opponent())
+                                    .max(self.evaluate(&lookahead_board, reply, me))
+                            })
+                            .max()
+                            .unwrap_or(0);
+                        lookahead_board.remove(coor);
+                        (coor, score - best_reply / 2)
+                    })
+                    .max_by(|a, b| a.1.cmp(&b.1))
+                    .map(|(coor, _score)| coor)
+            },
+        }
+    }
+
+    fn opening_move(&self, board: &BoardView) -> Option<caro_protocol::Coordinate> {
+        // keep the first stone inside the area the client shows initially
+        let height = board.height.min(caro_client::output_to_user::BOARD_HEIGHT);
+        let width = board.width.min(caro_client::output_to_user::BOARD_WIDTH);
+        if height == 0 || width == 0 {
+            return None;
+        }
+        Some(((height / 2) as i64, (width / 2) as i64))
+    }
+
+    // value of placing a stone of `side` at `coor`, summed over the four directions
+    fn evaluate(&self, board: &BoardView, coor: caro_protocol::Coordinate, side: Side) -> i64 {
+        const DIRECTIONS: [(i64, i64); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        let mut total = 0;
+        for (d_lat, d_long) in DIRECTIONS {
+            let (forward_run, forward_end) = self.scan(board, coor, (d_lat, d_long), side);
+            let (backward_run, backward_end) = self.scan(board, coor, (-d_lat, -d_long), side);
+            let run = 1 + forward_run + backward_run;
+            let value = self.line_value(run, forward_end, backward_end);
+            if value >= WIN_SCORE {
+                return WIN_SCORE;
+            }
+            total += value;
+        }
+        total
+    }
+
+    fn scan(&self, board: &BoardView, from: caro_protocol::Coordinate, (d_lat, d_long): (i64, i64), side: Side) -> (usize, LineEnd) {
+        let mut run = 0;
+        let mut coor = (from.0 + d_lat, from.1 + d_long);
+        loop {
+            if !board.on_board(coor) {
+                return (run, LineEnd::Edge);
+            }
+            match board.tile(coor) {
+                Some(tile) if tile == side => run += 1,
+                Some(_opponent) => return (run, LineEnd::Blocked),
+                None => return (run, LineEnd::Open),
+            }
+            coor = (coor.0 + d_lat, coor.1 + d_long);
+        }
+    }
+
+    fn line_value(&self, run: usize, end1: LineEnd, end2: LineEnd) -> i64 {
+        // the engine only counts an opponent stone as a block, the board edge is not one
+        let blocked = (end1 == LineEnd::Blocked) as usize + (end2 == LineEnd::Blocked) as usize;
+        if run >= self.rule.win_length {
+            let wins = match self.rule.block_rule {
+                BlockRule::Ignored => true,
+                BlockRule::NoBlockAllowed => blocked == 0,
+                BlockRule::OneBlockAllowed => blocked < 2,
+            };
+            if wins {
+                return WIN_SCORE;
+            }
+        }
+
+        let open = (end1 == LineEnd::Open) as i64 + (end2 == LineEnd::Open) as i64;
+        if open == 0 {
+            return 0;
+        }
+        let missing = self.rule.win_length.saturating_sub(run);
+        match (missing, open) {
+            (0, _) => 10,
+            (1, 2) => 50_000,
+            (1, _) => 5_000,
+            (2, 2) => 2_000,
+            (2, _) => 300,
+            (3, 2) => 150,
+            (3, _) => 30,
+            (_, open) => 10 * open,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineEnd {
+    Open,
+    Blocked,
+    Edge,
+}
@@ -0,0 +1,392 @@
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use caro_client::{
+    caro_protocol,
+    global_state,
+    input_from_user::{self, key_map},
+    output_to_user::{self, screen_entity}
+};
+
+use crate::{caro_ai, local_game, setup_entities};
+
+const COMPUTER_THINKING_DELAY: std::time::Duration = std::time::Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Setup,
+    Playing,
+    Over,
+}
+
+pub struct LocalApp {
+    global_state: Arc<RwLock<global_state::GlobalState>>,
+    screen_manager: output_to_user::ScreenManager,
+    setup_entities_vec: Vec<Box<dyn screen_entity::ScreenEntity>>,
+    settings: local_game::GameSettings,
+    game: Option<local_game::LocalGame>,
+    phase: Phase,
+    key_map: key_map::KeyMap,
+}
+
+impl LocalApp {
+    pub fn new() -> Self {
+        let global_state = Arc::new(RwLock::new(global_state::GlobalState::new()));
+        let mut screen_manager = output_to_user::ScreenManager::new(global_state.clone());
+        screen_manager.use_local_game_screen();
+        Self {
+            global_state,
+            screen_manager,
+            setup_entities_vec: setup_entities::get_setup_entities(),
+            settings: local_game::GameSettings::default(),
+            game: None,
+            phase: Phase::Setup,
+            key_map: key_map::KeyMap::default(),
+        }
+    }
+
+    pub fn set_key_map(&mut self, key_map: key_map::KeyMap) {
+        self.key_map = key_map;
+    }
+
+    pub async fn run(&mut self) {
+        self.show_setup().await;
+        loop {
+            let input = caro_console::input::get_user_input().await;
+            if let caro_console::input::InputType::Key(caro_console::input::KeyType::Exit) = input {
+                break;
+            }
+            let keep_running = match self.phase {
+                Phase::Setup => self.handle_setup_input(input).await,
+                Phase::Playing => self.handle_game_input(input).await,
+                Phase::Over => self.handle_game_over_input(input).await,
+            };
+            if !keep_running {
+                break;
+            }
+        }
+        self.screen_manager.clean();
+        caro_console::input::enable_prompt_mode_at(0, 0);
+    }
+
+    async fn show_setup(&mut self) {
+        self.phase = Phase::Setup;
+        self.game = None;
+        self.screen_manager.clear_game_result();
+        self.global_state.write().await.set_player_state(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected));
+        self.screen_manager.clean();
+        for entity in self.setup_entities_vec.iter() {
+            entity.display();
+        }
+        self.log_settings().await;
+        self.screen_manager.enable_prompt_mode().await;
+    }
+
+    async fn log_settings(&mut self) {
+        let rule = match self.settings.rule {
+            caro_protocol::GameRule::TicTacToe => "3",
+            caro_protocol::GameRule::FourBlockOne => "4",
+            caro_protocol::GameRule::FiveBlockTwo => "5",
+        };
+        let first = match self.settings.first_move {
+            local_game::FirstMove::Human => "me",
+            local_game::FirstMove::Computer => "ai",
+        };
+        let level = match self.settings.difficulty {
+            caro_ai::Difficulty::Easy => "easy",
+            caro_ai::Difficulty::Normal => "normal",
+            caro_ai::Difficulty::Hard => "hard",
+        };
+        self.screen_manager.log(format!("rule: {} first: {} level: {}", rule, first, level)).await;
+    }
+
+    async fn handle_setup_input(&mut self, input: caro_console::input::InputType) -> bool {
+        let line = match input {
+            caro_console::input::InputType::Text(line) => line,
+            caro_console::input::InputType::Key(_) => return true,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {},
+            ["rule", rule] => {
+                self.settings.rule = match *rule {
+                    "3" => caro_protocol::GameRule::TicTacToe,
+                    "4" => caro_protocol::GameRule::FourBlockOne,
+                    "5" => caro_protocol::GameRule::FiveBlockTwo,
+                    _ => {
+                        self.screen_manager.log(format!("Unknown rule: {}", rule)).await;
+                        return true;
+                    },
+                };
+                self.log_settings().await;
+            },
+            ["first", first] => {
+                self.settings.first_move = match *first {
+                    "me" => local_game::FirstMove::Human,
+                    "ai" => local_game::FirstMove::Computer,
+                    _ => {
+                        self.screen_manager.log(format!("Unknown side: {}", first)).await;
+                        return true;
+                    },
+                };
+                self.log_settings().await;
+            },
+            ["level", level] => {
+                self.settings.difficulty = match *level {
+                    "easy" => caro_ai::Difficulty::Easy,
+                    "normal" => caro_ai::Difficulty::Normal,
+                    "hard" => caro_ai::Difficulty::Hard,
+                    _ => {
+                        self.screen_manager.log(format!("Unknown level: {}", level)).await;
+                        return true;
+                    },
+                };
+                self.log_settings().await;
+            },
+            ["play"] => {
+                self.start_game().await;
+            },
+            ["exit"] => {
+                return false;
+            },
+            _ => {
+                self.screen_manager.log(format!("Invalid command: {}", line.trim())).await;
+            },
+        }
+        self.screen_manager.enable_prompt_mode().await;
+        true
+    }
+
+    async fn handle_game_over_input(&mut self, input: caro_console::input::InputType) -> bool {
+        match input {
+            caro_console::input::InputType::Text(line) => {
+                // the finished board stays on screen until the player moves on
+                if line.trim() == "play" || line.trim() == "rematch" {
+                    self.start_game().await;
+                    true
+                } else if line.trim() == "lobby" || line.trim() == "leave" {
+                    self.show_setup().await;
+                    true
+                } else {
+                    self.show_setup().await;
+                    self.handle_setup_input(caro_console::input::InputType::Text(line)).await
+                }
+            },
+            caro_console::input::InputType::Key(caro_console::input::KeyType::Esc) => {
+                self.screen_manager.enable_prompt_mode().await;
+                true
+            },
+            caro_console::input::InputType::Key(_) => true,
+        }
+    }
+
+    async fn handle_game_input(&mut self, input: caro_console::input::InputType) -> bool {
+        if let caro_console::input::InputType::Text(line) = &input && line.trim().is_empty() {
+            return true;
+        }
+        match self.key_map.to_user_command(input) {
+            input_from_user::UserCommand::InGame(command) => {
+                self.execute_ingame_command(command).await;
+            },
+            input_from_user::UserCommand::InRoom(input_from_user::InRoomCommand::LeaveRoom) => {
+                self.execute_ingame_command(input_from_user::InGameCommand::LeaveRoom).await;
+            },
+            input_from_user::UserCommand::General(input_from_user::GeneralCommand::ExitApplication) => {
+                return false;
+            },
+            input_from_user::UserCommand::General(input_from_user::GeneralCommand::Help(topic)) => {
+                self.screen_manager.log(input_from_user::command_parser::help_text(topic)).await;
+            },
+            input_from_user::UserCommand::General(input_from_user::GeneralCommand::ParseFailed(error)) => {
+                self.screen_manager.log(error.to_string()).await;
+            },
+            _ => {
+                self.screen_manager.log("Not available against the computer".to_string()).await;
+            },
+        }
+        true
+    }
+
+    async fn execute_ingame_command(&mut self, command: input_from_user::InGameCommand) {
+        let cursor_position = self.screen_manager.get_cursor_pos();
+        match command {
+            input_from_user::InGameCommand::Move(coor) => {
+                self.human_play(coor).await;
+            },
+            input_from_user::InGameCommand::Enter => {
+                self.human_play(cursor_position).await;
+            },
+            input_from_user::InGameCommand::Up => {
+                self.move_cursor((cursor_position.0 - 1, cursor_position.1)).await;
+            },
+            input_from_user::InGameCommand::Down => {
+                self.move_cursor((cursor_position.0 + 1, cursor_position.1)).await;
+            },
+            input_from_user::InGameCommand::Left => {
+                self.move_cursor((cursor_position.0, cursor_position.1 - 1)).await;
+            },
+            input_from_user::InGameCommand::Right => {
+                self.move_cursor((cursor_position.0, cursor_position.1 + 1)).await;
+            },
+            input_from_user::InGameCommand::Hint => {
+                let hint = self.game.as_ref().and_then(|game| game.hint());
+                if let Some(coor) = hint {
+                    self.move_cursor(coor).await;
+                    self.screen_manager.log(format!("Hint: try ({}, {})", coor.0, coor.1)).await;
+                } else {
+                    self.screen_manager.log("No hint available".to_string()).await;
+                }
+            },
+            input_from_user::InGameCommand::SwitchInputMode => {
+                if self.screen_manager.is_prompt_mode() {
+                    self.screen_manager.disable_prompt_mode();
+                } else {
+                    self.screen_manager.enable_prompt_mode().await;
+                }
+            },
+            input_from_user::InGameCommand::Rematch => {
+                self.screen_manager.log("Finish this game first".to_string()).await;
+            },
+            input_from_user::InGameCommand::LeaveRoom => {
+                self.show_setup().await;
+            },
+            input_from_user::InGameCommand::Undo => {
+                if self.game.as_mut().is_some_and(|game| game.undo()) {
+                    self.refresh_board_context();
+                    self.screen_manager.update_board_only().await;
+                } else {
+                    self.screen_manager.log("Nothing to undo".to_string()).await;
+                }
+            },
+            input_from_user::InGameCommand::Redo => {
+                if self.game.as_mut().is_some_and(|game| game.redo()) {
+                    self.refresh_board_context();
+                    self.screen_manager.update_board_only().await;
+                    self.check_game_over().await;
+                } else {
+                    self.screen_manager.log("Nothing to redo".to_string()).await;
+                }
+            },
+            input_from_user::InGameCommand::CenterView => {
+                self.screen_manager.center_view();
+                self.screen_manager.update_board_only().await;
+            },
+            input_from_user::InGameCommand::JumpToLastMove => {
+                match self.screen_manager.get_last_move() {
+                    Some(coor) => self.move_cursor(coor).await,
+                    None => self.screen_manager.log("No move yet".to_string()).await,
+                }
+            },
+            input_from_user::InGameCommand::ToggleChat => {
+                self.screen_manager.log("There is no chat against the computer".to_string()).await;
+            },
+        }
+    }
+
+    async fn start_game(&mut self) {
+        let game = local_game::LocalGame::new(self.settings);
+        let human_order = game.get_human_order();
+        self.game = Some(game);
+        self.phase = Phase::Playing;
+        self.screen_manager.clear_game_result();
+
+        self.global_state.write().await.set_player_state(caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
+        self.screen_manager.clean();
+        self.screen_manager.set_player_order(human_order);
+        self.screen_manager.set_cursor_pos(0, 0);
+        self.refresh_board_context();
+        self.screen_manager.update().await;
+        let mark = match human_order {
+            caro_protocol::PlayerOrder::Player1 => "X",
+            caro_protocol::PlayerOrder::Player2 => "O",
+        };
+        self.screen_manager.log(format!("Game started, you play {}", mark)).await;
+
+        if self.settings.first_move == local_game::FirstMove::Computer {
+            self.computer_play().await;
+        }
+        self.screen_manager.enable_prompt_mode().await;
+    }
+
+    async fn human_play(&mut self, coor: caro_protocol::Coordinate) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        if !game.is_human_turn() {
+            self.screen_manager.log("Not your turn".to_string()).await;
+            return;
+        }
+        match game.human_move(coor) {
+            simple_caro::MoveResult::Success => {
+                self.refresh_board_context();
+                self.screen_manager.update_board_only().await;
+                if !self.check_game_over().await {
+                    self.computer_play().await;
+                }
+            },
+            simple_caro::MoveResult::AlreadyOccupied => {
+                self.screen_manager.log(format!("Tile ({}, {}) already occupied", coor.0, coor.1)).await;
+            },
+            simple_caro::MoveResult::OutOfBound => {
+                self.screen_manager.log(format!("Tile ({}, {}) is out of the board", coor.0, coor.1)).await;
+            },
+            simple_caro::MoveResult::WrongTurn => {
+                self.screen_manager.log("Not your turn".to_string()).await;
+            },
+        }
+    }
+
+    async fn computer_play(&mut self) {
+        tokio::time::sleep(COMPUTER_THINKING_DELAY).await;
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        if game.computer_move().is_some() {
+            self.refresh_board_context();
+            self.screen_manager.update_board_only().await;
+        }
+        self.check_game_over().await;
+    }
+
+    async fn check_game_over(&mut self) -> bool {
+        let Some(game) = self.game.as_ref() else {
+            return false;
+        };
+        let human_order = game.get_human_order();
+        let result = match (game.get_state(), human_order) {
+            (caro_protocol::GameState::Player1Won, caro_protocol::PlayerOrder::Player1) |
+            (caro_protocol::GameState::Player2Won, caro_protocol::PlayerOrder::Player2) => "You won!",
+            (caro_protocol::GameState::Player1Won, _) |
+            (caro_protocol::GameState::Player2Won, _) => "The computer won!",
+            (caro_protocol::GameState::Drew, _) => "Draw!",
+            _ if game.is_board_full() => "Draw!",
+            _ => return false,
+        };
+        self.phase = Phase::Over;
+        if let Some(game_result) = game.get_result() {
+            self.screen_manager.set_game_result(&game_result);
+            self.screen_manager.update().await;
+        }
+        self.screen_manager.log(format!("{} type play for a rematch", result)).await;
+        true
+    }
+
+    async fn move_cursor(&mut self, coor: caro_protocol::Coordinate) {
+        self.screen_manager.set_cursor_pos(coor.0, coor.1);
+        self.screen_manager.update_board_only().await;
+    }
+
+    fn refresh_board_context(&mut self) {
+        if let Some(game) = self.game.as_ref() {
+            let game_context = game.get_context();
+            self.screen_manager.update_game_context(&game_context);
+        }
+    }
+}
+
+impl Default for LocalApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,4 @@
+pub mod caro_ai;
+pub mod game_loop;
+pub mod local_game;
+pub mod setup_entities;
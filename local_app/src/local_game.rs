@@ -0,0 +1,246 @@
+use caro_client::caro_protocol;
+use simple_caro;
+
+use crate::caro_ai;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstMove {
+    Human,
+    Computer,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GameSettings {
+    pub rule: caro_protocol::GameRule,
+    pub first_move: FirstMove,
+    pub difficulty: caro_ai::Difficulty,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            rule: caro_protocol::GameRule::FiveBlockTwo,
+            first_move: FirstMove::Human,
+            difficulty: caro_ai::Difficulty::Normal,
+        }
+    }
+}
+
+pub struct LocalGame {
+    game: simple_caro::SimpleCaro,
+    rule: caro_protocol::GameRule,
+    ai: caro_ai::CaroAi,
+    human_side: caro_ai::Side,
+    // (human move, computer reply) pairs taken back by undo, latest last
+    undone: Vec<(caro_protocol::Coordinate, caro_protocol::Coordinate)>,
+}
+
+impl LocalGame {
+    pub fn new(settings: GameSettings) -> Self {
+        // player 1 always moves first, so the side decides who opens
+        let human_side = match settings.first_move {
+            FirstMove::Human => caro_ai::Side::Player1,
+            FirstMove::Computer => caro_ai::Side::Player2,
+        };
+        Self {
+            game: new_engine(settings.rule),
+            rule: settings.rule,
+            ai: caro_ai::CaroAi::new(settings.rule, settings.difficulty),
+            human_side,
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn get_human_order(&self) -> caro_protocol::PlayerOrder {
+        match self.human_side {
+            caro_ai::Side::Player1 => caro_protocol::PlayerOrder::Player1,
+            caro_ai::Side::Player2 => caro_protocol::PlayerOrder::Player2,
+        }
+    }
+
+    pub fn is_human_turn(&self) -> bool {
+        matches!(
+            (self.game.get_state(), self.human_side),
+            (simple_caro::GameState::Player1Turn, caro_ai::Side::Player1) |
+            (simple_caro::GameState::Player2Turn, caro_ai::Side::Player2)
+        )
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.is_over()
+    }
+
+    // the engine never reports a draw by itself, a full board is one
+    pub fn is_board_full(&self) -> bool {
+        let tiles = self.game.get_board_height() * self.game.get_board_width();
+        self.game.occupied_tiles_count() as usize >= tiles
+    }
+
+    pub fn get_state(&self) -> caro_protocol::GameState {
+        match self.game.get_state() {
+            simple_caro::GameState::Player1Turn => caro_protocol::GameState::Player1Turn,
+            simple_caro::GameState::Player2Turn => caro_protocol::GameState::Player2Turn,
+            simple_caro::GameState::Player1Won => caro_protocol::GameState::Player1Won,
+            simple_caro::GameState::Player2Won => caro_protocol::GameState::Player2Won,
+            simple_caro::GameState::Drew => caro_protocol::GameState::Drew,
+            simple_caro::GameState::NotInprogress => caro_protocol::GameState::NotInprogress,
+        }
+    }
+
+    pub fn get_result(&self) -> Option<caro_protocol::GameResult> {
+        let rule = match self.rule {
+            caro_protocol::GameRule::TicTacToe => simple_caro::RuleType::TicTacToe,
+            caro_protocol::GameRule::FourBlockOne => simple_caro::RuleType::FourBlockOne,
+            caro_protocol::GameRule::FiveBlockTwo => simple_caro::RuleType::FiveBlockTwo,
+        };
+        match self.get_state() {
+            caro_protocol::GameState::Player1Won |
+            caro_protocol::GameState::Player2Won => Some(caro_protocol::GameResult {
+                game_state: self.get_state(),
+                reason: caro_protocol::GameEndReason::LineCompleted,
+                winning_line: self.game.get_winning_line(rule)
+                                    .into_iter()
+                                    .map(|tile| (tile.latitude, tile.longtitude))
+                                    .collect(),
+            }),
+            _ if self.is_board_full() => Some(caro_protocol::GameResult {
+                game_state: caro_protocol::GameState::Drew,
+                reason: caro_protocol::GameEndReason::BoardFull,
+                winning_line: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn human_move(&mut self, coor: caro_protocol::Coordinate) -> simple_caro::MoveResult {
+        let result = self.play(self.human_side, coor);
+        if let simple_caro::MoveResult::Success = result {
+            self.undone.clear();
+        }
+        result
+    }
+
+    pub fn computer_move(&mut self) -> Option<caro_protocol::Coordinate> {
+        let computer_side = self.human_side.opponent();
+        let coor = self.ai.choose_move(&self.get_board_view(), computer_side)?;
+        match self.play(computer_side, coor) {
+            simple_caro::MoveResult::Success => Some(coor),
+            _ => None,
+        }
+    }
+
+    // the human's last move goes back together with the computer's reply,
+    // so it is the human's turn again on the board as it was before
+    pub fn undo(&mut self) -> bool {
+        if !self.is_human_turn() {
+            return false;
+        }
+        let mut player1_moves = self.get_move_history(simple_caro::Participant::Player1);
+        let mut player2_moves = self.get_move_history(simple_caro::Participant::Player2);
+        let (human_moves, computer_moves) = match self.human_side {
+            caro_ai::Side::Player1 => (&mut player1_moves, &mut player2_moves),
+            caro_ai::Side::Player2 => (&mut player2_moves, &mut player1_moves),
+        };
+        let (Some(human_move), Some(computer_move)) = (human_moves.pop(), computer_moves.pop()) else {
+            return false;
+        };
+        // the engine's own undo misbehaves once a history runs empty,
+        // so the board is played again from the start without the pair
+        self.game = new_engine(self.rule);
+        for turn in 0..player1_moves.len().max(player2_moves.len()) {
+            if let Some(&coor) = player1_moves.get(turn) {
+                self.play(caro_ai::Side::Player1, coor);
+            }
+            if let Some(&coor) = player2_moves.get(turn) {
+                self.play(caro_ai::Side::Player2, coor);
+            }
+        }
+        self.undone.push((human_move, computer_move));
+        true
+    }
+
+    // plays back the pair the last undo took
+    pub fn redo(&mut self) -> bool {
+        if !self.is_human_turn() {
+            return false;
+        }
+        let Some((human_move, computer_move)) = self.undone.pop() else {
+            return false;
+        };
+        self.play(self.human_side, human_move);
+        self.play(self.human_side.opponent(), computer_move);
+        true
+    }
+
+    pub fn hint(&self) -> Option<caro_protocol::Coordinate> {
+        self.ai.suggest_move(&self.get_board_view(), self.human_side)
+    }
+
+    pub fn get_context(&self) -> caro_protocol::GameContext {
+        caro_protocol::GameContext {
+            // there is nobody to send deltas
+            seq: 0,
+            board_height: self.game.get_board_height(),
+            board_width: self.game.get_board_width(),
+            player1_move_history: self.get_move_history(simple_caro::Participant::Player1),
+            player2_move_history: self.get_move_history(simple_caro::Participant::Player2),
+            player1_undone_moves: Vec::new(),
+            player2_undone_moves: Vec::new(),
+            game_state: self.get_state(),
+            player1_connection_state: caro_protocol::ConnectState::Connected,
+            player2_connection_state: caro_protocol::ConnectState::Connected,
+            receiver_order: self.get_human_order(),
+        }
+    }
+
+    fn play(&mut self, side: caro_ai::Side, (latitude, longtitude): caro_protocol::Coordinate) -> simple_caro::MoveResult {
+        let result = self.game.player_move(participant(side), simple_caro::Coordinate {latitude, longtitude});
+        if let simple_caro::MoveResult::Success = result {
+            self.game.switch_turn();
+        }
+        result
+    }
+
+    fn get_move_history(&self, who: simple_caro::Participant) -> Vec<caro_protocol::Coordinate> {
+        self.game.get_moves_history(who)
+            .into_iter()
+            .map(|coor| (coor.latitude, coor.longtitude))
+            .collect()
+    }
+
+    fn get_board_view(&self) -> caro_ai::BoardView {
+        caro_ai::BoardView::new(
+            self.game.get_board_height(),
+            self.game.get_board_width(),
+            &self.get_move_history(simple_caro::Participant::Player1),
+            &self.get_move_history(simple_caro::Participant::Player2),
+        )
+    }
+}
+
+fn new_engine(rule: caro_protocol::GameRule) -> simple_caro::SimpleCaro {
+    let game = simple_caro::SimpleCaro::new();
+    match rule {
+        caro_protocol::GameRule::TicTacToe => {
+            game.set_rule(simple_caro::RuleType::TicTacToe);
+            game.set_board_size(3, 3);
+        }
+        caro_protocol::GameRule::FourBlockOne => {
+            game.set_rule(simple_caro::RuleType::FourBlockOne);
+            game.set_board_size(1024, 1024);
+        }
+        caro_protocol::GameRule::FiveBlockTwo => {
+            game.set_rule(simple_caro::RuleType::FiveBlockTwo);
+            game.set_board_size(1024, 1024);
+        }
+    }
+    game.start(simple_caro::GameState::Player1Turn);
+    game
+}
+
+fn participant(side: caro_ai::Side) -> simple_caro::Participant {
+    match side {
+        caro_ai::Side::Player1 => simple_caro::Participant::Player1,
+        caro_ai::Side::Player2 => simple_caro::Participant::Player2,
+    }
+}
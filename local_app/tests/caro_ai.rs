@@ -0,0 +1,68 @@
+use caro_client::caro_protocol;
+use local_app::caro_ai::{BoardView, CaroAi, Difficulty, Side};
+
+const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+fn row(latitude: i64, longtitudes: std::ops::RangeInclusive<i64>) -> Vec<caro_protocol::Coordinate> {
+    longtitudes.map(|longtitude| (latitude, longtitude)).collect()
+}
+
+#[test]
+fn a_win_is_taken_before_a_block() {
+    // both have an open four, the computer plays player 1 and moves now
+    let board = BoardView::new(20, 20, &row(5, 3..=6), &row(8, 3..=6));
+    for difficulty in DIFFICULTIES {
+        let ai = CaroAi::new(caro_protocol::GameRule::FiveBlockTwo, difficulty);
+        let coor = ai.choose_move(&board, Side::Player1);
+        assert!(matches!(coor, Some((5, 2)) | Some((5, 7))), "{:?} played {:?}", difficulty, coor);
+    }
+}
+
+#[test]
+fn an_open_four_is_blocked() {
+    let board = BoardView::new(20, 20, &[(15, 15), (0, 19)], &row(8, 3..=6));
+    for difficulty in DIFFICULTIES {
+        let ai = CaroAi::new(caro_protocol::GameRule::FiveBlockTwo, difficulty);
+        let coor = ai.choose_move(&board, Side::Player1);
+        assert!(matches!(coor, Some((8, 2)) | Some((8, 7))), "{:?} played {:?}", difficulty, coor);
+    }
+}
+
+#[test]
+fn a_blocked_line_only_wins_where_the_rule_allows() {
+    // player 1 can make four with one end blocked, player 2 threatens an open four
+    let player1_moves = row(5, 5..=7);
+    let mut player2_moves = row(9, 5..=7);
+    player2_moves.push((5, 4));
+
+    // with four in a row and no block allowed, the blocked line is worthless and the threat is met
+    let board = BoardView::new(20, 20, &player1_moves, &player2_moves);
+    let ai = CaroAi::new(caro_protocol::GameRule::FourBlockOne, Difficulty::Normal);
+    let coor = ai.choose_move(&board, Side::Player1);
+    assert!(matches!(coor, Some((9, 4)) | Some((9, 8))), "played {:?}", coor);
+
+    // five in a row with one block allowed wins through the blocked end
+    let mut player1_moves = player1_moves;
+    player1_moves.push((5, 8));
+    let board = BoardView::new(20, 20, &player1_moves, &player2_moves);
+    let ai = CaroAi::new(caro_protocol::GameRule::FiveBlockTwo, Difficulty::Normal);
+    assert_eq!(ai.choose_move(&board, Side::Player1), Some((5, 9)));
+}
+
+#[test]
+fn tic_tac_toe_ignores_blocks_and_edges() {
+    let board = BoardView::new(3, 3, &[(0, 0), (0, 1)], &[(1, 0), (1, 1)]);
+    let ai = CaroAi::new(caro_protocol::GameRule::TicTacToe, Difficulty::Normal);
+    assert_eq!(ai.choose_move(&board, Side::Player1), Some((0, 2)));
+    // asked for player 2, its own row wins
+    assert_eq!(ai.suggest_move(&board, Side::Player2), Some((1, 2)));
+}
+
+#[test]
+fn the_first_move_is_in_the_middle_of_the_shown_board() {
+    let board = BoardView::new(1024, 1024, &[], &[]);
+    let ai = CaroAi::new(caro_protocol::GameRule::FiveBlockTwo, Difficulty::Hard);
+    let (latitude, longtitude) = ai.choose_move(&board, Side::Player1).unwrap();
+    assert!((latitude as usize) < caro_client::output_to_user::BOARD_HEIGHT);
+    assert!((longtitude as usize) < caro_client::output_to_user::BOARD_WIDTH);
+}
@@ -0,0 +1,61 @@
+use caro_client::caro_protocol;
+use local_app::{
+    caro_ai::Difficulty,
+    local_game::{FirstMove, GameSettings, LocalGame}
+};
+
+fn moves(game: &LocalGame) -> (Vec<caro_protocol::Coordinate>, Vec<caro_protocol::Coordinate>) {
+    let context = game.get_context();
+    (context.player1_move_history, context.player2_move_history)
+}
+
+fn new_game(first_move: FirstMove) -> LocalGame {
+    LocalGame::new(GameSettings {
+        rule: caro_protocol::GameRule::FiveBlockTwo,
+        first_move,
+        difficulty: Difficulty::Normal,
+    })
+}
+
+#[test]
+fn undo_takes_back_the_move_and_the_reply() {
+    let mut game = new_game(FirstMove::Human);
+    // nothing was played yet
+    assert!(!game.undo());
+
+    assert!(matches!(game.human_move((5, 5)), simple_caro::MoveResult::Success));
+    let reply = game.computer_move().unwrap();
+    assert!(matches!(game.human_move((6, 6)), simple_caro::MoveResult::Success));
+    game.computer_move().unwrap();
+    let before = moves(&game);
+
+    assert!(game.undo());
+    assert_eq!(moves(&game), (vec![(5, 5)], vec![reply]));
+    assert!(game.is_human_turn());
+
+    assert!(game.redo());
+    assert_eq!(moves(&game), before);
+    assert!(game.is_human_turn());
+    // everything undone was played back
+    assert!(!game.redo());
+}
+
+#[test]
+fn the_computer_opening_is_not_undone_alone() {
+    let mut game = new_game(FirstMove::Computer);
+    let opening = game.computer_move().unwrap();
+    assert!(game.is_human_turn());
+    assert!(!game.undo());
+    assert_eq!(moves(&game), (vec![opening], vec![]));
+}
+
+#[test]
+fn a_new_move_replaces_what_was_undone() {
+    let mut game = new_game(FirstMove::Human);
+    assert!(matches!(game.human_move((5, 5)), simple_caro::MoveResult::Success));
+    let reply = game.computer_move().unwrap();
+    assert!(game.undo());
+    // the human now takes the tile the computer had replied on
+    assert!(matches!(game.human_move(reply), simple_caro::MoveResult::Success));
+    assert!(!game.redo());
+}
@@ -0,0 +1,198 @@
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle
+};
+
+use crate::{caro_protocol, client_request_executor, metrics};
+
+const HELP: &str = "\
+players               list players and their state
+rooms                 list rooms and who sits in them
+games                 list games and their state
+kick <pid>            remove a player and close its connection
+close <rid>           close a room, its game ends as aborted
+broadcast <message>   send a message to every player
+game <gid>            dump the context of a game
+quit                  close this console
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Help,
+    Players,
+    Rooms,
+    Games,
+    Kick(caro_protocol::PlayerId),
+    CloseRoom(caro_protocol::RoomId),
+    Broadcast(String),
+    DumpGame(caro_protocol::GameId),
+    Quit,
+    // nothing to do for a blank line
+    Empty,
+    Invalid(String),
+}
+
+pub fn parse_command(line: &str) -> AdminCommand {
+    let line = line.trim();
+    let (word, rest) = match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    };
+    match word {
+        "" => AdminCommand::Empty,
+        "help" => AdminCommand::Help,
+        "players" => AdminCommand::Players,
+        "rooms" => AdminCommand::Rooms,
+        "games" => AdminCommand::Games,
+        "kick" => match rest.parse() {
+            Ok(pid) => AdminCommand::Kick(pid),
+            Err(_) => AdminCommand::Invalid("usage: kick <pid>".to_string()),
+        },
+        "close" => match rest.parse() {
+            Ok(rid) => AdminCommand::CloseRoom(rid),
+            Err(_) => AdminCommand::Invalid("usage: close <rid>".to_string()),
+        },
+        "broadcast" if !rest.is_empty() => AdminCommand::Broadcast(rest.to_string()),
+        "broadcast" => AdminCommand::Invalid("usage: broadcast <message>".to_string()),
+        "game" => match rest.parse() {
+            Ok(gid) => AdminCommand::DumpGame(gid),
+            Err(_) => AdminCommand::Invalid("usage: game <gid>".to_string()),
+        },
+        "quit" | "exit" => AdminCommand::Quit,
+        _ => AdminCommand::Invalid(format!("unknown command {:?}, try help", word)),
+    }
+}
+
+// the text shown back to the operator
+pub async fn execute_command(command_executor: &client_request_executor::RequestExecutor, command: AdminCommand) -> String {
+    let mut output = String::new();
+    match command {
+        AdminCommand::Help => output.push_str(HELP),
+        AdminCommand::Players => {
+            let players = command_executor.player_statuses().await;
+            if players.is_empty() {
+                output.push_str("no players\n");
+            }
+            for player in players {
+                let room = player.rid.map(|rid| format!(" in room {}", rid)).unwrap_or_default();
+                let _ = writeln!(output, "player {}: {:?}{}", player.pid, player.state, room);
+            }
+        },
+        AdminCommand::Rooms => {
+            let status = command_executor.status().await;
+            if status.rooms.is_empty() {
+                output.push_str("no rooms\n");
+            }
+            for room in status.rooms {
+                let visibility = if room.private { ", private" } else { "" };
+                let game = match (room.gid, room.game_state) {
+                    (Some(gid), Some(game_state)) => format!(", game {} {:?}", gid, game_state),
+                    (Some(gid), None) => format!(", game {} not started", gid),
+                    (None, _) => String::new(),
+                };
+                let _ = writeln!(output, "room {}: {:?}{}, player1 {}, player2 {}{}",
+                    room.rid, room.rule, visibility, describe_seat(&room.player1), describe_seat(&room.player2), game);
+            }
+        },
+        AdminCommand::Games => {
+            let games = command_executor.game_statuses().await;
+            if games.is_empty() {
+                output.push_str("no games\n");
+            }
+            for game in games {
+                let _ = writeln!(output, "game {} in room {}: {:?}, {} moves", game.gid, game.rid, game.game_state, game.moves);
+            }
+        },
+        AdminCommand::Kick(pid) => match command_executor.kick_player(pid).await {
+            Ok(()) => { let _ = writeln!(output, "kicked player {}", pid); },
+            Err(err) => { let _ = writeln!(output, "error: {}", err); },
+        },
+        AdminCommand::CloseRoom(rid) => match command_executor.close_room(rid).await {
+            Ok(()) => { let _ = writeln!(output, "closed room {}", rid); },
+            Err(err) => { let _ = writeln!(output, "error: {}", err); },
+        },
+        AdminCommand::Broadcast(text) => {
+            let players = command_executor.broadcast(text).await;
+            let _ = writeln!(output, "sent to {} players", players);
+        },
+        AdminCommand::DumpGame(gid) => match command_executor.game_context(gid).await {
+            Ok(context) => { let _ = writeln!(output, "{:#?}", context); },
+            Err(err) => { let _ = writeln!(output, "error: {}", err); },
+        },
+        AdminCommand::Quit | AdminCommand::Empty => {},
+        AdminCommand::Invalid(reason) => { let _ = writeln!(output, "{}", reason); },
+    }
+    output
+}
+
+fn describe_seat(seat: &Option<metrics::SeatStatus>) -> String {
+    match seat {
+        Some(metrics::SeatStatus::Player { pid, state }) => format!("{} ({:?})", pid, state),
+        Some(metrics::SeatStatus::Reserved) => "reserved".to_string(),
+        None => "empty".to_string(),
+    }
+}
+
+// a line based console, e.g. `nc 127.0.0.1 9226`, meant to listen on a local address only
+pub struct AdminConsole {
+    listener: TcpListener,
+}
+
+impl AdminConsole {
+    pub async fn bind(addr: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn serve(self, command_executor: Arc<client_request_executor::RequestExecutor>) -> JoinHandle<()> {
+        tokio::spawn(
+            async move {
+                loop {
+                    let (stream, peer) = match self.listener.accept().await {
+                        Ok(accepted) => accepted,
+                        Err(err) => {
+                            tracing::warn!(%err, "cannot accept admin connection");
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        },
+                    };
+                    let command_executor = command_executor.clone();
+                    tokio::spawn(async move {
+                        tracing::info!(%peer, "admin connected");
+                        if let Err(err) = run_session(stream, &command_executor).await {
+                            tracing::debug!(%peer, %err, "admin connection failed");
+                        }
+                        tracing::info!(%peer, "admin disconnected");
+                    });
+                }
+            }
+        )
+    }
+}
+
+async fn run_session(stream: TcpStream, command_executor: &client_request_executor::RequestExecutor) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"caro admin console, type help for the commands\n> ").await?;
+    while let Some(line) = lines.next_line().await? {
+        let command = parse_command(&line);
+        if command == AdminCommand::Quit {
+            break;
+        }
+        if command != AdminCommand::Empty {
+            tracing::info!(command = line.trim(), "admin command");
+        }
+        let output = execute_command(command_executor, command).await;
+        writer.write_all(output.as_bytes()).await?;
+        writer.write_all(b"> ").await?;
+    }
+    Ok(())
+}
@@ -8,6 +8,10 @@ pub type Longtitude = i64;
 pub type RoomId = i32;
 pub type PlayerId = i32;
 pub type GameId = i32;
+pub type JoinCode = String;
+// handed to every connection, lets a player take its seat back after reconnecting
+pub type SessionToken = String;
+pub type RequestId = u64;
 pub type Coordinate = (Latitude, Longtitude);
 pub type Row = Vec<TileState>;
 
@@ -33,6 +37,8 @@ pub enum GameState {
     Player2Won,
     Drew,
     NotInprogress,
+    // closed by the server before anyone won
+    Aborted,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -54,8 +60,10 @@ pub enum PlayerOrder {
     Player2,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct GameContext {
+    // the last delta this context already contains
+    pub seq: u64,
     pub board_height: usize,
     pub board_width: usize,
     pub player1_move_history: Vec<Coordinate>,
@@ -68,18 +76,144 @@ pub struct GameContext {
     pub receiver_order: PlayerOrder,
 }
 
+impl GameContext {
+    // false if a delta in between was missed, the context is then left as it was
+    pub fn apply_delta(&mut self, delta: &GameDelta) -> bool {
+        if delta.seq <= self.seq {
+            // already part of the context
+            return true;
+        }
+        if delta.seq != self.seq + 1 {
+            return false;
+        }
+        let mut updated = self.clone();
+        for (order, coor) in delta.removed_moves.iter() {
+            let history = match order {
+                PlayerOrder::Player1 => &mut updated.player1_move_history,
+                PlayerOrder::Player2 => &mut updated.player2_move_history,
+            };
+            if history.pop() != Some(*coor) {
+                return false;
+            }
+        }
+        for (order, coor) in delta.added_moves.iter() {
+            match order {
+                PlayerOrder::Player1 => updated.player1_move_history.push(*coor),
+                PlayerOrder::Player2 => updated.player2_move_history.push(*coor),
+            }
+        }
+        if let Some(undone_moves) = &delta.player1_undone_moves {
+            updated.player1_undone_moves = undone_moves.clone();
+        }
+        if let Some(undone_moves) = &delta.player2_undone_moves {
+            updated.player2_undone_moves = undone_moves.clone();
+        }
+        if let Some(game_state) = delta.game_state {
+            updated.game_state = game_state;
+        }
+        if let Some(conn_state) = delta.player1_connection_state {
+            updated.player1_connection_state = conn_state;
+        }
+        if let Some(conn_state) = delta.player2_connection_state {
+            updated.player2_connection_state = conn_state;
+        }
+        updated.seq = delta.seq;
+        *self = updated;
+        true
+    }
+}
+
+// what changed in a game since the previous delta, only the parts that changed are set
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GameDelta {
+    pub seq: u64,
+    // latest first, as they are taken back
+    pub removed_moves: Vec<(PlayerOrder, Coordinate)>,
+    pub added_moves: Vec<(PlayerOrder, Coordinate)>,
+    pub player1_undone_moves: Option<Vec<Coordinate>>,
+    pub player2_undone_moves: Option<Vec<Coordinate>>,
+    pub game_state: Option<GameState>,
+    pub player1_connection_state: Option<ConnectState>,
+    pub player2_connection_state: Option<ConnectState>,
+}
+
+impl GameDelta {
+    // None if nothing changed between the two
+    pub fn between(seq: u64, old: &GameContext, new: &GameContext) -> Option<Self> {
+        let mut removed_moves = Vec::new();
+        let mut added_moves = Vec::new();
+        for (order, old_history, new_history) in [
+            (PlayerOrder::Player1, &old.player1_move_history, &new.player1_move_history),
+            (PlayerOrder::Player2, &old.player2_move_history, &new.player2_move_history),
+        ] {
+            let kept = old_history.iter().zip(new_history.iter()).take_while(|(old_move, new_move)| old_move == new_move).count();
+            removed_moves.extend(old_history[kept..].iter().rev().map(|coor| (order, *coor)));
+            added_moves.extend(new_history[kept..].iter().map(|coor| (order, *coor)));
+        }
+        let delta = Self {
+            seq,
+            removed_moves,
+            added_moves,
+            player1_undone_moves: (old.player1_undone_moves != new.player1_undone_moves).then(|| new.player1_undone_moves.clone()),
+            player2_undone_moves: (old.player2_undone_moves != new.player2_undone_moves).then(|| new.player2_undone_moves.clone()),
+            game_state: (old.game_state != new.game_state).then_some(new.game_state),
+            player1_connection_state: (old.player1_connection_state != new.player1_connection_state).then_some(new.player1_connection_state),
+            player2_connection_state: (old.player2_connection_state != new.player2_connection_state).then_some(new.player2_connection_state),
+        };
+        let unchanged = delta.removed_moves.is_empty() && delta.added_moves.is_empty()
+            && delta.player1_undone_moves.is_none() && delta.player2_undone_moves.is_none()
+            && delta.game_state.is_none()
+            && delta.player1_connection_state.is_none() && delta.player2_connection_state.is_none();
+        if unchanged {
+            None
+        } else {
+            Some(delta)
+        }
+    }
+}
+
+// private rooms are only reachable through their join code
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct RoomOptions {
+    pub private: bool,
+    pub password: Option<String>,
+    // the server's default size is used when not given, tic tac toe is always 3x3
+    #[serde(default)]
+    pub board_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RoomKey {
+    Id(RoomId),
+    Code(JoinCode),
+}
+
+// what the public room listing shows about a room
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomSummary {
+    pub rid: RoomId,
+    pub rule: GameRule,
+    pub players: usize,
+    pub has_password: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum GeneralRequest {
     PlayerRequestState,
     PlayerExitApplication,
     // response to check alive
     IAmAlive,
+    ResumeSession(SessionToken),
+    // a chat line for everyone seated in the player's room
+    Say(String),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum LoggedRequest {
-    RequestRoomAsPlayer1(GameRule),
-    JoinRoom(RoomId),
+    RequestRoomAsPlayer1(GameRule, RoomOptions),
+    // the password is only checked for rooms that have one
+    JoinRoom(RoomKey, Option<String>),
+    ListRooms,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -94,9 +228,11 @@ pub enum InGameRequest {
     PlayerRedo,
     PlayerRequestContext,
     PlayerLeaveRoom,
+    // play again once the game is over, starts when both players asked
+    RequestRematch,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PlayerCode {
     General(GeneralRequest),
     Logged(LoggedRequest),
@@ -104,31 +240,113 @@ pub enum PlayerCode {
     InGame(InGameRequest),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum GeneralResponse {
     State(PlayerState),
     // check alive
     AreYouAlive,
+    // pushed to everyone before the server closes the connections
+    ServerShuttingDown,
+    // pushed right after connecting, and again once a session was resumed
+    Session(SessionToken),
+    SessionResumed(RoomId, PlayerOrder),
+    // a message from the server operator to everyone
+    Broadcast(String),
+    // the operator removed this player, the connection is closed right after
+    Kicked,
+    // the operator closed the room, its players are back in the lobby
+    RoomClosed(RoomId),
+    // a chat line from the player seated in that order
+    Chat(PlayerOrder, String),
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JoinFailure {
+    NoSuchRoom,
+    RoomFull,
+    AlreadyInRoom,
+    WrongPassword,
+    TooManyAttempts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum LoggedResponse {
     JoinedRoomAsPlayer1(RoomId),
     JoinedRoomAsPlayer2(RoomId),
     FailedToCreateRoom,
-    FailedToJoinRoom(RoomId),
+    FailedToJoinRoom(RoomKey, JoinFailure),
+    RoomList(Vec<RoomSummary>),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum InRoomResponse {
     YourRoomIsFull(RoomId),
+    // sent to the creator of a private room so it can be shared
+    PrivateRoomCode(RoomId, JoinCode),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum InGameResponse {
     MoveSuccess,
-    MoveUnsuccess,
+    // a move, undo or redo the game refused
+    MoveRejected(MoveRejection),
+    // the whole game, sent on joining and when asked for
     Context(GameContext),
+    // pushed after every change, applies on top of the context
+    Delta(GameDelta),
+    // pushed to both players when the game ends
+    GameOver(GameResult),
+    // the opponent wants to play again
+    RematchOffered,
+    // both asked, the board is cleared and player1 moves first
+    RematchStarted,
+    // the seat of the opponent is empty from now on
+    OpponentLeft,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MoveRejection {
+    NotYourTurn,
+    AlreadyOccupied(Coordinate),
+    OutOfBounds(Coordinate),
+    NothingToUndo,
+    NothingToRedo,
+    // not started yet or already over
+    GameNotInProgress,
+    // a rematch asked for before the game ended
+    GameStillInProgress,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GameEndReason {
+    LineCompleted,
+    BoardFull,
+    // the loser left while the game was in progress
+    Forfeit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GameResult {
+    pub game_state: GameState,
+    pub reason: GameEndReason,
+    // the tiles of the line that won, from one end to the other, empty on a draw
+    pub winning_line: Vec<Coordinate>,
+}
+
+// sent back when a request could not be carried out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ErrorResponse {
+    UnknownPlayer,
+    NotInRoom,
+    NoSuchRoom(RoomId),
+    NoGameInRoom(RoomId),
+    ServerFull,
+    RuleNotAllowed(GameRule),
+    BoardSizeNotAllowed(usize),
+    NotAllowedInState(PlayerState),
+    UnknownSession,
+    // the request was dropped because too many were sent too quickly
+    RateLimited,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +355,7 @@ pub enum ServerCode {
     Logged(LoggedResponse),
     InRoom(InRoomResponse),
     InGame(InGameResponse),
+    Error(ErrorResponse),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,49 +364,90 @@ pub enum GenericCode {
     Server(ServerCode),
 }
 
+// a message on the wire, one json object per line
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessagePacket {
+    // given to a request by the client and repeated on every response to it, pushes have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<RequestId>,
     code: GenericCode,
 }
 
 impl<'a> MessagePacket {
     pub fn new_server_packet(code: ServerCode) -> Self {
         Self {
+            id: None,
             code: GenericCode::Server(code),
         }
     }
 
     pub fn new_player_packet(code: PlayerCode) -> Self {
         Self {
+            id: None,
             code: GenericCode::Player(code),
         }
     }
 
+    pub fn with_id(mut self, id: Option<RequestId>) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn id(&self) -> Option<RequestId> {
+        self.id
+    }
+
     pub fn code(&self) -> GenericCode {
         self.code.clone()
     }
 
     pub fn to_serial(self) -> Vec<u8> {
-        let json_str = serde_json::to_string(&self.code).unwrap();
-        json_str.as_bytes().to_vec()
+        let mut serial = serde_json::to_vec(&self).unwrap();
+        serial.push(FRAME_DELIMITER);
+        serial
+    }
+
+    // garbage from the other side is reported instead of panicking
+    pub fn from_serial(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
     }
 }
 
-pub trait ToMessagePacket {
-    fn to_message_packet(self) -> MessagePacket;
+// json never contains a raw newline, so it safely ends every message
+pub const FRAME_DELIMITER: u8 = b'\n';
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+// splits the bytes of a connection back into messages, however they were cut by the reads
+#[derive(Debug, Default)]
+pub struct FrameBuffer {
+    pending: Vec<u8>,
 }
 
-impl ToMessagePacket for &[u8] {
-    fn to_message_packet(self) -> MessagePacket {
-        let json_str = String::from_utf8_lossy(self);
-        let code: GenericCode = serde_json::from_str(&json_str).unwrap();
-        match code {
-            GenericCode::Server(server_code) => {
-                MessagePacket::new_server_packet(server_code)
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    // Err once a message grew past MAX_FRAME_SIZE, the bytes read so far are dropped
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, usize>> {
+        match self.pending.iter().position(|byte| *byte == FRAME_DELIMITER) {
+            Some(end) => {
+                let mut frame: Vec<u8> = self.pending.drain(..=end).collect();
+                frame.pop();
+                Some(Ok(frame))
             },
-            GenericCode::Player(player_code) => {
-                MessagePacket::new_player_packet(player_code)
+            None if self.pending.len() > MAX_FRAME_SIZE => {
+                let dropped = self.pending.len();
+                self.pending.clear();
+                Some(Err(dropped))
             },
+            None => None,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,221 +1,741 @@
-use std::sync::Arc;
+use std::sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc};
 
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, Notify, RwLock, RwLockWriteGuard};
+use tracing::Instrument;
 
 use crate::{
     caro_protocol,
+    event_bus::{EventBus, ServerEvent},
+    game_archive,
     game_manager,
+    metrics,
     player_manager,
-    room_manager
+    player_state_machine::{self, StateEvent},
+    room_manager,
+    server_error::{ServerError, ServerResult},
+    snapshot
 };
 
-pub struct RequestExecutor {
-    player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
-    room_manager: Arc<RwLock<room_manager::RoomContainer>>,
-    game_manager: Arc<RwLock<game_manager::GameContainer>>,
+// longer chat lines are cut, the log box of the client is small anyway
+const MAX_CHAT_LENGTH: usize = 200;
+
+tokio::task_local! {
+    // the player whose request is being handled and the id its responses are marked with
+    static CURRENT_REQUEST: (caro_protocol::PlayerId, caro_protocol::RequestId);
 }
 
-impl RequestExecutor {
-    pub fn new(player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
-                room_manager: Arc<RwLock<room_manager::RoomContainer>>,
-                game_manager: Arc<RwLock<game_manager::GameContainer>>) -> Self {
-        Self {
-            player_manager,
-            room_manager,
-            game_manager,
+// all managers locked at once, always in this order, so a request is applied as a whole
+// and no other task sees a room halfway through a change, each request checks what may
+// refuse it before its first change so a refused request leaves everything as it was
+struct Transaction<'a> {
+    players: RwLockWriteGuard<'a, player_manager::PlayerContainer>,
+    rooms: RwLockWriteGuard<'a, room_manager::RoomContainer>,
+    games: RwLockWriteGuard<'a, game_manager::GameContainer>,
+    // delivered once the managers are unlocked again
+    responses: Vec<(caro_protocol::PlayerId, caro_protocol::ServerCode)>,
+    events: Vec<ServerEvent>,
+    // true if the player is sent the whole context instead of a delta
+    contexts: Vec<(caro_protocol::PlayerId, bool)>,
+    // already posted to the games, only how they ended is awaited after unlocking
+    forfeits: Vec<(caro_protocol::RoomId, caro_protocol::GameId, oneshot::Receiver<Option<caro_protocol::GameState>>)>,
+}
+
+impl Transaction<'_> {
+    fn respond(&mut self, pid: caro_protocol::PlayerId, code: caro_protocol::ServerCode) {
+        self.responses.push((pid, code));
+    }
+
+    fn publish(&mut self, event: ServerEvent) {
+        match &event {
+            ServerEvent::PlayerJoined { rid, .. } |
+            ServerEvent::PlayerLeft { rid, .. } => {
+                tracing::Span::current().record("rid", rid);
+            },
+            ServerEvent::GameStarted { rid, gid, .. } => {
+                tracing::Span::current().record("rid", rid).record("gid", gid);
+            },
+            _ => {},
+        }
+        self.events.push(event);
+    }
+
+    // the context comes from the game's own task, so it is fetched after unlocking,
+    // the other player of the game gets a delta
+    fn push_game_context(&mut self, pid: caro_protocol::PlayerId) {
+        self.contexts.push((pid, true));
+    }
+
+    fn push_game_delta(&mut self, pid: caro_protocol::PlayerId) {
+        self.contexts.push((pid, false));
+    }
+
+    fn forfeit(&mut self, rid: caro_protocol::RoomId, loser: game_manager::PlayerOrder) {
+        let game = self.games.find_game_contain_room(rid).and_then(|gid| self.games.get_handle(gid));
+        if let Ok(game) = game {
+            tracing::Span::current().record("gid", game.get_gid());
+            if let Ok(outcome) = game.forfeit(loser) {
+                self.forfeits.push((rid, game.get_gid(), outcome));
+            }
         }
     }
 
-    pub async fn execute_request(&mut self, pid: i32, request_type: caro_protocol::PlayerCode) {
-        let player_state = self.player_manager.read().await.get_player_state(pid).unwrap();
+    fn execute_request(&mut self, pid: i32, request_type: caro_protocol::PlayerCode) -> ServerResult<()> {
+        let player_state = self.players.get_player_state(pid)?;
+        if !player_state_machine::request_allowed(player_state, &request_type) {
+            return Err(ServerError::NotAllowedInState(player_state));
+        }
         match request_type {
             caro_protocol::PlayerCode::General(code) => {
-                self.execute_general_request(pid, code).await;
+                self.execute_general_request(pid, code)
             },
             caro_protocol::PlayerCode::Logged(code) => {
-                if player_state == caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected) {
-                    self.execute_logged_request(pid, code).await;
-                }
+                self.execute_logged_request(pid, code)
             },
             caro_protocol::PlayerCode::InRoom(code) => {
-                if player_state == caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected) {
-                    self.execute_inroom_request(pid, code).await;
-                }
+                self.execute_inroom_request(pid, code)
             },
             caro_protocol::PlayerCode::InGame(code) => {
-                if player_state == caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected) {
-                    self.execute_ingame_request(pid, code).await;
-                }
+                self.execute_ingame_request(pid, code)
             },
         }
     }
 
-    async fn execute_general_request(&mut self, pid: i32, code: caro_protocol::GeneralRequest) {
+    fn execute_general_request(&mut self, pid: i32, code: caro_protocol::GeneralRequest) -> ServerResult<()> {
         match code {
             caro_protocol::GeneralRequest::PlayerExitApplication => {
-                self.clean_player_existence(pid).await;
+                self.clean_player_existence(pid);
             },
-            caro_protocol::GeneralRequest::PlayerRequestState => {
-                let player_state = self.player_manager.read().await.get_player_state(pid).unwrap();
-                let code = caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(player_state));
-                let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-                self.player_manager.write().await.response(pid, new_packet).await;
-            }
-            caro_protocol::GeneralRequest::IAmAlive => {
-                self.player_manager.write().await.mark_as_responsed_to_checkalive(pid);
+            caro_protocol::GeneralRequest::ResumeSession(session_token) => {
+                self.resume_session(pid, session_token)?;
             }
+            // answered by RequestExecutor::execute_query without a transaction
+            caro_protocol::GeneralRequest::PlayerRequestState |
+            caro_protocol::GeneralRequest::IAmAlive |
+            caro_protocol::GeneralRequest::Say(_) => {},
         }
+        Ok(())
     }
 
-    async fn execute_logged_request(&mut self, pid: i32, code: caro_protocol::LoggedRequest) {
-        let room_manager_clone = self.room_manager.clone();
-        let player_manager_clone = self.player_manager.clone();
-        let game_manager_clone = self.game_manager.clone();
-        let room_full_actions = |rid: i32| async move {
-            let gid = game_manager_clone.read().await.find_game_contain_room(rid).unwrap();
-            game_manager_clone.write().await.try_start_game(gid);
-            let (pid1, pid2) = room_manager_clone.read().await.get_pids_in_room(rid).unwrap();
-            let code = caro_protocol::ServerCode::InRoom(caro_protocol::InRoomResponse::YourRoomIsFull(rid));
-            let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
-            player_manager_clone.write().await.response(pid1, new_packet.clone()).await;
-            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
-            player_manager_clone.write().await.response(pid2, new_packet).await;
+    // takes over the seat of the session, either kept since a restart
+    // or still held by the connection the player lost
+    fn resume_session(&mut self, pid: i32, session_token: caro_protocol::SessionToken) -> ServerResult<()> {
+        let player_state = self.players.get_player_state(pid)?;
+        if player_state != caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected) {
+            return Err(ServerError::NotAllowedInState(player_state));
+        }
+        // everything that may refuse the resume is checked before a seat changes hands
+        let old_pid = match self.players.find_player_by_session(&session_token) {
+            Some(old_pid) if old_pid != pid => Some(old_pid),
+            Some(_own_pid) => return Err(ServerError::SessionNotFound),
+            None => None,
+        };
+        let rid = match old_pid {
+            Some(old_pid) => self.rooms.find_room_contain_player(old_pid).map_err(|_| ServerError::SessionNotFound)?,
+            None => self.rooms.find_reservation(&session_token)?,
+        };
+        self.check_room_can_start(rid, old_pid)?;
 
-            player_manager_clone.write().await.set_player_state(pid1, caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
-            player_manager_clone.write().await.set_player_state(pid2, caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
+        let seat = match old_pid {
+            Some(old_pid) => {
+                let (pid1, _pid2) = self.rooms.get_pids_in_room(rid)?;
+                let seat = if pid1 == old_pid {
+                    room_manager::PlayerOrder::Player1(pid)
+                } else {
+                    room_manager::PlayerOrder::Player2(pid)
+                };
+                self.rooms.remove_player_from_room(rid, old_pid)?;
+                self.rooms.add_player_to_room(rid, seat)?;
+                self.publish(ServerEvent::PlayerLeft { pid: old_pid, rid });
+                self.players.remove_player(old_pid)?;
+                seat
+            },
+            None => self.rooms.claim_reservation(&session_token, pid)?.1,
+        };
+        self.players.adopt_session(pid, session_token.clone())?;
+        self.players.apply_event(pid, StateEvent::EnterRoom)?;
+        if self.rooms.game_started(rid) {
+            self.players.apply_event(pid, StateEvent::GameStarted)?;
+        }
+
+        let order = match seat {
+            room_manager::PlayerOrder::Player1(_) => caro_protocol::PlayerOrder::Player1,
+            room_manager::PlayerOrder::Player2(_) => caro_protocol::PlayerOrder::Player2,
         };
+        let player_state = self.players.get_player_state(pid)?;
+        self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Session(session_token)));
+        self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(player_state)));
+        self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::SessionResumed(rid, order)));
+        self.publish(ServerEvent::PlayerJoined { pid, rid, order });
+
+        if self.rooms.game_started(rid) {
+            self.push_game_context(pid);
+        } else if self.rooms.room_full(rid) {
+            self.start_full_room(rid)?;
+        }
+        Ok(())
+    }
 
+    // the room must be able to start its game once it fills, so nobody is seated in a room
+    // that is then left half started, `changing` is the seat holder being replaced or seated
+    fn check_room_can_start(&self, rid: caro_protocol::RoomId, changing: Option<caro_protocol::PlayerId>) -> ServerResult<()> {
+        self.games.find_game_contain_room(rid)?;
+        let (pid1, pid2) = self.rooms.get_pids_in_room(rid)?;
+        for pid in [pid1, pid2] {
+            if pid == -1 || Some(pid) == changing {
+                continue;
+            }
+            match self.players.get_player_state(pid)? {
+                caro_protocol::PlayerState::InRoom(_) | caro_protocol::PlayerState::InGame(_) => {},
+                player_state => return Err(ServerError::IllegalTransition(player_state, StateEvent::GameStarted)),
+            }
+        }
+        Ok(())
+    }
+
+    fn start_full_room(&mut self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let gid = self.games.find_game_contain_room(rid)?;
+        let (pid1, pid2) = self.rooms.get_pids_in_room(rid)?;
+        // both players must be able to enter the game before anything is started
+        let mut entering = Vec::new();
+        for pid in [pid1, pid2] {
+            match self.players.get_player_state(pid)? {
+                caro_protocol::PlayerState::InRoom(_) => entering.push(pid),
+                // a player who stayed in a running game is already in game
+                caro_protocol::PlayerState::InGame(_) => {},
+                player_state => return Err(ServerError::IllegalTransition(player_state, StateEvent::GameStarted)),
+            }
+        }
+        self.games.start_game(gid)?;
+        self.rooms.mark_game_started(rid)?;
+        let code = caro_protocol::ServerCode::InRoom(caro_protocol::InRoomResponse::YourRoomIsFull(rid));
+        self.respond(pid1, code.clone());
+        self.respond(pid2, code);
+        for pid in entering {
+            self.players.apply_event(pid, StateEvent::GameStarted)?;
+        }
+        self.publish(ServerEvent::GameStarted { rid, gid, player1: pid1, player2: pid2 });
+        // the first update of a game is the whole context for both players
+        self.push_game_context(pid1);
+        Ok(())
+    }
+
+    fn execute_logged_request(&mut self, pid: i32, code: caro_protocol::LoggedRequest) -> ServerResult<()> {
         match code {
-            caro_protocol::LoggedRequest::RequestRoomAsPlayer1(rule_type) => {
-                let new_rid = self.room_manager.write().await.add_room(rule_type);
-                if new_rid == -1 {
-                    let code = caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::FailedToCreateRoom);
-                    let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-                    self.player_manager.write().await.response(pid, new_packet).await;
-                    return;
+            caro_protocol::LoggedRequest::RequestRoomAsPlayer1(rule_type, options) => {
+                let board_size = options.board_size;
+                let new_rid = self.rooms.add_room_with_options(rule_type, options)?;
+                let new_gid = match self.games.add_game(new_rid, rule_type, board_size) {
+                    Ok(new_gid) => new_gid,
+                    Err(err) => {
+                        self.rooms.remove_room(new_rid)?;
+                        return Err(err);
+                    },
+                };
+                let seated = self.rooms.add_player_to_room(new_rid, room_manager::PlayerOrder::Player1(pid))
+                    .and_then(|_| self.players.apply_event(pid, StateEvent::EnterRoom));
+                if let Err(err) = seated {
+                    self.games.remove_game(new_gid)?;
+                    self.rooms.remove_room(new_rid)?;
+                    return Err(err);
                 }
-                let _result = self.room_manager.write().await.add_player_to_room(new_rid, room_manager::PlayerOrder::Player1(pid));
-                if !_result {
-                    let code = caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::FailedToJoinRoom(new_rid));
-                    let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-                    self.player_manager.write().await.response(pid, new_packet).await;
-                } else {
-                    let _new_gid = self.game_manager.write().await.add_game(new_rid, rule_type);
-                    self.player_manager.write().await.set_player_state(pid, caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected));
-                    let code = caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(new_rid));
-                    let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-                    self.player_manager.write().await.response(pid, new_packet).await;
-                    if self.room_manager.read().await.room_full(new_rid) {
-                        room_full_actions(new_rid).await;
-                    }
+                self.respond(pid, caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(new_rid)));
+                if let Some(join_code) = self.rooms.get_join_code(new_rid) {
+                    self.respond(pid, caro_protocol::ServerCode::InRoom(caro_protocol::InRoomResponse::PrivateRoomCode(new_rid, join_code)));
+                }
+                self.publish(ServerEvent::PlayerJoined { pid, rid: new_rid, order: caro_protocol::PlayerOrder::Player1 });
+                if self.rooms.room_full(new_rid) {
+                    self.start_full_room(new_rid)?;
                 }
             },
-            caro_protocol::LoggedRequest::JoinRoom(rid) => {
-                let _result = self.room_manager.write().await.add_player_to_room(rid, room_manager::PlayerOrder::Player2(pid));
-                self.player_manager.write().await.set_player_state(pid, caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected));
-                let (pid1, pid2) = self.room_manager.read().await.get_pids_in_room(rid).unwrap();
-                let code = if pid == pid1 {
-                    caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid))
-                } else if pid == pid2 {
-                    caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer2(rid))
-                } else {
-                    caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::FailedToJoinRoom(rid))
+            caro_protocol::LoggedRequest::JoinRoom(key, password) => {
+                let peer_ip = self.players.get_peer_ip(pid)?;
+                let (rid, seat) = self.rooms.join_room(key, pid, peer_ip, password)?;
+                // which room the key leads to is only known once seated, so a room that
+                // cannot start gets its seat back instead of being checked first
+                let entered = self.check_room_can_start(rid, Some(pid))
+                    .and_then(|_| self.players.apply_event(pid, StateEvent::EnterRoom));
+                if let Err(err) = entered {
+                    self.rooms.remove_player_from_room(rid, pid)?;
+                    return Err(err);
+                }
+                let (code, order) = match seat {
+                    room_manager::PlayerOrder::Player1(_) => (caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid), caro_protocol::PlayerOrder::Player1),
+                    room_manager::PlayerOrder::Player2(_) => (caro_protocol::LoggedResponse::JoinedRoomAsPlayer2(rid), caro_protocol::PlayerOrder::Player2),
                 };
-                let new_packet = caro_protocol::MessagePacket::new_server_packet(code);
-                self.player_manager.write().await.response(pid, new_packet).await;
-                if self.room_manager.read().await.room_full(rid) {
-                    room_full_actions(rid).await;
+                self.respond(pid, caro_protocol::ServerCode::Logged(code));
+                self.publish(ServerEvent::PlayerJoined { pid, rid, order });
+                if self.rooms.room_full(rid) {
+                    self.start_full_room(rid)?;
                 }
             },
+            // answered by RequestExecutor::execute_query without a transaction
+            caro_protocol::LoggedRequest::ListRooms => {},
         }
-    }  
+        Ok(())
+    }
 
-    async fn execute_inroom_request(&mut self, pid: i32, code: caro_protocol::InRoomRequest) {
+    fn execute_inroom_request(&mut self, pid: i32, code: caro_protocol::InRoomRequest) -> ServerResult<()> {
         match code {
             caro_protocol::InRoomRequest::PlayerLeaveRoom => {
-                let rid = self.room_manager.read().await.find_room_contain_player(pid).unwrap();
-                self.room_manager.write().await.remove_player_from_room(rid, pid);
-                let room_empty = self.room_manager.read().await.room_empty(rid);
-                if room_empty {
-                    self.room_manager.write().await.remove_room(rid);
-                }
+                let rid = self.rooms.find_room_contain_player(pid)?;
+                self.leave_room(rid, pid)?;
+                let player_state = self.players.get_player_state(pid)?;
+                self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(player_state)));
             },
         }
+        Ok(())
     }
 
-    async fn execute_ingame_request(&mut self, pid: i32, code: caro_protocol::InGameRequest) {
-        let rid = self.room_manager.read().await.find_room_contain_player(pid).unwrap();
-        let gid = self.game_manager.read().await.find_game_contain_room(rid).unwrap();
+    // moves, undo and redo never get here, they are played by the game's own task
+    fn execute_ingame_request(&mut self, pid: i32, code: caro_protocol::InGameRequest) -> ServerResult<()> {
+        if let caro_protocol::InGameRequest::PlayerLeaveRoom = code {
+            let rid = self.rooms.find_room_contain_player(pid)?;
+            self.leave_room(rid, pid)?;
+            let player_state = self.players.get_player_state(pid)?;
+            self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(player_state)));
+        }
+        Ok(())
+    }
 
-        match code {
-            caro_protocol::InGameRequest::PlayerLeaveRoom => {
-                self.room_manager.write().await.remove_player_from_room(rid, pid);
-                let room_empty = self.room_manager.read().await.room_empty(rid);
-                if room_empty {
-                    self.game_manager.write().await.remove_game(gid);
-                    self.room_manager.write().await.remove_room(rid);
+    // every way out of a room ends here, so a game in progress is always forfeited by whoever leaves it
+    fn leave_room(&mut self, rid: caro_protocol::RoomId, pid: i32) -> ServerResult<()> {
+        let (pid1, _pid2) = self.rooms.get_pids_in_room(rid)?;
+        player_state_machine::next_state(self.players.get_player_state(pid)?, StateEvent::LeaveRoom)?;
+        let game_started = self.rooms.game_started(rid);
+        if game_started {
+            let loser = if pid == pid1 { game_manager::PlayerOrder::Player1 } else { game_manager::PlayerOrder::Player2 };
+            self.forfeit(rid, loser);
+            self.rooms.mark_game_ended(rid)?;
+        }
+        self.players.apply_event(pid, StateEvent::LeaveRoom)?;
+        self.rooms.remove_player_from_room(rid, pid)?;
+        self.publish(ServerEvent::PlayerLeft { pid, rid });
+        // whoever stays behind in a started game is told and sees the free seat
+        if let (true, Ok((pid1, pid2))) = (game_started, self.rooms.get_pids_in_room(rid)) {
+            for remaining_pid in [pid1, pid2] {
+                if remaining_pid != -1 {
+                    self.respond(remaining_pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::OpponentLeft));
+                    self.push_game_delta(remaining_pid);
+                }
+            }
+        }
+        if self.rooms.room_empty(rid) {
+            if let Ok(gid) = self.games.find_game_contain_room(rid) {
+                self.games.remove_game(gid)?;
+            }
+            self.rooms.remove_room(rid)?;
+        }
+        Ok(())
+    }
+
+    // everyone seated is sent back to the lobby and the game ends as aborted
+    fn close_room(&mut self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let (pid1, pid2) = self.rooms.get_pids_in_room(rid)?;
+        for pid in [pid1, pid2] {
+            if pid != -1 {
+                player_state_machine::next_state(self.players.get_player_state(pid)?, StateEvent::LeaveRoom)?;
+            }
+        }
+        for pid in [pid1, pid2] {
+            if pid == -1 {
+                continue;
+            }
+            self.players.apply_event(pid, StateEvent::LeaveRoom)?;
+            self.rooms.remove_player_from_room(rid, pid)?;
+            self.respond(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::RoomClosed(rid)));
+            self.publish(ServerEvent::PlayerLeft { pid, rid });
+        }
+        if let Ok(gid) = self.games.find_game_contain_room(rid) {
+            if self.rooms.game_started(rid) {
+                self.publish(ServerEvent::GameEnded { rid, gid, state: caro_protocol::GameState::Aborted });
+            }
+            self.games.remove_game(gid)?;
+        }
+        self.rooms.remove_room(rid)
+    }
+
+    fn clean_player_existence(&mut self, pid: i32) {
+        // players dropped by the heartbeat may not have joined any room yet
+        if let Ok(rid) = self.rooms.find_room_contain_player(pid) {
+            let _ = self.leave_room(rid, pid);
+        }
+        let _ = self.players.remove_player(pid);
+    }
+}
+
+// the line goes back to its sender too, so both see the chat in the same order
+fn chat_lines(rooms: &room_manager::RoomContainer, pid: caro_protocol::PlayerId, text: String) -> ServerResult<Vec<(caro_protocol::PlayerId, caro_protocol::ServerCode)>> {
+    let rid = rooms.find_room_contain_player(pid)?;
+    let (pid1, pid2) = rooms.get_pids_in_room(rid)?;
+    let order = if pid == pid1 { caro_protocol::PlayerOrder::Player1 } else { caro_protocol::PlayerOrder::Player2 };
+    let text: String = text.trim().chars().take(MAX_CHAT_LENGTH).collect();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok([pid1, pid2].into_iter()
+        .filter(|&seated_pid| seated_pid != -1)
+        .map(|seated_pid| (seated_pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Chat(order, text.clone()))))
+        .collect())
+}
+
+pub struct RequestExecutor {
+    player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
+    room_manager: Arc<RwLock<room_manager::RoomContainer>>,
+    game_manager: Arc<RwLock<game_manager::GameContainer>>,
+    event_bus: EventBus,
+    // requests being executed right now, shutdown waits for them
+    in_flight: AtomicUsize,
+    idle: Notify,
+    shutting_down: AtomicBool,
+}
+
+impl RequestExecutor {
+    pub fn new(player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
+                room_manager: Arc<RwLock<room_manager::RoomContainer>>,
+                game_manager: Arc<RwLock<game_manager::GameContainer>>) -> Self {
+        Self {
+            player_manager,
+            room_manager,
+            game_manager,
+            event_bus: EventBus::new(),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    // subscribers such as logging or persistence listen here
+    pub fn event_bus(&self) -> EventBus {
+        self.event_bus.clone()
+    }
+
+    async fn begin(&self) -> Transaction<'_> {
+        Transaction {
+            players: self.player_manager.write().await,
+            rooms: self.room_manager.write().await,
+            games: self.game_manager.write().await,
+            responses: Vec::new(),
+            events: Vec::new(),
+            contexts: Vec::new(),
+            forfeits: Vec::new(),
+        }
+    }
+
+    async fn commit(&self, transaction: Transaction<'_>) {
+        let Transaction { players, rooms, games, responses, events, contexts, forfeits } = transaction;
+        drop(games);
+        drop(rooms);
+        drop(players);
+        // a forfeit comes before the leave that caused it
+        for (rid, gid, outcome) in forfeits {
+            if let Ok(Some(state)) = outcome.await {
+                self.event_bus.publish(ServerEvent::GameEnded { rid, gid, state });
+            }
+        }
+        for event in events {
+            self.event_bus.publish(event);
+        }
+        for (pid, code) in responses {
+            self.send(pid, code).await;
+        }
+        for (pid, full_context) in contexts {
+            let _ = self.update_game(pid, full_context).await;
+        }
+    }
+
+    // a refused request has changed nothing, so nothing it queued goes out next to the error,
+    // it never got as far as a forfeit either
+    async fn finish(&self, mut transaction: Transaction<'_>, result: ServerResult<()>) -> ServerResult<()> {
+        if result.is_err() {
+            transaction.responses.clear();
+            transaction.events.clear();
+            transaction.contexts.clear();
+        }
+        self.commit(transaction).await;
+        result
+    }
+
+    async fn send(&self, pid: caro_protocol::PlayerId, code: caro_protocol::ServerCode) {
+        let responser = self.player_manager.read().await.get_responser(pid);
+        if let Ok(responser) = responser {
+            // whatever reaches the requester while its request is handled answers that request
+            let request_id = CURRENT_REQUEST.try_with(|(requester, id)| (*requester == pid).then_some(*id)).ok().flatten();
+            let new_packet = caro_protocol::MessagePacket::new_server_packet(code).with_id(request_id);
+            responser.write().await.send_response(new_packet).await;
+        }
+    }
+
+    // executes the request and reports a failure back to the player instead of dropping it
+    pub async fn handle_request(&self, pid: i32, request_id: Option<caro_protocol::RequestId>, request_type: caro_protocol::PlayerCode) {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        // rid and gid are filled in once the request found its room
+        let span = tracing::debug_span!("request", pid, id = ?request_id, request = ?request_type, rid = tracing::field::Empty, gid = tracing::field::Empty);
+        let handling = async {
+            if self.shutting_down.load(Ordering::Acquire) {
+                self.send(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::ServerShuttingDown)).await;
+            } else if let Err(err) = self.execute_request(pid, request_type).await {
+                tracing::debug!(%err, "request refused");
+                self.send(pid, err.to_response()).await;
+            }
+        }.instrument(span);
+        match request_id {
+            Some(request_id) => CURRENT_REQUEST.scope((pid, request_id), handling).await,
+            None => handling.await,
+        }
+        if self.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    // new requests are refused from now on and every player is told so
+    pub async fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Release);
+        let pids = self.player_manager.read().await.get_pids();
+        for pid in pids {
+            self.send(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::ServerShuttingDown)).await;
+        }
+    }
+
+    // returns false if requests were still running when the timeout passed
+    pub async fn drain(&self, timeout: std::time::Duration) -> bool {
+        let wait_idle = async {
+            loop {
+                // registered before the check so a notification in between is not lost
+                let notified = self.idle.notified();
+                if self.in_flight.load(Ordering::Acquire) == 0 {
+                    break;
                 }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait_idle).await.is_ok()
+    }
+
+    // games that are still being played, finished or pending ones are not worth keeping
+    pub async fn archive_games(&self) -> Vec<game_archive::ArchivedGame> {
+        let running = {
+            let rooms = self.room_manager.read().await;
+            let games = self.game_manager.read().await;
+            let mut running = Vec::new();
+            for gid in games.get_gids() {
+                let game = match games.get_handle(gid) {
+                    Ok(game) => game,
+                    Err(_) => continue,
+                };
+                let rid = game.get_rid();
+                let rule = rooms.get_rule_in_room(rid);
+                let pids = rooms.get_pids_in_room(rid);
+                if let (Ok(rule), Ok((player1, player2))) = (rule, pids) {
+                    running.push((game, rule, player1, player2));
+                }
+            }
+            running
+        };
+
+        let mut archived = Vec::new();
+        for (game, rule, player1, player2) in running {
+            let context = match game.get_context().await {
+                Ok(context) => context,
+                Err(_) => continue,
+            };
+            match context.game_state {
+                caro_protocol::GameState::Player1Turn | caro_protocol::GameState::Player2Turn => {
+                    archived.push(game_archive::ArchivedGame {
+                        rid: game.get_rid(),
+                        gid: game.get_gid(),
+                        rule,
+                        player1,
+                        player2,
+                        board_height: context.board_height,
+                        board_width: context.board_width,
+                        player1_move_history: context.player1_move_history,
+                        player2_move_history: context.player2_move_history,
+                        game_state: context.game_state,
+                    });
+                },
+                _ => {},
+            }
+        }
+        archived.sort_by_key(|game| game.rid);
+        archived
+    }
+
+    pub async fn execute_request(&self, pid: i32, request_type: caro_protocol::PlayerCode) -> ServerResult<()> {
+        match request_type {
+            caro_protocol::PlayerCode::InGame(code) if code != caro_protocol::InGameRequest::PlayerLeaveRoom => {
+                self.execute_game_request(pid, code).await
             },
-            caro_protocol::InGameRequest::PlayerRequestContext => {
-                self.response_game_context(pid).await;
+            caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerRequestState) |
+            caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::IAmAlive) |
+            caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say(_)) |
+            caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms) => {
+                self.execute_query(pid, request_type).await
             },
             _ => {
-                // do nothing
+                let mut transaction = self.begin().await;
+                let result = transaction.execute_request(pid, request_type);
+                self.finish(transaction, result).await
+            },
+        }
+    }
+
+    // requests that change no room only take the locks they read under, so they neither wait
+    // for nor hold up each other or the games, a heartbeat only touches its own player
+    async fn execute_query(&self, pid: i32, request_type: caro_protocol::PlayerCode) -> ServerResult<()> {
+        if let caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::IAmAlive) = request_type {
+            let mut players = self.player_manager.write().await;
+            players.get_player_state(pid)?;
+            players.mark_as_responsed_to_checkalive(pid);
+            return Ok(());
+        }
+        let responses = {
+            let players = self.player_manager.read().await;
+            let player_state = players.get_player_state(pid)?;
+            if !player_state_machine::request_allowed(player_state, &request_type) {
+                return Err(ServerError::NotAllowedInState(player_state));
+            }
+            match request_type {
+                caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say(text)) => {
+                    let rooms = self.room_manager.read().await;
+                    chat_lines(&rooms, pid, text)?
+                },
+                caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms) => {
+                    let rooms = self.room_manager.read().await.list_public_rooms();
+                    vec![(pid, caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::RoomList(rooms)))]
+                },
+                _ => vec![(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(player_state)))],
             }
+        };
+        for (pid, code) in responses {
+            self.send(pid, code).await;
         }
+        Ok(())
+    }
 
-        let (pid1, pid2) = self.room_manager.read().await.get_pids_in_room(rid).unwrap();
-        let player_order = if pid == pid1 {
-            game_manager::PlayerOrder::Player1
+    // only looks the game up under read locks, the game itself is played in its own task,
+    // so moves in different rooms run side by side until a player is seated or removed,
+    // which still write locks every container
+    async fn execute_game_request(&self, pid: i32, code: caro_protocol::InGameRequest) -> ServerResult<()> {
+        let (rid, game, pid1, pid2) = {
+            let players = self.player_manager.read().await;
+            let player_state = players.get_player_state(pid)?;
+            if !player_state_machine::request_allowed(player_state, &caro_protocol::PlayerCode::InGame(code)) {
+                return Err(ServerError::NotAllowedInState(player_state));
+            }
+            let rooms = self.room_manager.read().await;
+            let games = self.game_manager.read().await;
+            let rid = rooms.find_room_contain_player(pid)?;
+            let (pid1, pid2) = rooms.get_pids_in_room(rid)?;
+            let game = games.get_handle(games.find_game_contain_room(rid)?)?;
+            (rid, game, pid1, pid2)
+        };
+        tracing::Span::current().record("rid", rid).record("gid", game.get_gid());
+
+        if let caro_protocol::InGameRequest::PlayerRequestContext = code {
+            return self.update_game(pid, true).await;
+        }
+
+        let (player_order, order) = if pid == pid1 {
+            (game_manager::PlayerOrder::Player1, caro_protocol::PlayerOrder::Player1)
         } else if pid == pid2 {
-            game_manager::PlayerOrder::Player2
+            (game_manager::PlayerOrder::Player2, caro_protocol::PlayerOrder::Player2)
         } else {
-            return;
+            return Err(ServerError::PlayerNotInRoom(pid));
         };
-        
-        let result = self.game_manager.write().await.execute_command_in_game(gid, player_order, code).unwrap();
 
-        match result {
-            game_manager::OperationResult::Successfully(_game_state) => {
+        // nobody is left to agree to a rematch
+        if code == caro_protocol::InGameRequest::RequestRematch && (pid1 == -1 || pid2 == -1) {
+            self.send(pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::OpponentLeft)).await;
+            return Ok(());
+        }
 
+        let result = game.execute_command(player_order, code).await?;
+
+        match result {
+            game_manager::OperationResult::Successfully(state) if code == caro_protocol::InGameRequest::RequestRematch => {
+                match state {
+                    caro_protocol::GameState::Player1Turn |
+                    caro_protocol::GameState::Player2Turn => {
+                        for seated_pid in [pid1, pid2] {
+                            self.send(seated_pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::RematchStarted)).await;
+                        }
+                        self.event_bus.publish(ServerEvent::GameStarted { rid, gid: game.get_gid(), player1: pid1, player2: pid2 });
+                    },
+                    // the opponent still has to agree, the board stays as it is
+                    _ => {
+                        let opponent = if pid == pid1 { pid2 } else { pid1 };
+                        if opponent != -1 {
+                            self.send(opponent, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::RematchOffered)).await;
+                        }
+                        return Ok(());
+                    },
+                }
             },
-            game_manager::OperationResult::Unsuccessfully(_game_state) => {
-                
+            game_manager::OperationResult::Successfully(state) => {
+                let gid = game.get_gid();
+                if let caro_protocol::InGameRequest::PlayerMove(coordinate) = code {
+                    self.send(pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveSuccess)).await;
+                    self.event_bus.publish(ServerEvent::MoveApplied { rid, gid, order, coordinate });
+                }
+                match state {
+                    caro_protocol::GameState::Player1Won |
+                    caro_protocol::GameState::Player2Won |
+                    caro_protocol::GameState::Drew => {
+                        self.event_bus.publish(ServerEvent::GameEnded { rid, gid, state });
+                    },
+                    _ => {},
+                }
+            },
+            game_manager::OperationResult::Unsuccessfully(reason) => {
+                tracing::debug!(?reason, "move rejected");
+                // nothing changed, so there is nothing to push
+                self.send(pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveRejected(reason))).await;
+                return Ok(());
             },
         }
 
-        self.response_game_context(pid1).await;
-        self.response_game_context(pid2).await;
+        self.update_game(pid, false).await
     }
 
-    async fn response_game_context(&self, pid: i32) {
-        let rid = self.room_manager.read().await.find_room_contain_player(pid).unwrap();
-        let gid = self.game_manager.read().await.find_game_contain_room(rid).unwrap();
-        let (pid1, pid2) = self.room_manager.read().await.get_pids_in_room(rid).unwrap();
-        let internal_game_context = self.game_manager.read().await.get_context_in_game(gid).unwrap();
-
-        let player1_state = self.player_manager.read().await.get_player_state(pid1).unwrap();
-        let player1_connection_state = match player1_state {
-            caro_protocol::PlayerState::InGame(conn_state) => conn_state,
-            _ => caro_protocol::ConnectState::Disconnected,
+    // brings the players of pid's game up to date, pid gets the whole context if full_context is set,
+    // everyone else only what changed since the last update
+    async fn update_game(&self, pid: i32, full_context: bool) -> ServerResult<()> {
+        let game = {
+            let rooms = self.room_manager.read().await;
+            let games = self.game_manager.read().await;
+            let rid = rooms.find_room_contain_player(pid)?;
+            games.get_handle(games.find_game_contain_room(rid)?)?
         };
+        // kept locked until everyone was sent the update, so deltas go out in sequence
+        let mut pushed_view = game.lock_pushed_view().await;
+        let (pid1, pid2, player1_connection_state, player2_connection_state) = {
+            let players = self.player_manager.read().await;
+            let rooms = self.room_manager.read().await;
+            let (pid1, pid2) = rooms.get_pids_in_room(game.get_rid())?;
 
-        let player2_state = self.player_manager.read().await.get_player_state(pid2).unwrap();
-        let player2_connection_state = match player2_state {
-            caro_protocol::PlayerState::InGame(conn_state) => conn_state,
-            _ => caro_protocol::ConnectState::Disconnected,
-        };
-
-        let receiver_order = if pid == pid1 {
-            caro_protocol::PlayerOrder::Player1
-        } else if pid == pid2 {
-            caro_protocol::PlayerOrder::Player2
-        } else {
-            return;
+            // an empty seat counts as a disconnected player
+            let player1_connection_state = match players.get_player_state(pid1) {
+                Ok(caro_protocol::PlayerState::InGame(conn_state)) => conn_state,
+                _ => caro_protocol::ConnectState::Disconnected,
+            };
+            let player2_connection_state = match players.get_player_state(pid2) {
+                Ok(caro_protocol::PlayerState::InGame(conn_state)) => conn_state,
+                _ => caro_protocol::ConnectState::Disconnected,
+            };
+            (pid1, pid2, player1_connection_state, player2_connection_state)
         };
+        if pid != pid1 && pid != pid2 {
+            return Err(ServerError::PlayerNotInRoom(pid));
+        }
 
-        let game_context = caro_protocol::GameContext {
+        let internal_game_context = game.get_context().await?;
+        let game_result = internal_game_context.end_reason.map(|reason| caro_protocol::GameResult {
+            game_state: internal_game_context.game_state,
+            reason,
+            winning_line: internal_game_context.winning_line.clone(),
+        });
+        let mut game_context = caro_protocol::GameContext {
+            seq: pushed_view.seq,
             board_height: internal_game_context.board_height,
             board_width: internal_game_context.board_width,
             player1_move_history: internal_game_context.player1_move_history,
@@ -225,24 +745,271 @@ impl RequestExecutor {
             game_state: internal_game_context.game_state,
             player1_connection_state,
             player2_connection_state,
-            receiver_order,
+            receiver_order: caro_protocol::PlayerOrder::Player1,
+        };
+        // nobody has anything to apply a delta on before the first update
+        let first_update = pushed_view.context.is_none();
+        let delta = match &pushed_view.context {
+            Some(pushed_context) => caro_protocol::GameDelta::between(pushed_view.seq + 1, pushed_context, &game_context),
+            None => None,
         };
+        // everyone hears about the end once, whoever is sent the whole context hears it again
+        let just_ended = game_result.is_some() && matches!(&delta, Some(delta) if delta.game_state.is_some());
+        if let Some(delta) = &delta {
+            pushed_view.seq = delta.seq;
+            game_context.seq = delta.seq;
+        }
+        pushed_view.context = Some(game_context.clone());
+
+        // a seat may be empty, e.g. while a restored game waits for a player
+        for (seated_pid, receiver_order) in [(pid1, caro_protocol::PlayerOrder::Player1), (pid2, caro_protocol::PlayerOrder::Player2)] {
+            if seated_pid == -1 {
+                continue;
+            }
+            let whole_context = first_update || (full_context && seated_pid == pid);
+            if whole_context {
+                let game_context = caro_protocol::GameContext { receiver_order, ..game_context.clone() };
+                self.send(seated_pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Context(game_context))).await;
+            } else if let Some(delta) = &delta {
+                self.send(seated_pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Delta(delta.clone()))).await;
+            }
+            match &game_result {
+                Some(game_result) if whole_context || just_ended => {
+                    self.send(seated_pid, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::GameOver(game_result.clone()))).await;
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn clean_player_existence(&self, pid: i32) {
+        let mut transaction = self.begin().await;
+        transaction.clean_player_existence(pid);
+        self.commit(transaction).await;
+    }
+
+    pub async fn kick_player(&self, pid: caro_protocol::PlayerId) -> ServerResult<()> {
+        // nothing the player still sends or has queued is executed from here on
+        {
+            let mut players = self.player_manager.write().await;
+            players.get_player_state(pid)?;
+            players.stop_handling_request(pid).await;
+        }
+        self.send(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Kicked)).await;
+        // dropping the player closes its connection
+        self.clean_player_existence(pid).await;
+        Ok(())
+    }
+
+    pub async fn close_room(&self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let mut transaction = self.begin().await;
+        let result = transaction.close_room(rid);
+        self.finish(transaction, result).await
+    }
+
+    // returns how many players it was sent to
+    pub async fn broadcast(&self, text: String) -> usize {
+        let pids = self.player_manager.read().await.get_pids();
+        for pid in pids.iter() {
+            self.send(*pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Broadcast(text.clone()))).await;
+        }
+        pids.len()
+    }
+
+    pub async fn player_statuses(&self) -> Vec<metrics::PlayerStatus> {
+        let players = self.player_manager.read().await;
+        let rooms = self.room_manager.read().await;
+        let mut pids = players.get_pids();
+        pids.sort();
+        pids.into_iter()
+            .filter_map(|pid| {
+                let state = players.get_player_state(pid).ok()?;
+                Some(metrics::PlayerStatus { pid, state, rid: rooms.find_room_contain_player(pid).ok() })
+            })
+            .collect()
+    }
+
+    pub async fn game_statuses(&self) -> Vec<metrics::GameStatus> {
+        let handles = {
+            let games = self.game_manager.read().await;
+            let mut gids = games.get_gids();
+            gids.sort();
+            gids.into_iter().filter_map(|gid| games.get_handle(gid).ok()).collect::<Vec<_>>()
+        };
+        let mut statuses = Vec::new();
+        for game in handles {
+            if let Ok(context) = game.get_context().await {
+                statuses.push(metrics::GameStatus {
+                    gid: game.get_gid(),
+                    rid: game.get_rid(),
+                    game_state: context.game_state,
+                    moves: context.player1_move_history.len() + context.player2_move_history.len(),
+                });
+            }
+        }
+        statuses
+    }
+
+    pub async fn game_context(&self, gid: caro_protocol::GameId) -> ServerResult<game_manager::InternalGameContext> {
+        let game = self.game_manager.read().await.get_handle(gid)?;
+        game.get_context().await
+    }
+
+    // a new connection learns the token it needs to come back to its seat
+    pub async fn send_session(&self, pid: caro_protocol::PlayerId) {
+        let session_token = self.player_manager.read().await.get_session_token(pid);
+        if let Ok(session_token) = session_token {
+            self.send(pid, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Session(session_token))).await;
+        }
+    }
 
-        let code = caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Context(game_context));
-        let new_message_packet = caro_protocol::MessagePacket::new_server_packet(code);
-        self.player_manager.write().await.response(pid, new_message_packet).await;
+    pub async fn snapshot(&self) -> snapshot::ServerSnapshot {
+        let rooms = {
+            let players = self.player_manager.read().await;
+            let rooms = self.room_manager.read().await;
+            let games = self.game_manager.read().await;
+            let mut rids = rooms.get_rids();
+            rids.sort();
+            let mut snapshots = Vec::new();
+            for rid in rids {
+                let (Ok(rule), Ok(options), Ok((pid1, pid2)), Ok((reservation1, reservation2))) = (
+                    rooms.get_rule_in_room(rid),
+                    rooms.get_room_options(rid),
+                    rooms.get_pids_in_room(rid),
+                    rooms.get_reservations(rid),
+                ) else {
+                    continue;
+                };
+                // a seat belongs to whoever sits there, or to whoever it is still kept for
+                let player1 = players.get_session_token(pid1).ok().or(reservation1);
+                let player2 = players.get_session_token(pid2).ok().or(reservation2);
+                let game = if rooms.game_started(rid) {
+                    games.find_game_contain_room(rid).and_then(|gid| games.get_handle(gid)).ok()
+                } else {
+                    None
+                };
+                let room = snapshot::RoomSnapshot {
+                    rid,
+                    rule,
+                    options,
+                    join_code: rooms.get_join_code(rid),
+                    player1,
+                    player2,
+                    game: None,
+                };
+                snapshots.push((room, game));
+            }
+            snapshots
+        };
+
+        let mut snapshot = snapshot::ServerSnapshot::default();
+        for (mut room, game) in rooms {
+            if let Some(game) = game {
+                let Ok(context) = game.get_context().await else {
+                    continue;
+                };
+                room.game = Some(snapshot::GameSnapshot {
+                    board_size: context.board_height,
+                    moves: snapshot::order_moves(&context.player1_move_history, &context.player2_move_history),
+                    turn: context.game_state,
+                });
+            }
+            snapshot.rooms.push(room);
+        }
+        snapshot
     }
 
-    pub async fn clean_player_existence(&mut self, pid: i32) {
-        let rid = self.room_manager.read().await.find_room_contain_player(pid).unwrap();
-        let gid = self.game_manager.read().await.find_game_contain_room(rid).unwrap();
-        self.room_manager.write().await.remove_player_from_room(rid, pid);
-        let room_empty = self.room_manager.read().await.room_empty(rid);
-        if room_empty {
-            self.game_manager.write().await.remove_game(gid);
-            self.room_manager.write().await.remove_room(rid);
+    pub async fn status(&self) -> metrics::ServerStatus {
+        let (players, rooms) = {
+            let players = self.player_manager.read().await;
+            let rooms = self.room_manager.read().await;
+            let games = self.game_manager.read().await;
+            let mut rids = rooms.get_rids();
+            rids.sort();
+            let mut statuses = Vec::new();
+            for rid in rids {
+                let (Ok(rule), Ok(options), Ok((pid1, pid2)), Ok((reservation1, reservation2))) = (
+                    rooms.get_rule_in_room(rid),
+                    rooms.get_room_options(rid),
+                    rooms.get_pids_in_room(rid),
+                    rooms.get_reservations(rid),
+                ) else {
+                    continue;
+                };
+                let seat = |pid: caro_protocol::PlayerId, reservation: Option<caro_protocol::SessionToken>| {
+                    match (players.get_player_state(pid), reservation) {
+                        (Ok(state), _) => Some(metrics::SeatStatus::Player { pid, state }),
+                        (Err(_), Some(_)) => Some(metrics::SeatStatus::Reserved),
+                        (Err(_), None) => None,
+                    }
+                };
+                let gid = games.find_game_contain_room(rid).ok();
+                let game = match gid {
+                    Some(gid) if rooms.game_started(rid) => games.get_handle(gid).ok(),
+                    _ => None,
+                };
+                let room = metrics::RoomStatus {
+                    rid,
+                    rule,
+                    private: options.private,
+                    player1: seat(pid1, reservation1),
+                    player2: seat(pid2, reservation2),
+                    gid,
+                    game_state: None,
+                };
+                statuses.push((room, game));
+            }
+            (players.get_pids().len(), statuses)
+        };
+
+        let mut status = metrics::ServerStatus {
+            players,
+            rooms: Vec::new(),
+        };
+        for (mut room, game) in rooms {
+            if let Some(game) = game {
+                room.game_state = game.get_context().await.ok().map(|context| context.game_state);
+            }
+            status.rooms.push(room);
+        }
+        status
+    }
+
+    // rooms keep their ids and wait for their players for reserved_for,
+    // a room that cannot be brought back is left out and reported
+    pub async fn restore(&self, snapshot: &snapshot::ServerSnapshot, reserved_for: std::time::Duration) -> Vec<(caro_protocol::RoomId, ServerError)> {
+        let mut rooms = self.room_manager.write().await;
+        let mut games = self.game_manager.write().await;
+        let mut failures = Vec::new();
+        for room in snapshot.rooms.iter() {
+            let reservations = (room.player1.clone(), room.player2.clone());
+            if let Err(err) = rooms.restore_room(room.rid, room.rule, room.options.clone(), room.join_code.clone(), reservations, reserved_for) {
+                failures.push((room.rid, err));
+                continue;
+            }
+            let result = match &room.game {
+                Some(game) => games.restore_game(room.rid, room.rule, game.board_size, &game.moves, game.turn)
+                    .and_then(|_gid| rooms.mark_game_started(room.rid)),
+                None => games.add_game(room.rid, room.rule, room.options.board_size).map(|_gid| ()),
+            };
+            if let Err(err) = result {
+                let _ = rooms.remove_room(room.rid);
+                failures.push((room.rid, err));
+            }
         }
-        self.player_manager.write().await.remove_player(pid);
+        failures
     }
 
-}
\ No newline at end of file
+    // rooms nobody came back to are closed once their reservations ran out
+    pub async fn expire_reservations(&self) {
+        let mut transaction = self.begin().await;
+        for rid in transaction.rooms.expire_reservations() {
+            if let Ok(gid) = transaction.games.find_game_contain_room(rid) {
+                let _ = transaction.games.remove_game(gid);
+            }
+            let _ = transaction.rooms.remove_room(rid);
+        }
+        self.commit(transaction).await;
+    }
+}
@@ -0,0 +1,89 @@
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::caro_protocol;
+
+const EVENT_BUS_CAPACITY: usize = 256;
+
+// what happened on the server, published after the change was applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerEvent {
+    PlayerJoined {
+        pid: caro_protocol::PlayerId,
+        rid: caro_protocol::RoomId,
+        order: caro_protocol::PlayerOrder,
+    },
+    PlayerLeft {
+        pid: caro_protocol::PlayerId,
+        rid: caro_protocol::RoomId,
+    },
+    GameStarted {
+        rid: caro_protocol::RoomId,
+        gid: caro_protocol::GameId,
+        player1: caro_protocol::PlayerId,
+        player2: caro_protocol::PlayerId,
+    },
+    MoveApplied {
+        rid: caro_protocol::RoomId,
+        gid: caro_protocol::GameId,
+        order: caro_protocol::PlayerOrder,
+        coordinate: caro_protocol::Coordinate,
+    },
+    GameEnded {
+        rid: caro_protocol::RoomId,
+        gid: caro_protocol::GameId,
+        state: caro_protocol::GameState,
+    },
+    PlayerDisconnected {
+        pid: caro_protocol::PlayerId,
+    },
+}
+
+pub type EventHandler = Box<dyn FnMut(ServerEvent) + Send + 'static>;
+
+pub type SubscriberHandler = JoinHandle<()>;
+
+// fans every event out to all subscribers, publishing never waits for them
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _receiver) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self {
+            sender,
+        }
+    }
+
+    pub fn publish(&self, event: ServerEvent) {
+        // nobody listening is fine
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn spawn_subscriber(&self, mut handler: EventHandler) -> SubscriberHandler {
+        let mut receiver = self.subscribe();
+        tokio::spawn(
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => handler(event),
+                        // a slow subscriber misses events rather than holding everyone up
+                        Err(broadcast::error::RecvError::Lagged(_missed)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }
+        )
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+
+use crate::caro_protocol;
+
+// a game that was still being played when the server stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedGame {
+    pub rid: caro_protocol::RoomId,
+    pub gid: caro_protocol::GameId,
+    pub rule: caro_protocol::GameRule,
+    pub player1: caro_protocol::PlayerId,
+    pub player2: caro_protocol::PlayerId,
+    pub board_height: usize,
+    pub board_width: usize,
+    pub player1_move_history: Vec<caro_protocol::Coordinate>,
+    pub player2_move_history: Vec<caro_protocol::Coordinate>,
+    pub game_state: caro_protocol::GameState,
+}
+
+// writes one json file per shutdown into dir and returns its path
+pub fn write_archive(dir: &Path, games: &[ArchivedGame]) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("games-{}.json", stamp));
+    let content = serde_json::to_string_pretty(games)?;
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+pub fn read_archive(path: &Path) -> std::io::Result<Vec<ArchivedGame>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
@@ -1,12 +1,17 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 use simple_caro;
+use tokio::sync::{mpsc, oneshot, Mutex, MutexGuard};
+use tracing::Instrument;
 
 use crate::id_pool;
 use crate::caro_protocol;
+use crate::server_config;
+use crate::server_error::{ServerError, ServerResult};
 
+#[derive(Debug, Clone, Copy)]
 pub enum OperationResult {
-    Successfully(simple_caro::GameState),
-    Unsuccessfully(simple_caro::GameState),
+    Successfully(caro_protocol::GameState),
+    Unsuccessfully(caro_protocol::MoveRejection),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -15,12 +20,13 @@ enum GameAvailability {
     Started,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlayerOrder {
     Player1,
     Player2,
 }
 
+#[derive(Debug, Clone)]
 pub struct InternalGameContext {
     pub board_height: usize,
     pub board_width: usize,
@@ -29,15 +35,22 @@ pub struct InternalGameContext {
     pub player1_undone_moves: Vec<caro_protocol::Coordinate>,
     pub player2_undone_moves: Vec<caro_protocol::Coordinate>,
     pub game_state: caro_protocol::GameState,
+    pub winning_line: Vec<caro_protocol::Coordinate>,
+    pub end_reason: Option<caro_protocol::GameEndReason>,
 }
 
 pub struct GameOperator {
     game: simple_caro::SimpleCaro,
     room_id: caro_protocol::RoomId,
+    game_rule: caro_protocol::GameRule,
+    // who asked to play again since the game ended
+    rematch_votes: [bool; 2],
+    // the engine cannot end a game early, the winner by forfeit is kept here instead
+    forfeit_winner: Option<caro_protocol::GameState>,
 }
 
 impl GameOperator {
-    fn new(room_id: caro_protocol::RoomId, game_rule: caro_protocol::GameRule) -> Self {
+    fn new(room_id: caro_protocol::RoomId, game_rule: caro_protocol::GameRule, board_size: usize) -> Self {
         let game = simple_caro::SimpleCaro::new();
         match game_rule {
             caro_protocol::GameRule::TicTacToe => {
@@ -46,23 +59,33 @@ impl GameOperator {
             }
             caro_protocol::GameRule::FourBlockOne => {
                 game.set_rule(simple_caro::RuleType::FourBlockOne);
-                game.set_board_size(1024, 1024);
+                game.set_board_size(board_size, board_size);
             }
             caro_protocol::GameRule::FiveBlockTwo => {
                 game.set_rule(simple_caro::RuleType::FiveBlockTwo);
-                game.set_board_size(1024, 1024);
+                game.set_board_size(board_size, board_size);
             }
         }
         Self {
             game,
             room_id,
+            game_rule,
+            rematch_votes: [false; 2],
+            forfeit_winner: None,
         }
     }
 
+    // every game starts on an empty board, stopping the engine empties it down
+    // to no tiles at all, so its size is set again
     fn try_start(&mut self) -> bool {
         match self.get_availability() {
             GameAvailability::Pending => {
+                let (board_height, board_width) = (self.get_board_height(), self.get_board_width());
+                self.game.stop();
+                self.game.set_board_size(board_width, board_height);
                 self.game.start(simple_caro::GameState::Player1Turn);
+                self.rematch_votes = [false; 2];
+                self.forfeit_winner = None;
                 true
             }
             GameAvailability::Started => {
@@ -91,6 +114,7 @@ impl GameOperator {
             caro_protocol::GameState::Player2Won => GameAvailability::Pending,
             caro_protocol::GameState::Drew => GameAvailability::Pending,
             caro_protocol::GameState::NotInprogress => GameAvailability::Pending,
+            caro_protocol::GameState::Aborted => GameAvailability::Pending,
         }
     }
 
@@ -157,6 +181,9 @@ impl GameOperator {
     }
 
     fn get_state(&self) -> caro_protocol::GameState {
+        if let Some(winner) = self.forfeit_winner {
+            return winner;
+        }
         match self.game.get_state() {
             simple_caro::GameState::Player1Turn => caro_protocol::GameState::Player1Turn,
             simple_caro::GameState::Player2Turn => caro_protocol::GameState::Player2Turn,
@@ -167,147 +194,372 @@ impl GameOperator {
         }
     }
 
+    fn get_winning_line(&self) -> Vec<caro_protocol::Coordinate> {
+        let rule = match self.game_rule {
+            caro_protocol::GameRule::TicTacToe => simple_caro::RuleType::TicTacToe,
+            caro_protocol::GameRule::FourBlockOne => simple_caro::RuleType::FourBlockOne,
+            caro_protocol::GameRule::FiveBlockTwo => simple_caro::RuleType::FiveBlockTwo,
+        };
+        self.game.get_winning_line(rule)
+            .into_iter()
+            .map(|tile| (tile.latitude, tile.longtitude))
+            .collect()
+    }
+
+    fn get_end_reason(&self) -> Option<caro_protocol::GameEndReason> {
+        match (self.get_state(), self.forfeit_winner) {
+            (_, Some(_)) => Some(caro_protocol::GameEndReason::Forfeit),
+            (caro_protocol::GameState::Player1Won, None) |
+            (caro_protocol::GameState::Player2Won, None) => Some(caro_protocol::GameEndReason::LineCompleted),
+            (caro_protocol::GameState::Drew, None) => Some(caro_protocol::GameEndReason::BoardFull),
+            _ => None,
+        }
+    }
+
+    // only a game in progress can be forfeited, returns the state it ended in
+    fn forfeit(&mut self, loser: PlayerOrder) -> Option<caro_protocol::GameState> {
+        if let GameAvailability::Pending = self.get_availability() {
+            return None;
+        }
+        let winner = match loser {
+            PlayerOrder::Player1 => caro_protocol::GameState::Player2Won,
+            PlayerOrder::Player2 => caro_protocol::GameState::Player1Won,
+        };
+        self.forfeit_winner = Some(winner);
+        Some(winner)
+    }
+
+    // a fresh board is only dealt once both players asked, player1 moves first again
+    fn vote_rematch(&mut self, player_order: PlayerOrder) -> OperationResult {
+        match self.get_state() {
+            caro_protocol::GameState::Player1Won |
+            caro_protocol::GameState::Player2Won |
+            caro_protocol::GameState::Drew => {},
+            caro_protocol::GameState::Player1Turn |
+            caro_protocol::GameState::Player2Turn => return OperationResult::Unsuccessfully(caro_protocol::MoveRejection::GameStillInProgress),
+            caro_protocol::GameState::NotInprogress |
+            caro_protocol::GameState::Aborted => return OperationResult::Unsuccessfully(caro_protocol::MoveRejection::GameNotInProgress),
+        }
+        match player_order {
+            PlayerOrder::Player1 => self.rematch_votes[0] = true,
+            PlayerOrder::Player2 => self.rematch_votes[1] = true,
+        }
+        if self.rematch_votes == [true; 2] {
+            self.try_start();
+        }
+        OperationResult::Successfully(self.get_state())
+    }
+
     fn execute_command(&mut self, player_order: PlayerOrder, cmd_code: caro_protocol::InGameRequest) -> OperationResult {
-        let mut is_success = false;
         let who = match player_order {
             PlayerOrder::Player1 => simple_caro::Participant::Player1,
             PlayerOrder::Player2 => simple_caro::Participant::Player2,
         };
-        match cmd_code {
+        if let caro_protocol::InGameRequest::RequestRematch = cmd_code {
+            return self.vote_rematch(player_order);
+        }
+        // the engine only says wrong turn once the game is over
+        if let GameAvailability::Pending = self.get_availability() {
+            return OperationResult::Unsuccessfully(caro_protocol::MoveRejection::GameNotInProgress);
+        }
+        let result = match cmd_code {
             caro_protocol::InGameRequest::PlayerMove((latitude, longtitude)) => {
                 let pos = simple_caro::Coordinate {latitude, longtitude};
-                let result = self.game.player_move(who, pos);
-                match result {
+                match self.game.player_move(who, pos) {
                     simple_caro::MoveResult::Success => {
                         self.game.switch_turn();
-                        is_success = true;
-                    }
-                    _ => {
-                        is_success = false;
-                    }
+                        Ok(())
+                    },
+                    simple_caro::MoveResult::AlreadyOccupied => Err(caro_protocol::MoveRejection::AlreadyOccupied((latitude, longtitude))),
+                    simple_caro::MoveResult::WrongTurn => Err(caro_protocol::MoveRejection::NotYourTurn),
+                    simple_caro::MoveResult::OutOfBound => Err(caro_protocol::MoveRejection::OutOfBounds((latitude, longtitude))),
                 }
-            }
+            },
+            // an empty history is reported as out of bounds
             caro_protocol::InGameRequest::PlayerUndo => {
-                let result = self.game.player_undo(who);
-                match result {
-                    simple_caro::MoveResult::Success => {
-                        is_success = true;
-                    }
-                    _ => {
-                        is_success = false;
-                    }
+                match self.game.player_undo(who) {
+                    simple_caro::MoveResult::Success => Ok(()),
+                    simple_caro::MoveResult::WrongTurn => Err(caro_protocol::MoveRejection::NotYourTurn),
+                    _ => Err(caro_protocol::MoveRejection::NothingToUndo),
                 }
-            }
+            },
             caro_protocol::InGameRequest::PlayerRedo => {
-                let result = self.game.player_redo(who);
-                match result {
-                    simple_caro::MoveResult::Success => {
-                        is_success = true;
-                    }
-                    _ => {
-                        is_success = false;
-                    }
+                let redone = self.get_player_undone_moves(player_order).last().copied();
+                match (self.game.player_redo(who), redone) {
+                    (simple_caro::MoveResult::Success, _) => Ok(()),
+                    (simple_caro::MoveResult::WrongTurn, _) => Err(caro_protocol::MoveRejection::NotYourTurn),
+                    // the opponent took the tile in the meantime
+                    (simple_caro::MoveResult::AlreadyOccupied, Some(coor)) => Err(caro_protocol::MoveRejection::AlreadyOccupied(coor)),
+                    _ => Err(caro_protocol::MoveRejection::NothingToRedo),
                 }
-            }
-            caro_protocol::InGameRequest::PlayerLeaveRoom => {
-                
             },
-            caro_protocol::InGameRequest::PlayerRequestContext => {
+            caro_protocol::InGameRequest::PlayerLeaveRoom => Ok(()),
+            caro_protocol::InGameRequest::PlayerRequestContext => Ok(()),
+            caro_protocol::InGameRequest::RequestRematch => Ok(()),
+        };
 
-            },
-        }
-        
-        if is_success {
-            OperationResult::Successfully(self.game.get_state())
-        } else {
-            OperationResult::Unsuccessfully(self.game.get_state())
+        match result {
+            Ok(()) => OperationResult::Successfully(self.get_state()),
+            Err(reason) => OperationResult::Unsuccessfully(reason),
         }
     }
 
     fn get_rid(&self) -> caro_protocol::RoomId {
         self.room_id
     }
+
+    // puts the moves of a snapshot back on a fresh board, the turn is forced before every move
+    // since undoing a move does not hand the turn over
+    fn replay(&mut self, moves: &[(caro_protocol::PlayerOrder, caro_protocol::Coordinate)], turn: caro_protocol::GameState) -> bool {
+        self.try_start();
+        for (order, (latitude, longtitude)) in moves {
+            let (who, own_turn) = match order {
+                caro_protocol::PlayerOrder::Player1 => (simple_caro::Participant::Player1, caro_protocol::GameState::Player1Turn),
+                caro_protocol::PlayerOrder::Player2 => (simple_caro::Participant::Player2, caro_protocol::GameState::Player2Turn),
+            };
+            if self.get_state() != own_turn {
+                self.game.switch_turn();
+            }
+            let pos = simple_caro::Coordinate {latitude: *latitude, longtitude: *longtitude};
+            match self.game.player_move(who, pos) {
+                simple_caro::MoveResult::Success => self.game.switch_turn(),
+                _ => return false,
+            }
+        }
+        match (self.get_state(), turn) {
+            (caro_protocol::GameState::Player1Turn, caro_protocol::GameState::Player2Turn) |
+            (caro_protocol::GameState::Player2Turn, caro_protocol::GameState::Player1Turn) => self.game.switch_turn(),
+            _ => {},
+        }
+        self.get_state() == turn
+    }
+}
+
+enum GameCommand {
+    Start,
+    Stop,
+    Execute(PlayerOrder, caro_protocol::InGameRequest, oneshot::Sender<OperationResult>),
+    Context(oneshot::Sender<InternalGameContext>),
+    Forfeit(PlayerOrder, oneshot::Sender<Option<caro_protocol::GameState>>),
+}
+
+// every game runs in its own task and applies its commands one at a time,
+// so a busy game never holds up the others, the rooms themselves still live
+// in the shared containers
+fn spawn_game(gid: caro_protocol::GameId, mut game: GameOperator) -> mpsc::UnboundedSender<GameCommand> {
+    let (mailbox, mut commands) = mpsc::unbounded_channel::<GameCommand>();
+    let span = tracing::debug_span!(parent: None, "game", gid, rid = game.get_rid());
+    tokio::spawn(
+        async move {
+            // ends once every handle to the game is dropped
+            while let Some(command) = commands.recv().await {
+                match command {
+                    GameCommand::Start => {
+                        game.try_start();
+                    },
+                    GameCommand::Stop => {
+                        game.try_stop();
+                    },
+                    GameCommand::Execute(player_order, cmd_code, reply) => {
+                        let result = game.execute_command(player_order, cmd_code);
+                        tracing::debug!(?player_order, ?cmd_code, ?result, "command executed");
+                        let _ = reply.send(result);
+                    },
+                    GameCommand::Context(reply) => {
+                        let _ = reply.send(InternalGameContext {
+                            board_height: game.get_board_height(),
+                            board_width: game.get_board_width(),
+                            player1_move_history: game.get_player_move_history(PlayerOrder::Player1),
+                            player2_move_history: game.get_player_move_history(PlayerOrder::Player2),
+                            player1_undone_moves: game.get_player_undone_moves(PlayerOrder::Player1),
+                            player2_undone_moves: game.get_player_undone_moves(PlayerOrder::Player2),
+                            game_state: game.get_state(),
+                            winning_line: game.get_winning_line(),
+                            end_reason: game.get_end_reason(),
+                        });
+                    },
+                    GameCommand::Forfeit(loser, reply) => {
+                        let result = game.forfeit(loser);
+                        tracing::debug!(?loser, ?result, "game forfeited");
+                        let _ = reply.send(result);
+                    },
+                }
+            }
+            tracing::debug!("game closed");
+        }.instrument(span)
+    );
+    mailbox
+}
+
+// the game as the players were last told, deltas are worked out against it
+#[derive(Debug, Default)]
+pub struct PushedView {
+    pub seq: u64,
+    pub context: Option<caro_protocol::GameContext>,
+}
+
+#[derive(Clone)]
+pub struct GameHandle {
+    gid: caro_protocol::GameId,
+    room_id: caro_protocol::RoomId,
+    mailbox: mpsc::UnboundedSender<GameCommand>,
+    pushed_view: Arc<Mutex<PushedView>>,
+}
+
+impl GameHandle {
+    pub fn get_gid(&self) -> caro_protocol::GameId {
+        self.gid
+    }
+
+    pub fn get_rid(&self) -> caro_protocol::RoomId {
+        self.room_id
+    }
+
+    // held while an update is sent out, so the players see the changes in order
+    pub async fn lock_pushed_view(&self) -> MutexGuard<'_, PushedView> {
+        self.pushed_view.lock().await
+    }
+
+    fn post(&self, command: GameCommand) -> ServerResult<()> {
+        self.mailbox.send(command).map_err(|_closed| ServerError::GameNotFound(self.gid))
+    }
+
+    pub async fn execute_command(&self, player_order: PlayerOrder, cmd_code: caro_protocol::InGameRequest) -> ServerResult<OperationResult> {
+        let (reply, result) = oneshot::channel();
+        self.post(GameCommand::Execute(player_order, cmd_code, reply))?;
+        result.await.map_err(|_closed| ServerError::GameNotFound(self.gid))
+    }
+
+    // applied before anything sent to the game afterwards, the state it ended in arrives on the receiver
+    pub fn forfeit(&self, loser: PlayerOrder) -> ServerResult<oneshot::Receiver<Option<caro_protocol::GameState>>> {
+        let (reply, result) = oneshot::channel();
+        self.post(GameCommand::Forfeit(loser, reply))?;
+        Ok(result)
+    }
+
+    pub async fn get_context(&self) -> ServerResult<InternalGameContext> {
+        let (reply, context) = oneshot::channel();
+        self.post(GameCommand::Context(reply))?;
+        context.await.map_err(|_closed| ServerError::GameNotFound(self.gid))
+    }
 }
 
 pub struct GameContainer {
-    games_set: HashMap<caro_protocol::GameId, GameOperator>,
+    games_set: HashMap<caro_protocol::GameId, GameHandle>,
+    // which game belongs to which room, so requests are routed without a scan
+    game_of_room: HashMap<caro_protocol::RoomId, caro_protocol::GameId>,
     max_games: usize,
     gid_pool: id_pool::IdPool<i32>,
+    board_size_limits: server_config::BoardSizeLimits,
 }
 
 impl GameContainer {
     pub fn new(max_games: usize, gid_pool: id_pool::IdPool<i32>) -> Self {
         Self {
-            games_set: HashMap::<caro_protocol::GameId, GameOperator>::new(),
+            games_set: HashMap::<caro_protocol::GameId, GameHandle>::new(),
+            game_of_room: HashMap::new(),
             max_games,
             gid_pool,
+            board_size_limits: server_config::BoardSizeLimits::default(),
         }
     }
 
-    pub fn add_game(&mut self, rid: caro_protocol::RoomId, game_rule: caro_protocol::GameRule) -> caro_protocol::GameId {
+    pub fn set_board_size_limits(&mut self, board_size_limits: server_config::BoardSizeLimits) {
+        self.board_size_limits = board_size_limits;
+    }
+
+    // the board is board_size wide and high, or as large as the limits default to
+    pub fn add_game(&mut self,
+                    rid: caro_protocol::RoomId,
+                    game_rule: caro_protocol::GameRule,
+                    board_size: Option<usize>) -> ServerResult<caro_protocol::GameId> {
         if self.games_set.len() >= self.max_games {
-            return -1;
+            return Err(ServerError::GameLimitReached);
+        }
+        let limits = self.board_size_limits;
+        let board_size = board_size.unwrap_or(limits.default_size);
+        if game_rule != caro_protocol::GameRule::TicTacToe && (board_size < limits.min_size || board_size > limits.max_size) {
+            return Err(ServerError::BoardSizeNotAllowed(board_size));
         }
         let new_gid = self.gid_pool.alloc_id();
-        let new_game = GameOperator::new(rid, game_rule);
-        self.games_set.insert(new_gid, new_game);
-        new_gid
+        let new_game = GameOperator::new(rid, game_rule, board_size);
+        let handle = GameHandle {
+            gid: new_gid,
+            room_id: new_game.get_rid(),
+            mailbox: spawn_game(new_gid, new_game),
+            pushed_view: Arc::new(Mutex::new(PushedView::default())),
+        };
+        self.games_set.insert(new_gid, handle);
+        self.game_of_room.insert(rid, new_gid);
+        Ok(new_gid)
     }
 
-    pub fn remove_game(&mut self, gid: caro_protocol::GameId) {
+    // a game of a restored room, already started if it has moves or a turn to replay
+    pub fn restore_game(&mut self,
+                        rid: caro_protocol::RoomId,
+                        game_rule: caro_protocol::GameRule,
+                        board_size: usize,
+                        moves: &[(caro_protocol::PlayerOrder, caro_protocol::Coordinate)],
+                        turn: caro_protocol::GameState) -> ServerResult<caro_protocol::GameId> {
+        if self.games_set.len() >= self.max_games {
+            return Err(ServerError::GameLimitReached);
+        }
+        // tic tac toe is always played on 3x3 whatever the limits say
+        let limits = self.board_size_limits;
+        if game_rule != caro_protocol::GameRule::TicTacToe && (board_size < limits.min_size || board_size > limits.max_size) {
+            return Err(ServerError::RestoreFailed(rid));
+        }
+        let mut new_game = GameOperator::new(rid, game_rule, board_size);
+        if turn != caro_protocol::GameState::NotInprogress && !new_game.replay(moves, turn) {
+            return Err(ServerError::RestoreFailed(rid));
+        }
+        let new_gid = self.gid_pool.alloc_id();
+        let handle = GameHandle {
+            gid: new_gid,
+            room_id: new_game.get_rid(),
+            mailbox: spawn_game(new_gid, new_game),
+            pushed_view: Arc::new(Mutex::new(PushedView::default())),
+        };
+        self.games_set.insert(new_gid, handle);
+        self.game_of_room.insert(rid, new_gid);
+        Ok(new_gid)
+    }
+
+    pub fn remove_game(&mut self, gid: caro_protocol::GameId) -> ServerResult<()> {
+        let handle = self.games_set.remove(&gid).ok_or(ServerError::GameNotFound(gid))?;
+        self.game_of_room.remove(&handle.get_rid());
         self.gid_pool.dealloc_id(gid);
-        self.games_set.remove(&gid);
+        Ok(())
     }
 
-    pub fn try_start_game(&mut self, gid: caro_protocol::GameId) -> bool {
-        if let Some(game) = self.games_set.get_mut(&gid) {
-            game.try_start()
-        } else {
-            false
-        }
+    pub fn start_game(&mut self, gid: caro_protocol::GameId) -> ServerResult<()> {
+        self.get_handle(gid)?.post(GameCommand::Start)
     }
 
-    pub fn try_stop_game(&mut self, gid: caro_protocol::GameId) -> bool {
-        if let Some(game) = self.games_set.get_mut(&gid) {
-            game.try_stop()
-        } else {
-            false
-        }
+    pub fn stop_game(&mut self, gid: caro_protocol::GameId) -> ServerResult<()> {
+        self.get_handle(gid)?.post(GameCommand::Stop)
     }
 
-    pub fn get_context_in_game(&self, gid: caro_protocol::GameId) -> Option<InternalGameContext> {
-        if let Some(game) = self.games_set.get(&gid) {
-            Some(InternalGameContext {
-                board_height: game.get_board_height(),
-                board_width: game.get_board_width(),
-                player1_move_history: game.get_player_move_history(PlayerOrder::Player1),
-                player2_move_history: game.get_player_move_history(PlayerOrder::Player2),
-                player1_undone_moves: game.get_player_undone_moves(PlayerOrder::Player1),
-                player2_undone_moves: game.get_player_undone_moves(PlayerOrder::Player2),
-                game_state: game.get_state(),
-            })
-        } else {
-            None
-        }
+    // the handle outlives the lock on the container, commands are sent through it
+    pub fn get_handle(&self, gid: caro_protocol::GameId) -> ServerResult<GameHandle> {
+        self.games_set.get(&gid)
+            .cloned()
+            .ok_or(ServerError::GameNotFound(gid))
     }
 
-    pub fn execute_command_in_game(&mut self, gid: caro_protocol::GameId, player_order: PlayerOrder, cmd_code: caro_protocol::InGameRequest) -> Option<OperationResult> {
-        if let Some(game) = self.games_set.get_mut(&gid) {
-            Some(game.execute_command(player_order, cmd_code))
-        } else {
-            None
-        }
+    pub fn get_gids(&self) -> Vec<caro_protocol::GameId> {
+        self.games_set.keys().copied().collect()
     }
 
-    pub fn find_game_contain_room(&self, rid: caro_protocol::RoomId) -> Option<caro_protocol::GameId> {
-        let target = self.games_set.iter().find(|&(_gid, game)| {
-            let its_rid = game.get_rid();
-            its_rid == rid
-        });
-        if let Some((gid, _game)) = target {
-            Some(*gid)
-        } else {
-            None
-        }
+    pub fn get_rid_of_game(&self, gid: caro_protocol::GameId) -> ServerResult<caro_protocol::RoomId> {
+        self.games_set.get(&gid)
+            .map(|game| game.get_rid())
+            .ok_or(ServerError::GameNotFound(gid))
+    }
+
+    pub fn find_game_contain_room(&self, rid: caro_protocol::RoomId) -> ServerResult<caro_protocol::GameId> {
+        self.game_of_room.get(&rid)
+            .copied()
+            .ok_or(ServerError::NoGameInRoom(rid))
     }
 }
@@ -26,6 +26,11 @@ impl IdPool<i32> {
     pub fn dealloc_id(&mut self, id: i32) -> bool {
         self.pool.remove(&id)
     }
+
+    // takes a given id, e.g. one that was restored, returns false if it is already in use
+    pub fn reserve_id(&mut self, id: i32) -> bool {
+        id > 0 && self.pool.insert(id)
+    }
 }
 
 impl IdPool<i64> {
@@ -50,4 +55,9 @@ impl IdPool<i64> {
     pub fn dealloc_id(&mut self, id: i64) -> bool {
         self.pool.remove(&id)
     }
+
+    // takes a given id, e.g. one that was restored, returns false if it is already in use
+    pub fn reserve_id(&mut self, id: i64) -> bool {
+        id > 0 && self.pool.insert(id)
+    }
 }
\ No newline at end of file
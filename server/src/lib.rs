@@ -2,7 +2,19 @@ pub mod room_manager;
 pub mod player_manager;
 pub mod id_pool;
 pub mod player_life_tracker;
+pub mod player_state_machine;
 pub mod server_endpoint;
 pub mod game_manager;
 pub mod caro_protocol;
-pub mod client_request_executor;
\ No newline at end of file
+pub mod client_request_executor;
+pub mod server_app;
+pub mod server_config;
+pub mod server_error;
+pub mod event_bus;
+pub mod game_archive;
+pub mod snapshot;
+pub mod logging;
+pub mod metrics;
+pub mod metrics_endpoint;
+pub mod admin_console;
+pub mod rate_limiter;
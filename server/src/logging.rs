@@ -0,0 +1,25 @@
+use std::sync::Mutex;
+
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+use crate::server_config;
+
+// installs the global subscriber, does nothing if the embedding program already has one
+pub fn init(settings: &server_config::ServerSettings) -> std::io::Result<()> {
+    let writer = match &settings.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            BoxMakeWriter::new(Mutex::new(file))
+        },
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(settings.log_level.to_level_filter())
+        .with_ansi(settings.log_file.is_none())
+        .with_writer(writer);
+    let _already_set = match settings.log_format {
+        server_config::LogFormat::Pretty => builder.pretty().try_init(),
+        server_config::LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).try_init(),
+    };
+    Ok(())
+}
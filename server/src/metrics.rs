@@ -0,0 +1,191 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{atomic::{AtomicU64, Ordering}, Mutex},
+    time::{Duration, Instant}
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::caro_protocol;
+
+// moves per second is averaged over this window
+const MOVE_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// counters bumped from everywhere in the server, read by the metrics endpoint
+pub struct ServerMetrics {
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+    decode_errors: AtomicU64,
+    heartbeat_timeouts: AtomicU64,
+    moves: AtomicU64,
+    recent_moves: Mutex<VecDeque<Instant>>,
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        Self {
+            messages_in: AtomicU64::new(0),
+            messages_out: AtomicU64::new(0),
+            decode_errors: AtomicU64::new(0),
+            heartbeat_timeouts: AtomicU64::new(0),
+            moves: AtomicU64::new(0),
+            recent_moves: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record_message_in(&self) {
+        self.messages_in.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_message_out(&self) {
+        self.messages_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_heartbeat_timeout(&self) {
+        self.heartbeat_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_move(&self) {
+        self.moves.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut recent_moves = self.recent_moves.lock().unwrap();
+        recent_moves.push_back(now);
+        forget_old_moves(&mut recent_moves, now);
+    }
+
+    pub fn messages_in(&self) -> u64 {
+        self.messages_in.load(Ordering::Relaxed)
+    }
+
+    pub fn messages_out(&self) -> u64 {
+        self.messages_out.load(Ordering::Relaxed)
+    }
+
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn heartbeat_timeouts(&self) -> u64 {
+        self.heartbeat_timeouts.load(Ordering::Relaxed)
+    }
+
+    pub fn moves(&self) -> u64 {
+        self.moves.load(Ordering::Relaxed)
+    }
+
+    pub fn moves_per_second(&self) -> f64 {
+        let mut recent_moves = self.recent_moves.lock().unwrap();
+        forget_old_moves(&mut recent_moves, Instant::now());
+        recent_moves.len() as f64 / MOVE_RATE_WINDOW.as_secs_f64()
+    }
+
+    // prometheus text exposition format, the gauges come from the status
+    pub fn render(&self, status: &ServerStatus) -> String {
+        let mut text = String::new();
+        write_metric(&mut text, "caro_players_connected", "gauge", "Players with an open connection.", &[(None, status.players as f64)]);
+
+        let rules = [
+            caro_protocol::GameRule::TicTacToe,
+            caro_protocol::GameRule::FourBlockOne,
+            caro_protocol::GameRule::FiveBlockTwo,
+        ];
+        let rooms_by_rule: Vec<(Option<String>, f64)> = rules.iter()
+            .map(|rule| {
+                let count = status.rooms.iter().filter(|room| room.rule == *rule).count();
+                (Some(format!("rule=\"{:?}\"", rule)), count as f64)
+            })
+            .collect();
+        write_metric(&mut text, "caro_rooms", "gauge", "Open rooms by game rule.", &rooms_by_rule);
+        let games_in_progress = status.rooms.iter().filter(|room| room.in_progress()).count();
+        write_metric(&mut text, "caro_games_in_progress", "gauge", "Games waiting for a move.", &[(None, games_in_progress as f64)]);
+
+        write_metric(&mut text, "caro_moves_total", "counter", "Moves applied since start.", &[(None, self.moves() as f64)]);
+        write_metric(&mut text, "caro_moves_per_second", "gauge", "Moves per second over the last minute.", &[(None, self.moves_per_second())]);
+        write_metric(&mut text, "caro_messages_received_total", "counter", "Messages received from players.", &[(None, self.messages_in() as f64)]);
+        write_metric(&mut text, "caro_messages_sent_total", "counter", "Messages sent to players.", &[(None, self.messages_out() as f64)]);
+        write_metric(&mut text, "caro_decode_errors_total", "counter", "Messages that could not be decoded.", &[(None, self.decode_errors() as f64)]);
+        write_metric(&mut text, "caro_heartbeat_timeouts_total", "counter", "Players removed for not answering heartbeats.", &[(None, self.heartbeat_timeouts() as f64)]);
+        text
+    }
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn forget_old_moves(recent_moves: &mut VecDeque<Instant>, now: Instant) {
+    while let Some(oldest) = recent_moves.front() {
+        if now.duration_since(*oldest) <= MOVE_RATE_WINDOW {
+            break;
+        }
+        recent_moves.pop_front();
+    }
+}
+
+fn write_metric(text: &mut String, name: &str, kind: &str, help: &str, samples: &[(Option<String>, f64)]) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        match labels {
+            Some(labels) => { let _ = writeln!(text, "{}{{{}}} {}", name, labels, value); },
+            None => { let _ = writeln!(text, "{} {}", name, value); },
+        }
+    }
+}
+
+// what the /status page shows
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub players: usize,
+    pub rooms: Vec<RoomStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomStatus {
+    pub rid: caro_protocol::RoomId,
+    pub rule: caro_protocol::GameRule,
+    pub private: bool,
+    // an empty seat is None, a seat kept for a restored player is reserved
+    pub player1: Option<SeatStatus>,
+    pub player2: Option<SeatStatus>,
+    pub gid: Option<caro_protocol::GameId>,
+    // only set once the game was started
+    pub game_state: Option<caro_protocol::GameState>,
+}
+
+impl RoomStatus {
+    pub fn in_progress(&self) -> bool {
+        matches!(self.game_state, Some(caro_protocol::GameState::Player1Turn) | Some(caro_protocol::GameState::Player2Turn))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeatStatus {
+    Player {
+        pid: caro_protocol::PlayerId,
+        state: caro_protocol::PlayerState,
+    },
+    Reserved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStatus {
+    pub pid: caro_protocol::PlayerId,
+    pub state: caro_protocol::PlayerState,
+    pub rid: Option<caro_protocol::RoomId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameStatus {
+    pub gid: caro_protocol::GameId,
+    pub rid: caro_protocol::RoomId,
+    pub game_state: caro_protocol::GameState,
+    pub moves: usize,
+}
@@ -0,0 +1,103 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle
+};
+
+use crate::{client_request_executor, metrics};
+
+// enough for a request line and a few headers, anything bigger is not a scraper
+const MAX_REQUEST_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// a tiny http server for /metrics and /status, meant to listen on a local address only
+pub struct MetricsEndpoint {
+    listener: TcpListener,
+}
+
+impl MetricsEndpoint {
+    pub async fn bind(addr: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn serve(self, metrics: Arc<metrics::ServerMetrics>, command_executor: Arc<client_request_executor::RequestExecutor>) -> JoinHandle<()> {
+        tokio::spawn(
+            async move {
+                loop {
+                    let stream = match self.listener.accept().await {
+                        Ok((stream, _addr)) => stream,
+                        Err(err) => {
+                            tracing::warn!(%err, "cannot accept metrics connection");
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        },
+                    };
+                    let metrics = metrics.clone();
+                    let command_executor = command_executor.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = answer(stream, &metrics, &command_executor).await {
+                            tracing::debug!(%err, "metrics request failed");
+                        }
+                    });
+                }
+            }
+        )
+    }
+}
+
+async fn answer(mut stream: TcpStream, metrics: &metrics::ServerMetrics, command_executor: &client_request_executor::RequestExecutor) -> std::io::Result<()> {
+    let head = match tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await {
+        Ok(head) => head?,
+        Err(_elapsed) => return Ok(()),
+    };
+    let request_line = head.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    // query strings are of no use to either page
+    let path = target.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let status = command_executor.status().await;
+            ("200 OK", "text/plain; version=0.0.4", metrics.render(&status))
+        },
+        ("GET", "/status") => {
+            let status = command_executor.status().await;
+            let body = serde_json::to_string_pretty(&status)?;
+            ("200 OK", "application/json", body)
+        },
+        ("GET", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "only GET is supported\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// reads up to the blank line that ends the headers, a request never has a body here
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let bytesread = stream.read(&mut buffer).await?;
+        if bytesread == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..bytesread]);
+        if head.len() > MAX_REQUEST_SIZE {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "request head too large"));
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
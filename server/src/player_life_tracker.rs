@@ -1,9 +1,9 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use futures::future::BoxFuture;
-use tokio::{sync::RwLock, task::JoinHandle};
+use tokio::{sync::RwLock, task::JoinHandle, time::Instant};
 
-use crate::{caro_protocol, player_manager};
+use crate::{caro_protocol, player_manager, player_state_machine, server_config};
 
 pub type DisconnectedAction = Arc<tokio::sync::RwLock<dyn FnMut(caro_protocol::PlayerId) -> BoxFuture<'static, ()> + Send + Sync + 'static>>;
 
@@ -20,6 +20,7 @@ pub struct PlayerTracker {
     player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
     action_on_disconnect: DisconnectedAction,
     action_on_disconnect_timeout: DisconnectedAction,
+    heartbeat: server_config::HeartbeatSettings,
 }
 
 impl PlayerTracker {
@@ -38,9 +39,14 @@ impl PlayerTracker {
             player_manager,
             action_on_disconnect,
             action_on_disconnect_timeout,
+            heartbeat: server_config::HeartbeatSettings::default(),
         }
     }
 
+    pub fn set_heartbeat(&mut self, heartbeat: server_config::HeartbeatSettings) {
+        self.heartbeat = heartbeat;
+    }
+
     pub fn set_action_on_disconnect(&mut self, action: DisconnectedAction) {
         self.action_on_disconnect = action;
     }
@@ -54,14 +60,37 @@ impl PlayerTracker {
         tokio::spawn(
             async move {
                 let target = target_clone.clone();
+                // when each silent player stopped answering the check alive message
+                let mut silent_since = HashMap::<caro_protocol::PlayerId, Instant>::new();
                 loop {
-                    let pid = 0;
-                    continue; // This is a placeholder for actual player tracking logic
-                    target.write().await.player_manager.write().await.set_connection_state(pid, caro_protocol::ConnectState::Disconnected);
-                    tokio::spawn(target.read().await.action_on_disconnect.write().await(pid));
-                    // continue checking
-                    tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                    tokio::spawn(target.read().await.action_on_disconnect_timeout.write().await(pid));
+                    let heartbeat = target.read().await.heartbeat;
+                    tokio::time::sleep(std::time::Duration::from_secs(heartbeat.interval_secs)).await;
+
+                    let player_manager = target.read().await.player_manager.clone();
+                    let pids = player_manager.read().await.get_pids();
+                    silent_since.retain(|pid, _since| pids.contains(pid));
+                    for pid in pids {
+                        if player_manager.read().await.is_responsed_to_checkalive(pid) {
+                            if silent_since.remove(&pid).is_some() {
+                                let _ = player_manager.write().await.apply_event(pid, player_state_machine::StateEvent::ConnectionRestored);
+                            }
+                        } else {
+                            match silent_since.get(&pid) {
+                                None => {
+                                    silent_since.insert(pid, Instant::now());
+                                    let _ = player_manager.write().await.apply_event(pid, player_state_machine::StateEvent::ConnectionLost);
+                                    tokio::spawn(target.read().await.action_on_disconnect.write().await(pid));
+                                },
+                                Some(since) if since.elapsed().as_secs() >= heartbeat.timeout_secs => {
+                                    silent_since.remove(&pid);
+                                    tokio::spawn(target.read().await.action_on_disconnect_timeout.write().await(pid));
+                                    continue;
+                                },
+                                Some(_since) => {},
+                            }
+                        }
+                        player_manager.write().await.send_checkalive_message(pid).await;
+                    }
                 }
             }
         )
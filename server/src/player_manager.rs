@@ -5,12 +5,27 @@ use tokio::sync::RwLock;
 use crate::{
     server_endpoint,
     id_pool,
-    caro_protocol
+    caro_protocol,
+    player_state_machine,
+    server_error::{ServerError, ServerResult}
 };
 
+// long and random enough that a token cannot be guessed
+const SESSION_TOKEN_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const SESSION_TOKEN_LENGTH: usize = 32;
+
+fn new_session_token() -> caro_protocol::SessionToken {
+    (0..SESSION_TOKEN_LENGTH)
+        .map(|_| SESSION_TOKEN_ALPHABET[rand::random_range(0..SESSION_TOKEN_ALPHABET.len())] as char)
+        .collect()
+}
+
 struct Player {
     state: caro_protocol::PlayerState,
-    responser: server_endpoint::Responser,
+    session_token: caro_protocol::SessionToken,
+    // where the connection comes from, the unspecified address if the socket could not tell
+    peer_ip: std::net::IpAddr,
+    responser: Arc<RwLock<server_endpoint::Responser>>,
     request_getter: Arc<RwLock<server_endpoint::RequestGetter>>,
     response_handler: Option<Arc<RwLock<server_endpoint::ResponseHandler>>>,
 
@@ -18,40 +33,33 @@ struct Player {
 }
 
 impl Player {
-    fn new(receiver: server_endpoint::Receiver, sender: server_endpoint::Sender) -> Self {
-        let responser = server_endpoint::Responser::new(sender);
-        let request_getter = Arc::new(RwLock::new(server_endpoint::RequestGetter::new(receiver)));
+    fn new(receiver: server_endpoint::Receiver, sender: server_endpoint::Sender, session_token: caro_protocol::SessionToken) -> Self {
+        let peer_ip = receiver.peer_addr()
+            .map(|addr| addr.ip())
+            .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED));
+        let responser = Arc::new(RwLock::new(server_endpoint::Responser::new(sender)));
+        let request_getter = Arc::new(RwLock::new(server_endpoint::RequestGetter::new(receiver, responser.clone())));
         Self {
             state: caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected),
+            session_token,
+            peer_ip,
             responser,
             request_getter,
             response_handler: None,
-            responsed_to_checkalive: false,
+            // a fresh connection counts as alive until the first check
+            responsed_to_checkalive: true,
         }
     }
 
-    fn set_state(&mut self, state: caro_protocol::PlayerState) {
-        self.state = state;
+    fn apply_event(&mut self, event: player_state_machine::StateEvent) -> ServerResult<caro_protocol::PlayerState> {
+        self.state = player_state_machine::next_state(self.state, event)?;
+        Ok(self.state)
     }
 
     fn get_state(&self) -> caro_protocol::PlayerState {
         self.state
     }
 
-    fn set_connection_state(&mut self, state: caro_protocol::ConnectState) {
-        match self.state {
-            caro_protocol::PlayerState::Logged(_) => {
-                self.state = caro_protocol::PlayerState::Logged(state);
-            },
-            caro_protocol::PlayerState::InRoom(_) => {
-                self.state = caro_protocol::PlayerState::InRoom(state);
-            },
-            caro_protocol::PlayerState::InGame(_) => {
-                self.state = caro_protocol::PlayerState::InGame(state);
-            },
-        }
-    }
-
     fn get_connection_state(&self) -> caro_protocol::ConnectState {
         match self.state {
             caro_protocol::PlayerState::Logged(conn_state) => conn_state,
@@ -69,8 +77,11 @@ impl Player {
     }
 
     async fn response(&mut self, message: caro_protocol::MessagePacket) {
-        tokio::time::sleep(std::time::Duration::from_millis(2)).await; // avoid bombarding the client with messages
-        self.responser.send_response(message).await;
+        self.responser.write().await.send_response(message).await;
+    }
+
+    fn get_responser(&self) -> Arc<RwLock<server_endpoint::Responser>> {
+        self.responser.clone()
     }
 
     async fn handling_request(&mut self) -> bool {
@@ -99,6 +110,12 @@ impl Player {
         self.response(new_packet).await;
     }
 
+    // stops reading requests and tells the peer nothing more is coming
+    async fn close(&mut self) {
+        self.stop_handling_request().await;
+        self.responser.write().await.close().await;
+    }
+
     fn mark_as_responsed_to_checkalive(&mut self) {
         self.responsed_to_checkalive = true;
     }
@@ -112,6 +129,7 @@ pub struct PlayerContainer {
     players_map: HashMap<caro_protocol::PlayerId, Player>,
     max_player: usize,
     pid_pool: id_pool::IdPool<i32>,
+    player_of_session: HashMap<caro_protocol::SessionToken, caro_protocol::PlayerId>,
 }
 
 impl PlayerContainer {
@@ -120,44 +138,93 @@ impl PlayerContainer {
             players_map: HashMap::new(),
             max_player,
             pid_pool,
+            player_of_session: HashMap::new(),
         }
     }
 }
 
 impl PlayerContainer {
-    pub fn add_player(&mut self, receiver: server_endpoint::Receiver, sender: server_endpoint::Sender) -> caro_protocol::PlayerId {
+    pub fn add_player(&mut self, receiver: server_endpoint::Receiver, sender: server_endpoint::Sender) -> ServerResult<caro_protocol::PlayerId> {
         if self.players_map.len() >= self.max_player {
-            return -1;
+            return Err(ServerError::PlayerLimitReached);
         }
         let pid = self.pid_pool.alloc_id();
-        let new_player = Player::new(receiver, sender);
+        let session_token = loop {
+            let session_token = new_session_token();
+            if !self.player_of_session.contains_key(&session_token) {
+                break session_token
+            }
+        };
+        self.player_of_session.insert(session_token.clone(), pid);
+        let new_player = Player::new(receiver, sender, session_token);
         self.players_map.insert(pid, new_player);
-        pid
+        Ok(pid)
     }
 
-    pub fn remove_player(&mut self, pid: caro_protocol::PlayerId) {
+    pub fn remove_player(&mut self, pid: caro_protocol::PlayerId) -> ServerResult<()> {
+        let player = self.players_map.remove(&pid).ok_or(ServerError::PlayerNotFound(pid))?;
+        // the connection is closed along with the player instead of lingering until the peer goes away,
+        // the request removing it may be one of its own, so what was queued still runs
+        if let Some(response_handler) = &player.response_handler && let Ok(response_handler) = response_handler.try_read() {
+            response_handler.stop_reading();
+        }
+        if self.player_of_session.get(&player.session_token) == Some(&pid) {
+            self.player_of_session.remove(&player.session_token);
+        }
         self.pid_pool.dealloc_id(pid);
-        self.players_map.remove(&pid);
+        Ok(())
     }
 
-    pub fn set_player_state(&mut self, pid: caro_protocol::PlayerId, state: caro_protocol::PlayerState) {
-        if let Some(player) = self.players_map.get_mut(&pid) {
-            player.set_state(state);
+    // every connection is shut and its player dropped, along with the request action
+    // that keeps the executor alive
+    pub async fn close_connections(&mut self) {
+        for (_pid, mut player) in self.players_map.drain() {
+            player.close().await;
         }
+        self.player_of_session.clear();
     }
 
-    pub fn get_player_state(&self, pid: caro_protocol::PlayerId) -> Option<caro_protocol::PlayerState> {
-        self.players_map.get(&pid).map(|p| p.get_state())
+    pub fn get_session_token(&self, pid: caro_protocol::PlayerId) -> ServerResult<caro_protocol::SessionToken> {
+        self.players_map.get(&pid)
+            .map(|p| p.session_token.clone())
+            .ok_or(ServerError::PlayerNotFound(pid))
     }
 
-    pub fn set_connection_state(&mut self, pid: caro_protocol::PlayerId, state: caro_protocol::ConnectState) {
-        if let Some(player) = self.players_map.get_mut(&pid) {
-            player.set_connection_state(state);
-        }
+    pub fn get_peer_ip(&self, pid: caro_protocol::PlayerId) -> ServerResult<std::net::IpAddr> {
+        self.players_map.get(&pid)
+            .map(|p| p.peer_ip)
+            .ok_or(ServerError::PlayerNotFound(pid))
+    }
+
+    pub fn find_player_by_session(&self, session_token: &caro_protocol::SessionToken) -> Option<caro_protocol::PlayerId> {
+        self.player_of_session.get(session_token).copied()
+    }
+
+    // the player carries on with a session it resumed, its own token is dropped
+    pub fn adopt_session(&mut self, pid: caro_protocol::PlayerId, session_token: caro_protocol::SessionToken) -> ServerResult<()> {
+        let player = self.players_map.get_mut(&pid).ok_or(ServerError::PlayerNotFound(pid))?;
+        let old_token = std::mem::replace(&mut player.session_token, session_token.clone());
+        self.player_of_session.remove(&old_token);
+        self.player_of_session.insert(session_token, pid);
+        Ok(())
+    }
+
+    // the only way to change a player's state, illegal transitions are refused
+    pub fn apply_event(&mut self, pid: caro_protocol::PlayerId, event: player_state_machine::StateEvent) -> ServerResult<caro_protocol::PlayerState> {
+        let player = self.players_map.get_mut(&pid).ok_or(ServerError::PlayerNotFound(pid))?;
+        player.apply_event(event)
+    }
+
+    pub fn get_player_state(&self, pid: caro_protocol::PlayerId) -> ServerResult<caro_protocol::PlayerState> {
+        self.players_map.get(&pid)
+            .map(|p| p.get_state())
+            .ok_or(ServerError::PlayerNotFound(pid))
     }
 
-    pub fn get_connection_state(&self, pid: caro_protocol::PlayerId) -> Option<caro_protocol::ConnectState> {
-        self.players_map.get(&pid).map(|p| p.get_connection_state())
+    pub fn get_connection_state(&self, pid: caro_protocol::PlayerId) -> ServerResult<caro_protocol::ConnectState> {
+        self.players_map.get(&pid)
+            .map(|p| p.get_connection_state())
+            .ok_or(ServerError::PlayerNotFound(pid))
     }
 
     pub async fn set_action_on_request(&mut self, pid: caro_protocol::PlayerId, action: server_endpoint::HandleAction) {
@@ -166,8 +233,9 @@ impl PlayerContainer {
         }
     }
 
-    pub async fn get_action_on_request(&self, pid: caro_protocol::PlayerId) -> server_endpoint::HandleAction {
-        self.players_map.get(&pid).unwrap().get_action_on_request().await
+    pub async fn get_action_on_request(&self, pid: caro_protocol::PlayerId) -> ServerResult<server_endpoint::HandleAction> {
+        let player = self.players_map.get(&pid).ok_or(ServerError::PlayerNotFound(pid))?;
+        Ok(player.get_action_on_request().await)
     }
 
     pub async fn handling_request(&mut self, pid: caro_protocol::PlayerId) -> bool {
@@ -190,6 +258,17 @@ impl PlayerContainer {
         }
     }
 
+    // lets a response be sent without keeping the whole container locked meanwhile
+    pub fn get_responser(&self, pid: caro_protocol::PlayerId) -> ServerResult<Arc<RwLock<server_endpoint::Responser>>> {
+        self.players_map.get(&pid)
+            .map(|p| p.get_responser())
+            .ok_or(ServerError::PlayerNotFound(pid))
+    }
+
+    pub fn get_pids(&self) -> Vec<caro_protocol::PlayerId> {
+        self.players_map.keys().copied().collect()
+    }
+
     pub fn player_exist(&self, pid: caro_protocol::PlayerId) -> bool {
         self.players_map.contains_key(&pid)
     }
@@ -0,0 +1,45 @@
+use crate::{
+    caro_protocol,
+    server_error::{ServerError, ServerResult}
+};
+
+// everything that may move a player from one state to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateEvent {
+    EnterRoom,
+    GameStarted,
+    LeaveRoom,
+    ConnectionLost,
+    ConnectionRestored,
+}
+
+pub fn next_state(current: caro_protocol::PlayerState, event: StateEvent) -> ServerResult<caro_protocol::PlayerState> {
+    use caro_protocol::{ConnectState::*, PlayerState::*};
+    let next = match (current, event) {
+        (Logged(Connected), StateEvent::EnterRoom) => InRoom(Connected),
+        // a room may fill while its first player is away, who then comes back into the game
+        (InRoom(conn_state), StateEvent::GameStarted) => InGame(conn_state),
+        // a disconnected player can still be taken out of its room, e.g. on timeout
+        (InRoom(conn_state), StateEvent::LeaveRoom) => Logged(conn_state),
+        (InGame(conn_state), StateEvent::LeaveRoom) => Logged(conn_state),
+        (Logged(Connected), StateEvent::ConnectionLost) => Logged(Disconnected),
+        (InRoom(Connected), StateEvent::ConnectionLost) => InRoom(Disconnected),
+        (InGame(Connected), StateEvent::ConnectionLost) => InGame(Disconnected),
+        (Logged(Disconnected), StateEvent::ConnectionRestored) => Logged(Connected),
+        (InRoom(Disconnected), StateEvent::ConnectionRestored) => InRoom(Connected),
+        (InGame(Disconnected), StateEvent::ConnectionRestored) => InGame(Connected),
+        _ => return Err(ServerError::IllegalTransition(current, event)),
+    };
+    Ok(next)
+}
+
+// which requests a player may send in each state, general requests are always accepted
+pub fn request_allowed(state: caro_protocol::PlayerState, request: &caro_protocol::PlayerCode) -> bool {
+    use caro_protocol::{ConnectState::*, PlayerState::*};
+    match request {
+        caro_protocol::PlayerCode::General(_) => true,
+        caro_protocol::PlayerCode::Logged(_) => state == Logged(Connected),
+        caro_protocol::PlayerCode::InRoom(_) => state == InRoom(Connected),
+        caro_protocol::PlayerCode::InGame(_) => state == InGame(Connected),
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant
+};
+
+// lets bursts through up to capacity, then one request per 1 / refill_per_sec seconds
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn try_take(&mut self) -> bool {
+        self.try_take_at(Instant::now())
+    }
+
+    pub fn try_take_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// open connections per address, shared by the listener and every connection it accepted
+#[derive(Debug, Clone)]
+pub struct ConnectionCounter {
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    max_per_ip: usize,
+}
+
+impl ConnectionCounter {
+    pub fn new(max_per_ip: usize) -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            max_per_ip,
+        }
+    }
+
+    // None once the address already has max_per_ip connections open
+    pub fn try_open(&self, ip: IpAddr) -> Option<ConnectionGuard> {
+        let mut connections = self.connections.lock().unwrap();
+        let open = connections.entry(ip).or_insert(0);
+        if *open >= self.max_per_ip {
+            return None;
+        }
+        *open += 1;
+        Some(ConnectionGuard {
+            connections: self.connections.clone(),
+            ip,
+        })
+    }
+
+    pub fn open_connections(&self, ip: IpAddr) -> usize {
+        self.connections.lock().unwrap().get(&ip).copied().unwrap_or(0)
+    }
+}
+
+// counts as an open connection until dropped
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(open) = connections.get_mut(&self.ip) {
+            *open -= 1;
+            if *open == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, net::IpAddr, time::{Duration, Instant}};
 
 use crate::{
     id_pool,
-    caro_protocol
+    caro_protocol,
+    server_error::{ServerError, ServerResult}
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlayerOrder {
     Player1(caro_protocol::PlayerId),
     Player2(caro_protocol::PlayerId),
@@ -15,32 +16,80 @@ struct GameRoom {
     player1_id: caro_protocol::PlayerId,
     player2_id: caro_protocol::PlayerId,
     rule: caro_protocol::GameRule,
+    game_started: bool,
+    options: caro_protocol::RoomOptions,
+    join_code: Option<caro_protocol::JoinCode>,
+    // seats kept for players of a restored room until they come back
+    player1_reservation: Option<caro_protocol::SessionToken>,
+    player2_reservation: Option<caro_protocol::SessionToken>,
 }
 
 impl GameRoom {
-    fn new(rule: caro_protocol::GameRule) -> Self {
+    fn new(rule: caro_protocol::GameRule, options: caro_protocol::RoomOptions) -> Self {
         Self {
             player1_id: -1,
             player2_id: -1,
             rule,
+            game_started: false,
+            options,
+            join_code: None,
+            player1_reservation: None,
+            player2_reservation: None,
         }
     }
 
+    fn password_matches(&self, password: &Option<String>) -> bool {
+        match &self.options.password {
+            Some(expected) => password.as_ref() == Some(expected),
+            None => true,
+        }
+    }
+
+    fn seat1_taken(&self) -> bool {
+        self.player1_id != -1 || self.player1_reservation.is_some()
+    }
+
+    fn seat2_taken(&self) -> bool {
+        self.player2_id != -1 || self.player2_reservation.is_some()
+    }
+
+    fn player_count(&self) -> usize {
+        [self.seat1_taken(), self.seat2_taken()].iter().filter(|&&taken| taken).count()
+    }
+
     fn is_full(&self) -> bool {
-        self.player1_id != -1 && self.player2_id != -1
+        self.seat1_taken() && self.seat2_taken()
     }
 
     fn is_empty(&self) -> bool {
-        self.player1_id == -1 && self.player2_id == -1
+        !self.seat1_taken() && !self.seat2_taken()
     }
 
-    fn add_player(&mut self, player: PlayerOrder) {
+    fn add_player(&mut self, player: PlayerOrder) -> bool {
         match player {
-            PlayerOrder::Player1(pid) => self.player1_id = pid,
-            PlayerOrder::Player2(pid) => self.player2_id = pid,
+            PlayerOrder::Player1(pid) if !self.seat1_taken() => self.player1_id = pid,
+            PlayerOrder::Player2(pid) if !self.seat2_taken() => self.player2_id = pid,
+            // never push someone else out of their seat
+            _ => return false,
+        }
+        true
+    }
+
+    // the creator's seat is handed out first in case the creator already left
+    fn free_seat(&self, pid: caro_protocol::PlayerId) -> Option<PlayerOrder> {
+        if !self.seat1_taken() {
+            Some(PlayerOrder::Player1(pid))
+        } else if !self.seat2_taken() {
+            Some(PlayerOrder::Player2(pid))
+        } else {
+            None
         }
     }
 
+    fn get_reservations(&self) -> (Option<caro_protocol::SessionToken>, Option<caro_protocol::SessionToken>) {
+        (self.player1_reservation.clone(), self.player2_reservation.clone())
+    }
+
     fn remove_player(&mut self, pid: caro_protocol::PlayerId) {
         if self.player1_id == pid {
             self.player1_id = -1;
@@ -60,10 +109,34 @@ impl GameRoom {
     }
 }
 
+// join codes avoid characters that are easily mixed up when read out loud
+const JOIN_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const JOIN_CODE_LENGTH: usize = 8;
+const MAX_FAILED_JOINS: usize = 5;
+const FAILED_JOIN_WINDOW: Duration = Duration::from_secs(60);
+
+fn new_join_code() -> caro_protocol::JoinCode {
+    (0..JOIN_CODE_LENGTH)
+        .map(|_| JOIN_CODE_ALPHABET[rand::random_range(0..JOIN_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
 pub struct RoomContainer {
     rooms_set: HashMap<caro_protocol::RoomId, GameRoom>,
     max_rooms: usize,
     rid_pool: id_pool::IdPool<i32>,
+    allowed_rules: Vec<caro_protocol::GameRule>,
+    // where every seated player sits, so requests are routed without a scan
+    room_of_player: HashMap<caro_protocol::PlayerId, caro_protocol::RoomId>,
+    join_codes: HashMap<caro_protocol::JoinCode, caro_protocol::RoomId>,
+    // moments of recent failed joins per address, used to stop code and password guessing,
+    // a reconnect gets a new pid but not a new address
+    failed_joins: HashMap<IpAddr, Vec<Instant>>,
+    max_failed_joins: usize,
+    failed_join_window: Duration,
+    reservations: HashMap<caro_protocol::SessionToken, caro_protocol::RoomId>,
+    // reserved seats that are not claimed by then are given up
+    reservation_deadline: Option<Instant>,
 }
 
 impl RoomContainer {
@@ -72,63 +145,309 @@ impl RoomContainer {
             rooms_set: HashMap::new(),
             max_rooms,
             rid_pool,
+            allowed_rules: vec![
+                caro_protocol::GameRule::TicTacToe,
+                caro_protocol::GameRule::FourBlockOne,
+                caro_protocol::GameRule::FiveBlockTwo,
+            ],
+            room_of_player: HashMap::new(),
+            join_codes: HashMap::new(),
+            failed_joins: HashMap::new(),
+            max_failed_joins: MAX_FAILED_JOINS,
+            failed_join_window: FAILED_JOIN_WINDOW,
+            reservations: HashMap::new(),
+            reservation_deadline: None,
         }
     }
+
+    pub fn set_join_limit(&mut self, max_failed_joins: usize, failed_join_window: Duration) {
+        self.max_failed_joins = max_failed_joins;
+        self.failed_join_window = failed_join_window;
+    }
+
+    pub fn set_allowed_rules(&mut self, allowed_rules: Vec<caro_protocol::GameRule>) {
+        self.allowed_rules = allowed_rules;
+    }
+
+    pub fn rule_allowed(&self, rule: caro_protocol::GameRule) -> bool {
+        self.allowed_rules.contains(&rule)
+    }
 }
 
 impl RoomContainer {
-    pub fn add_room(&mut self, rule: caro_protocol::GameRule) -> caro_protocol::RoomId {
+    pub fn add_room(&mut self, rule: caro_protocol::GameRule) -> ServerResult<caro_protocol::RoomId> {
+        self.add_room_with_options(rule, caro_protocol::RoomOptions::default())
+    }
+
+    pub fn add_room_with_options(&mut self, rule: caro_protocol::GameRule, options: caro_protocol::RoomOptions) -> ServerResult<caro_protocol::RoomId> {
+        if !self.rule_allowed(rule) {
+            return Err(ServerError::RuleNotAllowed(rule));
+        }
         if self.rooms_set.len() >= self.max_rooms {
-            return -1;
+            return Err(ServerError::RoomLimitReached);
         }
         let new_rid = self.rid_pool.alloc_id();
-        let new_room = GameRoom::new(rule);
+        let private = options.private;
+        let mut new_room = GameRoom::new(rule, options);
+        if private {
+            let join_code = loop {
+                let join_code = new_join_code();
+                if !self.join_codes.contains_key(&join_code) {
+                    break join_code
+                }
+            };
+            self.join_codes.insert(join_code.clone(), new_rid);
+            new_room.join_code = Some(join_code);
+        }
         self.rooms_set.insert(new_rid, new_room);
-        new_rid
+        Ok(new_rid)
     }
 
-    pub fn remove_room(&mut self, rid: caro_protocol::RoomId) {
+    pub fn remove_room(&mut self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let room = self.rooms_set.remove(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        let (pid1, pid2) = room.get_pids();
+        for pid in [pid1, pid2] {
+            self.room_of_player.remove(&pid);
+        }
+        if let Some(join_code) = room.join_code {
+            self.join_codes.remove(&join_code);
+        }
+        for session_token in [room.player1_reservation, room.player2_reservation].into_iter().flatten() {
+            self.reservations.remove(&session_token);
+        }
         self.rid_pool.dealloc_id(rid);
-        self.rooms_set.remove(&rid);
+        Ok(())
     }
 
-    pub fn add_player_to_room(&mut self, rid: caro_protocol::RoomId, player: PlayerOrder) -> bool {
-        if let Some(room) = self.rooms_set.get_mut(&rid) {
-            room.add_player(player);
-            true
+    // brings back a room from a snapshot under its old id, its seats wait for their session tokens
+    pub fn restore_room(&mut self,
+                        rid: caro_protocol::RoomId,
+                        rule: caro_protocol::GameRule,
+                        options: caro_protocol::RoomOptions,
+                        join_code: Option<caro_protocol::JoinCode>,
+                        reservations: (Option<caro_protocol::SessionToken>, Option<caro_protocol::SessionToken>),
+                        reserved_for: Duration) -> ServerResult<()> {
+        if self.rooms_set.len() >= self.max_rooms {
+            return Err(ServerError::RoomLimitReached);
+        }
+        if let Some(join_code) = &join_code && self.join_codes.contains_key(join_code) {
+            return Err(ServerError::RestoreFailed(rid));
+        }
+        if !self.rid_pool.reserve_id(rid) {
+            return Err(ServerError::RestoreFailed(rid));
+        }
+        let mut room = GameRoom::new(rule, options);
+        if let Some(join_code) = &join_code {
+            self.join_codes.insert(join_code.clone(), rid);
+        }
+        room.join_code = join_code;
+        let (reservation1, reservation2) = reservations;
+        for session_token in [&reservation1, &reservation2].into_iter().flatten() {
+            self.reservations.insert(session_token.clone(), rid);
+        }
+        room.player1_reservation = reservation1;
+        room.player2_reservation = reservation2;
+        self.rooms_set.insert(rid, room);
+        self.reservation_deadline = Some(Instant::now() + reserved_for);
+        Ok(())
+    }
+
+    pub fn find_reservation(&self, session_token: &caro_protocol::SessionToken) -> ServerResult<caro_protocol::RoomId> {
+        self.reservations.get(session_token)
+            .copied()
+            .ok_or(ServerError::SessionNotFound)
+    }
+
+    // seats the player where its session was sitting before the restart
+    pub fn claim_reservation(&mut self, session_token: &caro_protocol::SessionToken, pid: caro_protocol::PlayerId) -> ServerResult<(caro_protocol::RoomId, PlayerOrder)> {
+        if self.find_room_contain_player(pid).is_ok() {
+            return Err(ServerError::SessionNotFound);
+        }
+        let rid = *self.reservations.get(session_token).ok_or(ServerError::SessionNotFound)?;
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        let seat = if room.player1_reservation.as_ref() == Some(session_token) {
+            room.player1_reservation = None;
+            PlayerOrder::Player1(pid)
+        } else if room.player2_reservation.as_ref() == Some(session_token) {
+            room.player2_reservation = None;
+            PlayerOrder::Player2(pid)
         } else {
-            false
+            return Err(ServerError::SessionNotFound);
+        };
+        room.add_player(seat);
+        self.reservations.remove(session_token);
+        self.room_of_player.insert(pid, rid);
+        Ok((rid, seat))
+    }
+
+    pub fn get_reservations(&self, rid: caro_protocol::RoomId) -> ServerResult<(Option<caro_protocol::SessionToken>, Option<caro_protocol::SessionToken>)> {
+        self.rooms_set.get(&rid)
+            .map(|room| room.get_reservations())
+            .ok_or(ServerError::RoomNotFound(rid))
+    }
+
+    // once the deadline passed the remaining reservations are dropped,
+    // returns the rooms that are left without anyone in them
+    pub fn expire_reservations(&mut self) -> Vec<caro_protocol::RoomId> {
+        match self.reservation_deadline {
+            Some(deadline) if Instant::now() >= deadline => {},
+            _ => return Vec::new(),
+        }
+        self.reservation_deadline = None;
+        let mut emptied = Vec::new();
+        for (_session_token, rid) in self.reservations.drain() {
+            if let Some(room) = self.rooms_set.get_mut(&rid) {
+                room.player1_reservation = None;
+                room.player2_reservation = None;
+                if room.is_empty() && !emptied.contains(&rid) {
+                    emptied.push(rid);
+                }
+            }
+        }
+        emptied.sort();
+        emptied
+    }
+
+    pub fn get_room_options(&self, rid: caro_protocol::RoomId) -> ServerResult<caro_protocol::RoomOptions> {
+        self.rooms_set.get(&rid)
+            .map(|room| room.options.clone())
+            .ok_or(ServerError::RoomNotFound(rid))
+    }
+
+    pub fn get_join_code(&self, rid: caro_protocol::RoomId) -> Option<caro_protocol::JoinCode> {
+        self.rooms_set.get(&rid).and_then(|room| room.join_code.clone())
+    }
+
+    // public rooms that can still be joined, in id order
+    pub fn list_public_rooms(&self) -> Vec<caro_protocol::RoomSummary> {
+        let mut rooms: Vec<caro_protocol::RoomSummary> = self.rooms_set.iter()
+            .filter(|&(_rid, room)| !room.options.private && !room.game_started && !room.is_full())
+            .map(|(rid, room)| caro_protocol::RoomSummary {
+                rid: *rid,
+                rule: room.rule,
+                players: room.player_count(),
+                has_password: room.options.password.is_some(),
+            })
+            .collect();
+        rooms.sort_by_key(|summary| summary.rid);
+        rooms
+    }
+
+    pub fn add_player_to_room(&mut self, rid: caro_protocol::RoomId, player: PlayerOrder) -> ServerResult<()> {
+        let pid = match player {
+            PlayerOrder::Player1(pid) => pid,
+            PlayerOrder::Player2(pid) => pid,
+        };
+        if self.find_room_contain_player(pid).is_ok() {
+            return Err(ServerError::JoinRefused(caro_protocol::RoomKey::Id(rid), caro_protocol::JoinFailure::AlreadyInRoom));
+        }
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        if !room.add_player(player) {
+            return Err(ServerError::JoinRefused(caro_protocol::RoomKey::Id(rid), caro_protocol::JoinFailure::RoomFull));
+        }
+        self.room_of_player.insert(pid, rid);
+        Ok(())
+    }
+
+    // seats the player on whichever seat is free, returns the room and the seat taken
+    pub fn join_room(&mut self, key: caro_protocol::RoomKey, pid: caro_protocol::PlayerId, peer_ip: IpAddr, password: Option<String>) -> ServerResult<(caro_protocol::RoomId, PlayerOrder)> {
+        if self.too_many_failed_joins(peer_ip) {
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::TooManyAttempts));
+        }
+        if self.find_room_contain_player(pid).is_ok() {
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::AlreadyInRoom));
+        }
+        // a private room does not exist for anyone asking by its id
+        let target = match &key {
+            caro_protocol::RoomKey::Id(rid) => self.rooms_set.get(rid)
+                .filter(|room| !room.options.private)
+                .map(|_room| *rid),
+            caro_protocol::RoomKey::Code(join_code) => self.join_codes.get(join_code).copied(),
+        };
+        let Some(rid) = target else {
+            self.record_failed_join(peer_ip);
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::NoSuchRoom));
+        };
+        if !self.rooms_set[&rid].password_matches(&password) {
+            self.record_failed_join(peer_ip);
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::WrongPassword));
         }
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        // nobody may step into a game that is already being played
+        if room.game_started {
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::RoomFull));
+        }
+        let Some(seat) = room.free_seat(pid) else {
+            return Err(ServerError::JoinRefused(key, caro_protocol::JoinFailure::RoomFull));
+        };
+        room.add_player(seat);
+        self.room_of_player.insert(pid, rid);
+        Ok((rid, seat))
     }
 
-    pub fn remove_player_from_room(&mut self, rid: caro_protocol::RoomId, pid: caro_protocol::PlayerId) -> bool {
-        if let Some(room) = self.rooms_set.get_mut(&rid) {
-            room.remove_player(pid);
-            true
+    fn too_many_failed_joins(&mut self, peer_ip: IpAddr) -> bool {
+        let window = self.failed_join_window;
+        if let Some(failures) = self.failed_joins.get_mut(&peer_ip) {
+            failures.retain(|moment| moment.elapsed() < window);
+            failures.len() >= self.max_failed_joins
         } else {
             false
         }
     }
 
-    pub fn get_pids_in_room(&self, rid: caro_protocol::RoomId) -> Option<(caro_protocol::PlayerId, caro_protocol::PlayerId)> {
+    fn record_failed_join(&mut self, peer_ip: IpAddr) {
+        let window = self.failed_join_window;
+        // addresses that stopped guessing are dropped, so the map does not grow with every visitor
+        self.failed_joins.retain(|_, failures| failures.last().is_some_and(|moment| moment.elapsed() < window));
+        self.failed_joins.entry(peer_ip).or_default().push(Instant::now());
+    }
+
+    pub fn mark_game_started(&mut self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        room.game_started = true;
+        Ok(())
+    }
+
+    // the room takes new players again, whoever stays waits for the next game
+    pub fn mark_game_ended(&mut self, rid: caro_protocol::RoomId) -> ServerResult<()> {
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        room.game_started = false;
+        Ok(())
+    }
+
+    pub fn game_started(&self, rid: caro_protocol::RoomId) -> bool {
         if let Some(room) = self.rooms_set.get(&rid) {
-            Some(room.get_pids())
+            room.game_started
         } else {
-            None
+            false
         }
     }
 
-    pub fn get_rule_in_room(&self, rid: caro_protocol::RoomId) -> Option<caro_protocol::GameRule> {
-        if let Some(room) = self.rooms_set.get(&rid) {
-            Some(room.get_rule())
-        } else {
-            None
+    pub fn remove_player_from_room(&mut self, rid: caro_protocol::RoomId, pid: caro_protocol::PlayerId) -> ServerResult<()> {
+        let room = self.rooms_set.get_mut(&rid).ok_or(ServerError::RoomNotFound(rid))?;
+        room.remove_player(pid);
+        if self.room_of_player.get(&pid) == Some(&rid) {
+            self.room_of_player.remove(&pid);
         }
+        Ok(())
+    }
+
+    pub fn get_pids_in_room(&self, rid: caro_protocol::RoomId) -> ServerResult<(caro_protocol::PlayerId, caro_protocol::PlayerId)> {
+        self.rooms_set.get(&rid)
+            .map(|room| room.get_pids())
+            .ok_or(ServerError::RoomNotFound(rid))
+    }
+
+    pub fn get_rule_in_room(&self, rid: caro_protocol::RoomId) -> ServerResult<caro_protocol::GameRule> {
+        self.rooms_set.get(&rid)
+            .map(|room| room.get_rule())
+            .ok_or(ServerError::RoomNotFound(rid))
     }
 
+    // both seats hold a player, a seat kept for someone who has not come back does not count
     pub fn room_full(&self, rid: caro_protocol::RoomId) -> bool {
         if let Some(room) = self.rooms_set.get(&rid) {
-            room.is_full()
+            room.player1_id != -1 && room.player2_id != -1
         } else {
             false
         }
@@ -142,19 +461,17 @@ impl RoomContainer {
         }
     }
 
+    pub fn get_rids(&self) -> Vec<caro_protocol::RoomId> {
+        self.rooms_set.keys().copied().collect()
+    }
+
     pub fn room_exist(&self, rid: caro_protocol::RoomId) -> bool {
         self.rooms_set.contains_key(&rid)
     }
 
-    pub fn find_room_contain_player(&self, pid: caro_protocol::PlayerId) -> Option<caro_protocol::RoomId> {
-        let target = self.rooms_set.iter().find(|&(_rid, room)| {
-            let (pid1, pid2) = room.get_pids();
-            pid == pid1 || pid == pid2
-        });
-        if let Some((rid, _room)) = target {
-            Some(*rid)
-        } else {
-            None
-        }
+    pub fn find_room_contain_player(&self, pid: caro_protocol::PlayerId) -> ServerResult<caro_protocol::RoomId> {
+        self.room_of_player.get(&pid)
+            .copied()
+            .ok_or(ServerError::PlayerNotInRoom(pid))
     }
 }
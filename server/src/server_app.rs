@@ -0,0 +1,321 @@
+use std::{future::Future, path::Path, sync::Arc, time::Duration};
+
+use tokio::{sync::RwLock, task::JoinHandle};
+use tracing::Instrument;
+
+use crate::{
+    admin_console,
+    caro_protocol,
+    client_request_executor,
+    event_bus,
+    game_archive,
+    game_manager,
+    id_pool,
+    make_action,
+    make_disconnected_action,
+    metrics,
+    metrics_endpoint,
+    player_life_tracker,
+    player_manager,
+    room_manager,
+    server_config,
+    server_endpoint,
+    snapshot
+};
+
+pub struct CaroServer {
+    listener: server_endpoint::Listener,
+    player_manager: Arc<RwLock<player_manager::PlayerContainer>>,
+    command_executor: Arc<client_request_executor::RequestExecutor>,
+    shutdown_settings: server_config::ShutdownSettings,
+    snapshot_settings: server_config::SnapshotSettings,
+    snapshot_task: Option<JoinHandle<()>>,
+    metrics_address: Option<std::net::SocketAddr>,
+    metrics_task: Option<JoinHandle<()>>,
+    admin_address: Option<std::net::SocketAddr>,
+    admin_task: Option<JoinHandle<()>>,
+    tracker_task: player_life_tracker::TrackingHandler,
+}
+
+impl CaroServer {
+    pub async fn bind(settings: server_config::ServerSettings) -> std::io::Result<Self> {
+        let metrics = Arc::new(metrics::ServerMetrics::new());
+        let mut listener = server_endpoint::Listener::new(&settings.bind_address()).await?;
+        listener.set_metrics(metrics.clone());
+        listener.set_limits(settings.limits);
+        if let Ok(address) = listener.local_addr() {
+            tracing::info!(%address, "listening");
+        }
+
+        let pid_pool = id_pool::IdPool::<i32>::new();
+        let player_manager = Arc::new(RwLock::new(player_manager::PlayerContainer::new(settings.max_players, pid_pool)));
+        let rid_pool = id_pool::IdPool::<i32>::new();
+        let room_manager = Arc::new(RwLock::new(room_manager::RoomContainer::new(settings.max_rooms, rid_pool)));
+        room_manager.write().await.set_allowed_rules(settings.allowed_rules.clone());
+        let gid_pool = id_pool::IdPool::<i32>::new();
+        let game_manager = Arc::new(RwLock::new(game_manager::GameContainer::new(settings.max_games, gid_pool)));
+        game_manager.write().await.set_board_size_limits(settings.board);
+
+        let command_executor = Arc::new(client_request_executor::RequestExecutor::new(player_manager.clone(),
+                                                                                        room_manager.clone(),
+                                                                                        game_manager.clone()));
+
+        if let Some(path) = &settings.snapshot.path && path.exists() {
+            restore_snapshot(&command_executor, path, Duration::from_secs(settings.snapshot.reclaim_timeout_secs)).await;
+        }
+        let snapshot_task = spawn_snapshot_task(command_executor.clone(), settings.snapshot.clone());
+
+        let (metrics_address, metrics_task) = match &settings.metrics.address {
+            Some(address) => {
+                let endpoint = metrics_endpoint::MetricsEndpoint::bind(address).await?;
+                let metrics_address = endpoint.local_addr().ok();
+                if let Some(address) = metrics_address {
+                    tracing::info!(%address, "serving metrics");
+                }
+                (metrics_address, Some(endpoint.serve(metrics.clone(), command_executor.clone())))
+            },
+            None => (None, None),
+        };
+        let (admin_address, admin_task) = match &settings.admin.address {
+            Some(address) => {
+                let console = admin_console::AdminConsole::bind(address).await?;
+                let admin_address = console.local_addr().ok();
+                if let Some(address) = admin_address {
+                    tracing::info!(%address, "admin console listening");
+                }
+                (admin_address, Some(console.serve(command_executor.clone())))
+            },
+            None => (None, None),
+        };
+
+        let event_bus = command_executor.event_bus();
+        let metrics_clone = metrics.clone();
+        event_bus.spawn_subscriber(Box::new(move |event: event_bus::ServerEvent| {
+            if let event_bus::ServerEvent::MoveApplied { .. } = event {
+                metrics_clone.record_move();
+            }
+        }));
+        event_bus.spawn_subscriber(Box::new(|event: event_bus::ServerEvent| {
+            // single moves would drown out everything else
+            match event {
+                event_bus::ServerEvent::MoveApplied { .. } => tracing::debug!(?event, "server event"),
+                _ => tracing::info!(?event, "server event"),
+            }
+        }));
+
+        let player_tracker = Arc::new(RwLock::new(player_life_tracker::PlayerTracker::new(player_manager.clone())));
+        player_tracker.write().await.set_heartbeat(settings.heartbeat);
+        player_tracker.write().await.set_action_on_disconnect(
+            make_disconnected_action!(move |pid: caro_protocol::PlayerId| {
+                let event_bus = event_bus.clone();
+                let future = async move {
+                    event_bus.publish(event_bus::ServerEvent::PlayerDisconnected { pid });
+                    tracing::info!(pid, "player disconnected");
+                };
+                Box::pin(future) as futures::future::BoxFuture<'static, ()>
+            })
+        );
+
+        let executor_clone = command_executor.clone();
+        player_tracker.write().await.set_action_on_disconnect_timeout(
+            make_disconnected_action!(move |pid: caro_protocol::PlayerId| {
+                let command_executor = executor_clone.clone();
+                let metrics = metrics.clone();
+                let future = async move {
+                    metrics.record_heartbeat_timeout();
+                    command_executor.clean_player_existence(pid).await;
+                    tracing::info!(pid, "player removed after heartbeat timeout");
+                };
+                Box::pin(future) as futures::future::BoxFuture<'static, ()>
+            })
+        );
+        let tracker_task = player_life_tracker::PlayerTracker::tracking_player(player_tracker.clone()).await;
+
+        Ok(Self {
+            listener,
+            player_manager,
+            command_executor,
+            shutdown_settings: settings.shutdown,
+            snapshot_settings: settings.snapshot,
+            snapshot_task,
+            metrics_address,
+            metrics_task,
+            admin_address,
+            admin_task,
+            tracker_task,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn metrics_addr(&self) -> Option<std::net::SocketAddr> {
+        self.metrics_address
+    }
+
+    pub fn admin_addr(&self) -> Option<std::net::SocketAddr> {
+        self.admin_address
+    }
+
+    // runs until SIGINT or SIGTERM
+    pub async fn serve(self) {
+        self.serve_until(shutdown_signal()).await;
+    }
+
+    pub async fn serve_until<F: Future<Output = ()>>(mut self, shutdown: F) {
+        tokio::pin!(shutdown);
+        loop {
+            let accepted = tokio::select! {
+                _ = &mut shutdown => break,
+                accepted = self.listener.accept() => accepted,
+            };
+            let (receiver, sender) = match accepted {
+                Ok(endpoints) => endpoints,
+                Err(err) => {
+                    // usually out of file descriptors, give the running games a moment to free some
+                    tracing::warn!(%err, "cannot accept connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                },
+            };
+            let peer = receiver.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+            let new_pid = match self.player_manager.write().await.add_player(receiver, sender) {
+                Ok(pid) => pid,
+                Err(err) => {
+                    // dropping the endpoints closes the connection
+                    tracing::warn!(%peer, %err, "connection refused");
+                    continue;
+                },
+            };
+            // everything logged on behalf of this connection carries its pid
+            let span = tracing::info_span!("player", pid = new_pid, %peer);
+            span.in_scope(|| tracing::info!("player connected"));
+
+            let executor_clone = self.command_executor.clone();
+            let span_clone = span.clone();
+
+            self.player_manager.write().await.set_action_on_request(
+                new_pid,
+                make_action!(move |msg: caro_protocol::MessagePacket| {
+                    let command_executor = executor_clone.clone();
+                    let future = async move {
+                        if let caro_protocol::GenericCode::Player(player_code) = msg.code() {
+                            command_executor.handle_request(new_pid, msg.id(), player_code).await;
+                        }
+                    };
+                    Box::pin(future.instrument(span_clone.clone())) as futures::future::BoxFuture<'static, ()>
+                }
+            )).await;
+
+            self.player_manager.write().await.handling_request(new_pid).instrument(span.clone()).await;
+            self.command_executor.send_session(new_pid).instrument(span).await;
+        }
+
+        self.shutdown().await;
+    }
+
+    async fn shutdown(self) {
+        let CaroServer { listener, player_manager, command_executor, shutdown_settings, snapshot_settings, snapshot_task, metrics_task, admin_task, tracker_task, .. } = self;
+        // nobody new gets in while the others are being sent away
+        drop(listener);
+        if let Some(admin_task) = admin_task {
+            admin_task.abort();
+        }
+        // nobody is timed out of a room that is about to be saved
+        tracker_task.abort();
+        tracing::info!("shutting down");
+
+        command_executor.begin_shutdown().await;
+        if !command_executor.drain(Duration::from_secs(shutdown_settings.timeout_secs)).await {
+            tracing::warn!(timeout_secs = shutdown_settings.timeout_secs, "stopped waiting for running requests");
+        }
+
+        // the last snapshot is taken once nothing changes anymore
+        if let Some(snapshot_task) = snapshot_task {
+            snapshot_task.abort();
+        }
+        if let Some(path) = &snapshot_settings.path {
+            save_snapshot(&command_executor, path).await;
+        }
+
+        let games = command_executor.archive_games().await;
+        if let Some(dir) = &shutdown_settings.archive_dir && !games.is_empty() {
+            match game_archive::write_archive(dir, &games) {
+                Ok(path) => tracing::info!(games = games.len(), path = %path.display(), "archived running games"),
+                Err(err) => tracing::error!(dir = %dir.display(), %err, "cannot archive running games"),
+            }
+        }
+
+        // the sessions were needed for the snapshot, the connections are not needed anymore
+        player_manager.write().await.close_connections().await;
+        if let Some(metrics_task) = metrics_task {
+            metrics_task.abort();
+        }
+        tracing::info!("server stopped");
+    }
+}
+
+async fn restore_snapshot(command_executor: &client_request_executor::RequestExecutor, path: &Path, reserved_for: Duration) {
+    let snapshot = match snapshot::read_snapshot(path) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            tracing::error!(path = %path.display(), %err, "cannot read snapshot");
+            return;
+        },
+    };
+    let failures = command_executor.restore(&snapshot, reserved_for).await;
+    for (rid, err) in failures.iter() {
+        tracing::warn!(rid, %err, "room not restored");
+    }
+    tracing::info!(rooms = snapshot.rooms.len() - failures.len(), path = %path.display(), "restored snapshot");
+}
+
+async fn save_snapshot(command_executor: &client_request_executor::RequestExecutor, path: &Path) {
+    let snapshot = command_executor.snapshot().await;
+    if let Err(err) = snapshot::write_snapshot(path, &snapshot) {
+        tracing::error!(path = %path.display(), %err, "cannot write snapshot");
+    }
+}
+
+fn spawn_snapshot_task(command_executor: Arc<client_request_executor::RequestExecutor>, settings: server_config::SnapshotSettings) -> Option<JoinHandle<()>> {
+    let path = settings.path?;
+    let task = tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(settings.interval_secs));
+            // the first tick fires right away, there is nothing to save yet
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                command_executor.expire_reservations().await;
+                save_snapshot(&command_executor, &path).await;
+            }
+        }
+    );
+    Some(task)
+}
+
+pub async fn shutdown_signal() {
+    // a signal that cannot be listened for never fires
+    let interrupt = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            },
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
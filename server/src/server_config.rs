@@ -0,0 +1,394 @@
+use std::{fmt, path::{Path, PathBuf}};
+
+use clap::Parser;
+use serde::{Serialize, Deserialize};
+
+use crate::caro_protocol;
+
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 12225;
+// the client can only scroll up to coordinate 1023
+pub const BOARD_SIZE_LIMIT: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn to_level_filter(self) -> tracing::level_filters::LevelFilter {
+        match self {
+            LogLevel::Error => tracing::level_filters::LevelFilter::ERROR,
+            LogLevel::Warn => tracing::level_filters::LevelFilter::WARN,
+            LogLevel::Info => tracing::level_filters::LevelFilter::INFO,
+            LogLevel::Debug => tracing::level_filters::LevelFilter::DEBUG,
+            LogLevel::Trace => tracing::level_filters::LevelFilter::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardSizeLimits {
+    pub min_size: usize,
+    pub max_size: usize,
+    pub default_size: usize,
+}
+
+impl Default for BoardSizeLimits {
+    fn default() -> Self {
+        Self {
+            min_size: 5,
+            max_size: BOARD_SIZE_LIMIT,
+            default_size: BOARD_SIZE_LIMIT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeartbeatSettings {
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for HeartbeatSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    // token bucket of every connection, refilled at requests_per_sec up to burst
+    pub requests_per_sec: f64,
+    pub burst: u32,
+    // requests of one player queued up behind the one being executed
+    pub max_pending_requests: usize,
+    pub max_connections_per_ip: usize,
+    // the connection is closed once this many packets could not be decoded
+    pub max_invalid_packets: u32,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self {
+            requests_per_sec: 10.0,
+            burst: 20,
+            max_pending_requests: 8,
+            max_connections_per_ip: 8,
+            max_invalid_packets: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownSettings {
+    pub timeout_secs: u64,
+    // games still running on shutdown are written here, nowhere if unset
+    pub archive_dir: Option<PathBuf>,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            archive_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotSettings {
+    // no snapshots are written or restored if unset
+    pub path: Option<PathBuf>,
+    pub interval_secs: u64,
+    // how long restored seats wait for their players
+    pub reclaim_timeout_secs: u64,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            path: None,
+            interval_secs: 30,
+            reclaim_timeout_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    // host:port serving /metrics and /status, nothing is served if unset
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminSettings {
+    // loopback host:port of the admin console, there is none if unset
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    pub max_players: usize,
+    pub max_rooms: usize,
+    pub max_games: usize,
+    pub allowed_rules: Vec<caro_protocol::GameRule>,
+    pub board: BoardSizeLimits,
+    pub heartbeat: HeartbeatSettings,
+    pub limits: LimitSettings,
+    pub shutdown: ShutdownSettings,
+    pub snapshot: SnapshotSettings,
+    pub metrics: MetricsSettings,
+    pub admin: AdminSettings,
+    pub log_level: LogLevel,
+    pub log_format: LogFormat,
+    // standard output if unset
+    pub log_file: Option<PathBuf>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            max_players: 256,
+            max_rooms: 256,
+            max_games: 256,
+            allowed_rules: vec![
+                caro_protocol::GameRule::TicTacToe,
+                caro_protocol::GameRule::FourBlockOne,
+                caro_protocol::GameRule::FiveBlockTwo,
+            ],
+            board: BoardSizeLimits::default(),
+            heartbeat: HeartbeatSettings::default(),
+            limits: LimitSettings::default(),
+            shutdown: ShutdownSettings::default(),
+            snapshot: SnapshotSettings::default(),
+            metrics: MetricsSettings::default(),
+            admin: AdminSettings::default(),
+            log_level: LogLevel::Info,
+            log_format: LogFormat::Pretty,
+            log_file: None,
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        let settings: ServerSettings = toml::from_str(&content)
+            .map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.host.trim().is_empty() {
+            return Err(ConfigError::Invalid("host must not be empty".to_string()));
+        }
+        if self.max_players == 0 || self.max_rooms == 0 || self.max_games == 0 {
+            return Err(ConfigError::Invalid("max_players, max_rooms and max_games must be greater than 0".to_string()));
+        }
+        if self.allowed_rules.is_empty() {
+            return Err(ConfigError::Invalid("allowed_rules must contain at least one rule".to_string()));
+        }
+        let board = &self.board;
+        if board.min_size == 0 || board.max_size > BOARD_SIZE_LIMIT {
+            return Err(ConfigError::Invalid(format!("board sizes must be between 1 and {}", BOARD_SIZE_LIMIT)));
+        }
+        if board.min_size > board.max_size || board.default_size < board.min_size || board.default_size > board.max_size {
+            return Err(ConfigError::Invalid("board sizes must satisfy min_size <= default_size <= max_size".to_string()));
+        }
+        if self.heartbeat.interval_secs == 0 {
+            return Err(ConfigError::Invalid("heartbeat interval_secs must be greater than 0".to_string()));
+        }
+        if self.heartbeat.timeout_secs < self.heartbeat.interval_secs {
+            return Err(ConfigError::Invalid("heartbeat timeout_secs must not be shorter than interval_secs".to_string()));
+        }
+        let limits = &self.limits;
+        if limits.requests_per_sec.is_nan() || limits.requests_per_sec <= 0.0 || limits.burst == 0 {
+            return Err(ConfigError::Invalid("limits requests_per_sec and burst must be greater than 0".to_string()));
+        }
+        if limits.max_pending_requests == 0 || limits.max_connections_per_ip == 0 || limits.max_invalid_packets == 0 {
+            return Err(ConfigError::Invalid("limits max_pending_requests, max_connections_per_ip and max_invalid_packets must be greater than 0".to_string()));
+        }
+        if self.snapshot.interval_secs == 0 {
+            return Err(ConfigError::Invalid("snapshot interval_secs must be greater than 0".to_string()));
+        }
+        if let Some(address) = &self.metrics.address && address.trim().is_empty() {
+            return Err(ConfigError::Invalid("metrics address must not be empty".to_string()));
+        }
+        // the console asks nobody who they are, so only this machine may reach it
+        if let Some(address) = &self.admin.address && !is_loopback_address(address) {
+            return Err(ConfigError::Invalid("admin address must be a loopback host:port".to_string()));
+        }
+        Ok(())
+    }
+
+    fn apply_overrides(&mut self, args: &ServerArgs) {
+        if let Some(host) = &args.host {
+            self.host = host.clone();
+        }
+        if let Some(port) = args.port {
+            self.port = port;
+        }
+        if let Some(max_players) = args.max_players {
+            self.max_players = max_players;
+        }
+        if let Some(max_rooms) = args.max_rooms {
+            self.max_rooms = max_rooms;
+        }
+        if let Some(max_games) = args.max_games {
+            self.max_games = max_games;
+        }
+        if let Some(rules) = &args.rules {
+            self.allowed_rules = rules.iter().map(|rule| rule.to_game_rule()).collect();
+        }
+        if let Some(log_level) = args.log_level {
+            self.log_level = log_level;
+        }
+        if let Some(log_format) = args.log_format {
+            self.log_format = log_format;
+        }
+        if let Some(path) = &args.log_file {
+            self.log_file = Some(path.clone());
+        }
+        if let Some(path) = &args.snapshot {
+            self.snapshot.path = Some(path.clone());
+        }
+        if let Some(address) = &args.metrics_address {
+            self.metrics.address = Some(address.clone());
+        }
+        if let Some(address) = &args.admin_address {
+            self.admin.address = Some(address.clone());
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "cannot parse {}: {}", path.display(), err),
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn is_loopback_address(address: &str) -> bool {
+    match address.parse::<std::net::SocketAddr>() {
+        Ok(address) => address.ip().is_loopback(),
+        Err(_) => matches!(address.rsplit_once(':'), Some(("localhost", port)) if port.parse::<u16>().is_ok()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RuleArg {
+    #[value(name = "3")]
+    TicTacToe,
+    #[value(name = "4")]
+    FourBlockOne,
+    #[value(name = "5")]
+    FiveBlockTwo,
+}
+
+impl RuleArg {
+    fn to_game_rule(self) -> caro_protocol::GameRule {
+        match self {
+            RuleArg::TicTacToe => caro_protocol::GameRule::TicTacToe,
+            RuleArg::FourBlockOne => caro_protocol::GameRule::FourBlockOne,
+            RuleArg::FiveBlockTwo => caro_protocol::GameRule::FiveBlockTwo,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "simple_caro_app", about = "Caro game server")]
+pub struct ServerArgs {
+    /// Read settings from this TOML file before applying the flags below
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// Validate the given config file and exit
+    #[arg(long, value_name = "FILE", conflicts_with = "config")]
+    pub check_config: Option<PathBuf>,
+    #[arg(long)]
+    pub host: Option<String>,
+    #[arg(short, long)]
+    pub port: Option<u16>,
+    #[arg(long)]
+    pub max_players: Option<usize>,
+    #[arg(long)]
+    pub max_rooms: Option<usize>,
+    #[arg(long)]
+    pub max_games: Option<usize>,
+    /// Comma separated list of the rules players may create rooms with
+    #[arg(long, value_delimiter = ',')]
+    pub rules: Option<Vec<RuleArg>>,
+    #[arg(long)]
+    pub log_level: Option<LogLevel>,
+    #[arg(long)]
+    pub log_format: Option<LogFormat>,
+    /// Append the log to this file instead of printing it
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+    /// Keep a snapshot of the rooms in this file and restore from it on startup
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+    /// Serve /metrics and /status over HTTP on this host:port
+    #[arg(long, value_name = "ADDRESS")]
+    pub metrics_address: Option<String>,
+    /// Accept admin console connections on this loopback host:port
+    #[arg(long, value_name = "ADDRESS")]
+    pub admin_address: Option<String>,
+}
+
+impl ServerArgs {
+    pub fn into_settings(self) -> Result<ServerSettings, ConfigError> {
+        let mut settings = match &self.config {
+            Some(path) => ServerSettings::from_file(path)?,
+            None => ServerSettings::default(),
+        };
+        settings.apply_overrides(&self);
+        settings.validate()?;
+        Ok(settings)
+    }
+}
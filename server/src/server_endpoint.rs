@@ -3,14 +3,31 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpListener;
 use futures::future::BoxFuture;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tokio::task::JoinHandle;
+use tracing::Instrument;
 
-use crate::caro_protocol::{self, ToMessagePacket};
+use crate::{caro_protocol, metrics, rate_limiter, server_config};
 
 pub type HandleAction = Arc<tokio::sync::RwLock<dyn FnMut(caro_protocol::MessagePacket) -> BoxFuture<'static, ()> + Send + Sync + 'static>>;
 
-pub type ResponseHandler = JoinHandle<()>;
+// the reading task of a connection and the worker executing what it queued
+pub struct ResponseHandler {
+    reader: JoinHandle<()>,
+    worker: JoinHandle<()>,
+}
+
+impl ResponseHandler {
+    // whatever was queued already is still executed
+    pub fn stop_reading(&self) {
+        self.reader.abort();
+    }
+
+    pub fn abort(&self) {
+        self.reader.abort();
+        self.worker.abort();
+    }
+}
 
 #[macro_export]
 macro_rules! make_action {
@@ -21,48 +38,114 @@ macro_rules! make_action {
 
 pub struct Listener {
     listener: TcpListener,
+    metrics: Arc<metrics::ServerMetrics>,
+    limits: server_config::LimitSettings,
+    connection_counter: rate_limiter::ConnectionCounter,
 }
 
 impl Listener {
-    pub async fn new(addr: &str) -> Self {
-        Self {
-            listener: TcpListener::bind(addr).await.unwrap(),
-        }
+    pub async fn new(addr: &str) -> std::io::Result<Self> {
+        let limits = server_config::LimitSettings::default();
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            metrics: Arc::new(metrics::ServerMetrics::new()),
+            limits,
+            connection_counter: rate_limiter::ConnectionCounter::new(limits.max_connections_per_ip),
+        })
     }
 
-    pub async fn accept(&mut self) -> (Receiver, Sender) {
-        let (stream, _addr) = self.listener.accept().await.unwrap();
+    // applies to connections accepted from now on
+    pub fn set_limits(&mut self, limits: server_config::LimitSettings) {
+        self.limits = limits;
+        self.connection_counter = rate_limiter::ConnectionCounter::new(limits.max_connections_per_ip);
+    }
+
+    // every connection accepted from now on counts its messages here
+    pub fn set_metrics(&mut self, metrics: Arc<metrics::ServerMetrics>) {
+        self.metrics = metrics;
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // connections over the per address limit are closed right away and never returned
+    pub async fn accept(&mut self) -> std::io::Result<(Receiver, Sender)> {
+        let (stream, connection_guard) = loop {
+            let (stream, addr) = self.listener.accept().await?;
+            match self.connection_counter.try_open(addr.ip()) {
+                Some(connection_guard) => break (stream, connection_guard),
+                None => tracing::warn!(peer = %addr, "too many connections from this address"),
+            }
+        };
         let (receiver, sender) = stream.into_split();
-        (
+        Ok((
             Receiver {
                 receiver,
                 buffer: [0; 1024],
+                metrics: self.metrics.clone(),
+                limits: self.limits,
+                bucket: rate_limiter::TokenBucket::new(self.limits.burst, self.limits.requests_per_sec),
+                frames: caro_protocol::FrameBuffer::new(),
+                invalid_packets: 0,
+                connection_guard: Some(connection_guard),
             },
-            Sender {sender}
-        )
+            Sender {
+                sender,
+                metrics: self.metrics.clone(),
+            }
+        ))
     }
 }
 
 pub struct Sender {
     sender: OwnedWriteHalf,
+    metrics: Arc<metrics::ServerMetrics>,
 }
 
 impl Sender {
     async fn send(&mut self, message: Vec<u8>) {
-        self.sender.write_all(&message).await.unwrap();
-        self.sender.flush().await.unwrap();
+        // a broken connection is noticed by the receiving side
+        if self.sender.write_all(&message).await.is_ok() {
+            self.metrics.record_message_out();
+            let _ = self.sender.flush().await;
+        }
+    }
+
+    async fn close(&mut self) {
+        let _ = self.sender.shutdown().await;
     }
 }
 
 pub struct Receiver {
     receiver: OwnedReadHalf,
     buffer: [u8; 1024],
+    metrics: Arc<metrics::ServerMetrics>,
+    limits: server_config::LimitSettings,
+    bucket: rate_limiter::TokenBucket,
+    frames: caro_protocol::FrameBuffer,
+    invalid_packets: u32,
+    // released as soon as the connection is closed
+    connection_guard: Option<rate_limiter::ConnectionGuard>,
 }
 
 impl Receiver {
-    async fn receive(&mut self) -> (Vec<u8>, usize) {
-        let bytesread = self.receiver.read(&mut self.buffer).await.unwrap();
-        (self.buffer[..bytesread].to_vec(), bytesread)
+    pub fn peer_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.receiver.peer_addr()
+    }
+
+    // the bytes are kept until they make up whole messages
+    async fn receive(&mut self) -> usize {
+        // a reset connection is treated the same as a closed one
+        let bytesread = self.receiver.read(&mut self.buffer).await.unwrap_or(0);
+        self.frames.push(&self.buffer[..bytesread]);
+        bytesread
+    }
+
+    // true once the connection sent too many of them
+    fn note_invalid_packet(&mut self) -> bool {
+        self.invalid_packets += 1;
+        self.invalid_packets >= self.limits.max_invalid_packets
     }
 }
 
@@ -78,17 +161,24 @@ impl Responser {
     }
 
     pub async fn send_response(&mut self, message: caro_protocol::MessagePacket) {
+        tracing::trace!(code = ?message.code(), "sending");
         self.sender.send(message.to_serial()).await;
     }
+
+    pub async fn close(&mut self) {
+        self.sender.close().await;
+    }
 }
 
 pub struct RequestGetter {
     receiver: Receiver,
     action: HandleAction,
+    // rejections are answered directly, they never reach the action
+    responser: Arc<RwLock<Responser>>,
 }
 
 impl RequestGetter {
-    pub fn new(receiver: Receiver) -> Self {
+    pub fn new(receiver: Receiver, responser: Arc<RwLock<Responser>>) -> Self {
         let action = make_action!(|_msg: caro_protocol::MessagePacket| {
             let future = async move {
             };
@@ -97,6 +187,7 @@ impl RequestGetter {
         Self {
             receiver,
             action,
+            responser,
         }
     }
 
@@ -109,23 +200,79 @@ impl RequestGetter {
     }
 
     pub async fn handling_request(target: Arc<RwLock<RequestGetter>>) -> ResponseHandler {
+        let (max_pending_requests, metrics) = {
+            let target = target.read().await;
+            (target.receiver.limits.max_pending_requests, target.receiver.metrics.clone())
+        };
+        // the requests of a connection run one after another in the order they arrived,
+        // other connections are served by their own worker meanwhile
+        let (queue, mut requests) = mpsc::channel::<BoxFuture<'static, ()>>(max_pending_requests);
+        let worker = tokio::spawn(
+            async move {
+                while let Some(request) = requests.recv().await {
+                    request.await;
+                }
+            }.instrument(tracing::Span::current())
+        );
         let target_clone = target.clone();
-        tokio::spawn(
+        let reader = tokio::spawn(
             async move {
                 let target = target_clone.clone();
-                loop {
-                    let (msg, bytesread) = target.write().await.receiver.receive().await;
+                'receiving: loop {
+                    let bytesread = target.write().await.receiver.receive().await;
                     if bytesread == 0 {
                         break;
                     }
-                    println!("recv {:?}", msg);
-                    let msg = msg.to_message_packet();
-                    tokio::spawn(target.read().await.action.write().await(msg));
+                    loop {
+                        let frame = target.write().await.receiver.frames.next_frame();
+                        let decoded = match frame {
+                            None => break,
+                            Some(Ok(frame)) => caro_protocol::MessagePacket::from_serial(&frame).map_err(|err| err.to_string()),
+                            Some(Err(dropped)) => Err(format!("{} bytes without an end of message", dropped)),
+                        };
+                        let msg = match decoded {
+                            Ok(msg) => msg,
+                            Err(err) => {
+                                metrics.record_decode_error();
+                                tracing::debug!(%err, "cannot decode message");
+                                let mut target = target.write().await;
+                                if target.receiver.note_invalid_packet() {
+                                    tracing::warn!(invalid_packets = target.receiver.invalid_packets, "closing connection sending garbage");
+                                    target.responser.write().await.close().await;
+                                    break 'receiving;
+                                }
+                                continue;
+                            },
+                        };
+                        metrics.record_message_in();
+                        tracing::trace!(id = ?msg.id(), code = ?msg.code(), "received");
+
+                        // a flooding client is answered, but its requests are not executed
+                        let (id, code) = (msg.id(), msg.code());
+                        let allowed = target.write().await.receiver.bucket.try_take();
+                        let queued = allowed && {
+                            let request = target.read().await.action.write().await(msg);
+                            queue.try_send(request).is_ok()
+                        };
+                        if !queued {
+                            tracing::debug!(?id, ?code, "rate limited");
+                            let code = caro_protocol::ServerCode::Error(caro_protocol::ErrorResponse::RateLimited);
+                            let responser = target.read().await.responser.clone();
+                            responser.write().await.send_response(caro_protocol::MessagePacket::new_server_packet(code).with_id(id)).await;
+                        }
+                    }
                 }
-            }
-        )
+                target.write().await.receiver.connection_guard.take();
+                tracing::debug!("connection closed");
+            }.instrument(tracing::Span::current())
+        );
+        ResponseHandler {
+            reader,
+            worker,
+        }
     }
 
+    // the queued requests are dropped along with the reading
     pub async fn stop_handling_request(handler: Arc<RwLock<ResponseHandler>>) {
         handler.write().await.abort();
     }
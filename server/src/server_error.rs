@@ -0,0 +1,76 @@
+use std::fmt;
+
+use crate::{caro_protocol, player_state_machine};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerError {
+    PlayerNotFound(caro_protocol::PlayerId),
+    PlayerNotInRoom(caro_protocol::PlayerId),
+    RoomNotFound(caro_protocol::RoomId),
+    GameNotFound(caro_protocol::GameId),
+    NoGameInRoom(caro_protocol::RoomId),
+    PlayerLimitReached,
+    RoomLimitReached,
+    GameLimitReached,
+    RuleNotAllowed(caro_protocol::GameRule),
+    BoardSizeNotAllowed(usize),
+    NotAllowedInState(caro_protocol::PlayerState),
+    IllegalTransition(caro_protocol::PlayerState, player_state_machine::StateEvent),
+    JoinRefused(caro_protocol::RoomKey, caro_protocol::JoinFailure),
+    SessionNotFound,
+    RestoreFailed(caro_protocol::RoomId),
+}
+
+pub type ServerResult<T> = Result<T, ServerError>;
+
+impl ServerError {
+    pub fn to_response(self) -> caro_protocol::ServerCode {
+        let code = match self {
+            // a refused join has its own answer the client already waits for
+            ServerError::JoinRefused(key, reason) => {
+                return caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::FailedToJoinRoom(key, reason));
+            },
+            ServerError::PlayerNotFound(_) => caro_protocol::ErrorResponse::UnknownPlayer,
+            ServerError::PlayerNotInRoom(_) => caro_protocol::ErrorResponse::NotInRoom,
+            ServerError::RoomNotFound(rid) => caro_protocol::ErrorResponse::NoSuchRoom(rid),
+            // a game always lives in a room, the client only knows about the latter
+            ServerError::GameNotFound(_) => caro_protocol::ErrorResponse::NotInRoom,
+            ServerError::NoGameInRoom(rid) => caro_protocol::ErrorResponse::NoGameInRoom(rid),
+            ServerError::PlayerLimitReached |
+            ServerError::RoomLimitReached |
+            ServerError::GameLimitReached => caro_protocol::ErrorResponse::ServerFull,
+            ServerError::RuleNotAllowed(rule) => caro_protocol::ErrorResponse::RuleNotAllowed(rule),
+            ServerError::BoardSizeNotAllowed(board_size) => caro_protocol::ErrorResponse::BoardSizeNotAllowed(board_size),
+            ServerError::NotAllowedInState(state) |
+            ServerError::IllegalTransition(state, _) => caro_protocol::ErrorResponse::NotAllowedInState(state),
+            ServerError::SessionNotFound => caro_protocol::ErrorResponse::UnknownSession,
+            // only happens while starting up, no player ever asked for it
+            ServerError::RestoreFailed(rid) => caro_protocol::ErrorResponse::NoSuchRoom(rid),
+        };
+        caro_protocol::ServerCode::Error(code)
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::PlayerNotFound(pid) => write!(f, "player {} does not exist", pid),
+            ServerError::PlayerNotInRoom(pid) => write!(f, "player {} is not in any room", pid),
+            ServerError::RoomNotFound(rid) => write!(f, "room {} does not exist", rid),
+            ServerError::GameNotFound(gid) => write!(f, "game {} does not exist", gid),
+            ServerError::NoGameInRoom(rid) => write!(f, "room {} has no game", rid),
+            ServerError::PlayerLimitReached => write!(f, "player limit reached"),
+            ServerError::RoomLimitReached => write!(f, "room limit reached"),
+            ServerError::GameLimitReached => write!(f, "game limit reached"),
+            ServerError::RuleNotAllowed(rule) => write!(f, "rule {:?} is not allowed on this server", rule),
+            ServerError::BoardSizeNotAllowed(board_size) => write!(f, "board size {} is out of the allowed range", board_size),
+            ServerError::NotAllowedInState(state) => write!(f, "request not allowed in state {:?}", state),
+            ServerError::IllegalTransition(state, event) => write!(f, "cannot apply {:?} in state {:?}", event, state),
+            ServerError::JoinRefused(key, reason) => write!(f, "cannot join room {:?}: {:?}", key, reason),
+            ServerError::SessionNotFound => write!(f, "no seat is kept for this session"),
+            ServerError::RestoreFailed(rid) => write!(f, "room {} cannot be restored", rid),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
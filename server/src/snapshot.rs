@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::caro_protocol;
+
+// everything needed to bring the rooms back after a restart,
+// players are known by their session token since player ids do not survive
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ServerSnapshot {
+    pub rooms: Vec<RoomSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub rid: caro_protocol::RoomId,
+    pub rule: caro_protocol::GameRule,
+    pub options: caro_protocol::RoomOptions,
+    pub join_code: Option<caro_protocol::JoinCode>,
+    pub player1: Option<caro_protocol::SessionToken>,
+    pub player2: Option<caro_protocol::SessionToken>,
+    // only rooms whose game was started have one
+    pub game: Option<GameSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub board_size: usize,
+    pub moves: Vec<(caro_protocol::PlayerOrder, caro_protocol::Coordinate)>,
+    pub turn: caro_protocol::GameState,
+}
+
+// player 1 always opens, so the two histories are merged back taking turns
+pub fn order_moves(player1_moves: &[caro_protocol::Coordinate], player2_moves: &[caro_protocol::Coordinate]) -> Vec<(caro_protocol::PlayerOrder, caro_protocol::Coordinate)> {
+    let mut moves = Vec::with_capacity(player1_moves.len() + player2_moves.len());
+    for turn in 0..player1_moves.len().max(player2_moves.len()) {
+        if let Some(coordinate) = player1_moves.get(turn) {
+            moves.push((caro_protocol::PlayerOrder::Player1, *coordinate));
+        }
+        if let Some(coordinate) = player2_moves.get(turn) {
+            moves.push((caro_protocol::PlayerOrder::Player2, *coordinate));
+        }
+    }
+    moves
+}
+
+// written next to the target first, so a crash never leaves half a snapshot behind
+pub fn write_snapshot(path: &Path, snapshot: &ServerSnapshot) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() && !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(snapshot)?;
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)
+}
+
+pub fn read_snapshot(path: &Path) -> std::io::Result<ServerSnapshot> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::TcpStream};
+
+use simple_caro_app::{
+    admin_console::{self, AdminCommand},
+    caro_protocol,
+    event_bus::ServerEvent,
+    server_app::CaroServer,
+    server_config::{AdminSettings, ServerSettings},
+    server_error::ServerError
+};
+
+use common::{check_consistency, Harness};
+
+async fn start_game(harness: &mut Harness) -> (caro_protocol::PlayerId, caro_protocol::PlayerId, caro_protocol::RoomId) {
+    let pid1 = harness.connect_player().await;
+    let pid2 = harness.connect_player().await;
+    harness.request(pid1, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ))).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(pid1).unwrap();
+    harness.request(pid2, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None))).await.unwrap();
+    harness.request(pid1, caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((1, 1)))).await.unwrap();
+    (pid1, pid2, rid)
+}
+
+#[test]
+fn commands_are_parsed() {
+    assert_eq!(admin_console::parse_command("players"), AdminCommand::Players);
+    assert_eq!(admin_console::parse_command("  kick 7 "), AdminCommand::Kick(7));
+    assert_eq!(admin_console::parse_command("close 3"), AdminCommand::CloseRoom(3));
+    assert_eq!(admin_console::parse_command("game 2"), AdminCommand::DumpGame(2));
+    assert_eq!(admin_console::parse_command("broadcast back in  5 minutes"), AdminCommand::Broadcast("back in  5 minutes".to_string()));
+    assert_eq!(admin_console::parse_command(""), AdminCommand::Empty);
+    assert_eq!(admin_console::parse_command("exit"), AdminCommand::Quit);
+    assert!(matches!(admin_console::parse_command("kick someone"), AdminCommand::Invalid(_)));
+    assert!(matches!(admin_console::parse_command("broadcast"), AdminCommand::Invalid(_)));
+    assert!(matches!(admin_console::parse_command("reboot"), AdminCommand::Invalid(_)));
+}
+
+#[tokio::test]
+async fn closing_a_room_aborts_its_game() {
+    let mut harness = Harness::new().await;
+    let (pid1, pid2, rid) = start_game(&mut harness).await;
+    let gid = harness.game_manager.read().await.find_game_contain_room(rid).unwrap();
+    let mut events = harness.executor.event_bus().subscribe();
+
+    harness.executor.close_room(rid).await.unwrap();
+    assert!(!harness.room_manager.read().await.room_exist(rid));
+    assert!(harness.game_manager.read().await.get_gids().is_empty());
+    for pid in [pid1, pid2] {
+        assert_eq!(harness.player_manager.read().await.get_player_state(pid), Ok(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected)));
+    }
+    let mut ended = None;
+    while let Ok(event) = events.try_recv() {
+        if let ServerEvent::GameEnded { .. } = event {
+            ended = Some(event);
+        }
+    }
+    assert_eq!(ended, Some(ServerEvent::GameEnded { rid, gid, state: caro_protocol::GameState::Aborted }));
+    assert_eq!(harness.executor.close_room(rid).await, Err(ServerError::RoomNotFound(rid)));
+    check_consistency(&harness).await.unwrap();
+}
+
+#[tokio::test]
+async fn kicking_a_player_frees_its_seat() {
+    let mut harness = Harness::new().await;
+    let (pid1, pid2, rid) = start_game(&mut harness).await;
+
+    harness.executor.kick_player(pid1).await.unwrap();
+    assert!(!harness.player_manager.read().await.player_exist(pid1));
+    assert_eq!(harness.room_manager.read().await.get_pids_in_room(rid), Ok((-1, pid2)));
+    assert_eq!(harness.executor.kick_player(pid1).await, Err(ServerError::PlayerNotFound(pid1)));
+    check_consistency(&harness).await.unwrap();
+}
+
+#[tokio::test]
+async fn listings_show_players_rooms_and_games() {
+    let mut harness = Harness::new().await;
+    let (pid1, _pid2, rid) = start_game(&mut harness).await;
+    let idle = harness.connect_player().await;
+    let gid = harness.game_manager.read().await.find_game_contain_room(rid).unwrap();
+
+    let players = admin_console::execute_command(&harness.executor, AdminCommand::Players).await;
+    assert!(players.contains(&format!("player {}: InGame(Connected) in room {}", pid1, rid)), "{}", players);
+    assert!(players.contains(&format!("player {}: Logged(Connected)\n", idle)), "{}", players);
+
+    let rooms = admin_console::execute_command(&harness.executor, AdminCommand::Rooms).await;
+    assert!(rooms.contains(&format!("room {}: TicTacToe", rid)), "{}", rooms);
+    assert!(rooms.contains(&format!("game {} Player2Turn", gid)), "{}", rooms);
+
+    let games = admin_console::execute_command(&harness.executor, AdminCommand::Games).await;
+    assert_eq!(games, format!("game {} in room {}: Player2Turn, 1 moves\n", gid, rid));
+
+    let dump = admin_console::execute_command(&harness.executor, AdminCommand::DumpGame(gid)).await;
+    assert!(dump.contains("player1_move_history"), "{}", dump);
+    let missing = admin_console::execute_command(&harness.executor, AdminCommand::DumpGame(gid + 100)).await;
+    assert!(missing.starts_with("error:"), "{}", missing);
+
+    let sent = admin_console::execute_command(&harness.executor, AdminCommand::Broadcast("hello".to_string())).await;
+    assert_eq!(sent, "sent to 3 players\n");
+}
+
+#[test]
+fn console_is_only_reachable_from_this_machine() {
+    let with_admin = |address: &str| ServerSettings {
+        admin: AdminSettings { address: Some(address.to_string()) },
+        ..Default::default()
+    };
+    for address in ["127.0.0.1:7000", "[::1]:7000", "localhost:7000"] {
+        assert!(with_admin(address).validate().is_ok(), "{} was refused", address);
+    }
+    for address in ["0.0.0.0:7000", "192.168.1.20:7000", "example.com:7000", "localhost", ""] {
+        assert!(with_admin(address).validate().is_err(), "{} was accepted", address);
+    }
+}
+
+#[tokio::test]
+async fn console_answers_over_tcp() {
+    let settings = ServerSettings {
+        port: 0,
+        admin: AdminSettings { address: Some("127.0.0.1:0".to_string()) },
+        ..Default::default()
+    };
+    let server = CaroServer::bind(settings).await.unwrap();
+    let admin_addr = server.admin_addr().unwrap();
+    let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server.serve_until(async move {
+        let _ = stopped.await;
+    }));
+
+    let stream = TcpStream::connect(admin_addr).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let greeting = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert!(greeting.contains("admin console"));
+    writer.write_all(b"rooms\nquit\n").await.unwrap();
+    let answer = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(answer, "> no rooms");
+    // quit closes the connection
+    let mut rest = Vec::new();
+    while let Some(line) = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap() {
+        rest.push(line);
+    }
+    assert_eq!(rest, vec!["> ".to_string()]);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
@@ -0,0 +1,238 @@
+// shared by several test crates, each one only uses part of it
+#![allow(dead_code)]
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream, sync::RwLock};
+
+use simple_caro_app::{
+    caro_protocol,
+    client_request_executor::RequestExecutor,
+    game_manager::GameContainer,
+    id_pool::IdPool,
+    player_manager::PlayerContainer,
+    room_manager::RoomContainer,
+    server_app::CaroServer,
+    server_config::{LimitSettings, ServerSettings},
+    server_endpoint::Listener,
+    server_error::ServerError
+};
+
+// a request executor wired to real containers, with one loopback connection per player
+pub struct Harness {
+    listener: Listener,
+    pub player_manager: Arc<RwLock<PlayerContainer>>,
+    pub room_manager: Arc<RwLock<RoomContainer>>,
+    pub game_manager: Arc<RwLock<GameContainer>>,
+    pub executor: Arc<RequestExecutor>,
+    // keeps the client side of every connection open
+    clients: Vec<TcpStream>,
+}
+
+impl Harness {
+    pub async fn new() -> Self {
+        Self::with_capacity(16).await
+    }
+
+    pub async fn with_capacity(capacity: usize) -> Self {
+        let mut listener = Listener::new("127.0.0.1:0").await.unwrap();
+        // every player connects from the loopback address
+        listener.set_limits(LimitSettings { max_connections_per_ip: capacity, ..Default::default() });
+        let player_manager = Arc::new(RwLock::new(PlayerContainer::new(capacity, IdPool::<i32>::new())));
+        let room_manager = Arc::new(RwLock::new(RoomContainer::new(capacity, IdPool::<i32>::new())));
+        let game_manager = Arc::new(RwLock::new(GameContainer::new(capacity, IdPool::<i32>::new())));
+        let executor = Arc::new(RequestExecutor::new(player_manager.clone(), room_manager.clone(), game_manager.clone()));
+        Self {
+            listener,
+            player_manager,
+            room_manager,
+            game_manager,
+            executor,
+            clients: Vec::new(),
+        }
+    }
+
+    pub async fn connect_player(&mut self) -> caro_protocol::PlayerId {
+        let addr = self.listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (receiver, sender) = self.listener.accept().await.unwrap();
+        self.clients.push(client);
+        self.player_manager.write().await.add_player(receiver, sender).unwrap()
+    }
+
+    pub async fn request(&mut self, pid: caro_protocol::PlayerId, code: caro_protocol::PlayerCode) -> Result<(), ServerError> {
+        self.executor.execute_request(pid, code).await
+    }
+}
+
+// a real server on a free port, stopped through the sender
+pub async fn start_server() -> (std::net::SocketAddr, tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+    let settings = ServerSettings {
+        port: 0,
+        ..Default::default()
+    };
+    let server = CaroServer::bind(settings).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server.serve_until(async move {
+        let _ = stopped.await;
+    }));
+    (addr, stop_server, serving)
+}
+
+pub fn create_room() -> caro_protocol::PlayerCode {
+    create_room_with(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default())
+}
+
+pub fn create_room_with(rule: caro_protocol::GameRule, options: caro_protocol::RoomOptions) -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(rule, options))
+}
+
+pub fn join_room(rid: caro_protocol::RoomId) -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None))
+}
+
+pub fn play(coordinate: caro_protocol::Coordinate) -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove(coordinate))
+}
+
+pub fn request(id: caro_protocol::RequestId, code: caro_protocol::PlayerCode) -> Vec<u8> {
+    caro_protocol::MessagePacket::new_player_packet(code).with_id(Some(id)).to_serial()
+}
+
+// a connection as the client sees it, packets are split back out of the stream
+pub struct Connection {
+    pub stream: TcpStream,
+    frames: caro_protocol::FrameBuffer,
+}
+
+impl Connection {
+    pub async fn open(addr: std::net::SocketAddr) -> Self {
+        let mut connection = Self {
+            stream: TcpStream::connect(addr).await.unwrap(),
+            frames: caro_protocol::FrameBuffer::new(),
+        };
+        let (_, first) = connection.next().await;
+        assert!(matches!(first, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Session(_))));
+        connection
+    }
+
+    pub async fn next(&mut self) -> (Option<caro_protocol::RequestId>, caro_protocol::ServerCode) {
+        let mut buffer = [0; 1024];
+        loop {
+            if let Some(frame) = self.frames.next_frame() {
+                let msg = caro_protocol::MessagePacket::from_serial(&frame.unwrap()).unwrap();
+                match msg.code() {
+                    caro_protocol::GenericCode::Server(code) => return (msg.id(), code),
+                    caro_protocol::GenericCode::Player(code) => panic!("server sent a player code {:?}", code),
+                }
+            }
+            let bytesread = tokio::time::timeout(Duration::from_secs(5), self.stream.read(&mut buffer)).await.unwrap().unwrap();
+            assert!(bytesread > 0, "connection closed");
+            self.frames.push(&buffer[..bytesread]);
+        }
+    }
+
+    // skips everything else the server pushes in between
+    pub async fn next_matching(&mut self, matches: impl Fn(&caro_protocol::ServerCode) -> bool) -> (Option<caro_protocol::RequestId>, caro_protocol::ServerCode) {
+        loop {
+            let (id, code) = self.next().await;
+            if matches(&code) {
+                return (id, code);
+            }
+        }
+    }
+}
+
+pub fn in_game(code: &caro_protocol::ServerCode) -> bool {
+    matches!(code, caro_protocol::ServerCode::InGame(_))
+}
+
+// sends an in game request and waits for the answer to it
+pub async fn send_ingame(player: &mut Connection, id: caro_protocol::RequestId, code: caro_protocol::InGameRequest) -> caro_protocol::InGameResponse {
+    player.stream.write_all(&request(id, caro_protocol::PlayerCode::InGame(code))).await.unwrap();
+    loop {
+        match player.next_matching(in_game).await {
+            (Some(answered), caro_protocol::ServerCode::InGame(response)) if answered == id => return response,
+            _ => continue,
+        }
+    }
+}
+
+pub async fn next_ingame(player: &mut Connection, expected: impl Fn(&caro_protocol::InGameResponse) -> bool) -> caro_protocol::InGameResponse {
+    let (_, code) = tokio::time::timeout(
+        Duration::from_secs(5),
+        player.next_matching(|code| matches!(code, caro_protocol::ServerCode::InGame(response) if expected(response)))
+    ).await.unwrap();
+    match code {
+        caro_protocol::ServerCode::InGame(response) => response,
+        other => panic!("expected an in game response, got {:?}", other),
+    }
+}
+
+pub async fn check_consistency(harness: &Harness) -> Result<(), String> {
+    let player_manager = harness.player_manager.read().await;
+    let room_manager = harness.room_manager.read().await;
+    let game_manager = harness.game_manager.read().await;
+    check_containers(&player_manager, &room_manager, &game_manager)
+}
+
+// player state, room membership and game membership must always agree
+pub fn check_containers(player_manager: &PlayerContainer, room_manager: &RoomContainer, game_manager: &GameContainer) -> Result<(), String> {
+    for pid in player_manager.get_pids() {
+        let state = player_manager.get_player_state(pid).unwrap();
+        let room = room_manager.find_room_contain_player(pid);
+        match (state, room) {
+            (caro_protocol::PlayerState::Logged(_), Err(_)) => {},
+            (caro_protocol::PlayerState::Logged(_), Ok(rid)) => return Err(format!("player {} is {:?} but sits in room {}", pid, state, rid)),
+            (caro_protocol::PlayerState::InRoom(_) | caro_protocol::PlayerState::InGame(_), Err(_)) => return Err(format!("player {} is {:?} without a room", pid, state)),
+            (caro_protocol::PlayerState::InRoom(_) | caro_protocol::PlayerState::InGame(_), Ok(rid)) => {
+                if game_manager.find_game_contain_room(rid).is_err() {
+                    return Err(format!("room {} of player {} has no game", rid, pid));
+                }
+            },
+        }
+    }
+
+    for rid in room_manager.get_rids() {
+        if room_manager.room_empty(rid) {
+            return Err(format!("room {} is empty but still exists", rid));
+        }
+        let (pid1, pid2) = room_manager.get_pids_in_room(rid).unwrap();
+        for pid in [pid1, pid2] {
+            if pid != -1 && !player_manager.player_exist(pid) {
+                return Err(format!("room {} holds removed player {}", rid, pid));
+            }
+        }
+        // both seats may be empty while a restored room waits for its players
+        if pid1 == pid2 && pid1 != -1 {
+            return Err(format!("player {} holds both seats of room {}", pid1, rid));
+        }
+        // once the game runs, whoever sits in the room plays it, connected or not
+        if room_manager.game_started(rid) {
+            for pid in [pid1, pid2] {
+                if pid == -1 {
+                    continue;
+                }
+                let state = player_manager.get_player_state(pid).unwrap();
+                if !matches!(state, caro_protocol::PlayerState::InGame(_)) {
+                    return Err(format!("room {} runs a game but player {} is {:?}", rid, pid, state));
+                }
+            }
+        }
+        let games = game_manager.get_gids().into_iter()
+            .filter(|gid| game_manager.get_rid_of_game(*gid) == Ok(rid))
+            .count();
+        if games != 1 {
+            return Err(format!("room {} has {} games", rid, games));
+        }
+    }
+
+    for gid in game_manager.get_gids() {
+        let rid = game_manager.get_rid_of_game(gid).unwrap();
+        if !room_manager.room_exist(rid) {
+            return Err(format!("game {} outlived room {}", gid, rid));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc
+};
+
+use tokio::sync::broadcast;
+
+use simple_caro_app::{
+    caro_protocol::{self, GameState, PlayerOrder},
+    event_bus::{EventBus, ServerEvent}
+};
+
+use common::{check_containers, create_room, join_room, play, Harness};
+
+fn drain(receiver: &mut broadcast::Receiver<ServerEvent>) -> Vec<ServerEvent> {
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn a_whole_game_is_published() {
+    let mut harness = Harness::new().await;
+    let mut events = harness.executor.event_bus().subscribe();
+    let player1 = harness.connect_player().await;
+    let player2 = harness.connect_player().await;
+
+    harness.request(player1, create_room()).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(player1).unwrap();
+    harness.request(player2, join_room(rid)).await.unwrap();
+    let gid = harness.game_manager.read().await.find_game_contain_room(rid).unwrap();
+    assert_eq!(drain(&mut events), vec![
+        ServerEvent::PlayerJoined { pid: player1, rid, order: PlayerOrder::Player1 },
+        ServerEvent::PlayerJoined { pid: player2, rid, order: PlayerOrder::Player2 },
+        ServerEvent::GameStarted { rid, gid, player1, player2 },
+    ]);
+
+    // player 1 fills the top row
+    let moves = [(player1, (0, 0)), (player2, (1, 0)), (player1, (0, 1)), (player2, (1, 1)), (player1, (0, 2))];
+    for (pid, coordinate) in moves {
+        harness.request(pid, play(coordinate)).await.unwrap();
+    }
+    let published = drain(&mut events);
+    assert_eq!(published.len(), moves.len() + 1);
+    assert_eq!(published[0], ServerEvent::MoveApplied { rid, gid, order: PlayerOrder::Player1, coordinate: (0, 0) });
+    assert_eq!(published[1], ServerEvent::MoveApplied { rid, gid, order: PlayerOrder::Player2, coordinate: (1, 0) });
+    assert_eq!(published[moves.len()], ServerEvent::GameEnded { rid, gid, state: GameState::Player1Won });
+}
+
+#[tokio::test]
+async fn rejected_moves_and_failed_requests_publish_nothing() {
+    let mut harness = Harness::new().await;
+    let mut events = harness.executor.event_bus().subscribe();
+    let player1 = harness.connect_player().await;
+    let player2 = harness.connect_player().await;
+    assert!(harness.request(player1, join_room(5)).await.is_err());
+    assert_eq!(drain(&mut events), vec![]);
+
+    harness.request(player1, create_room()).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(player1).unwrap();
+    harness.request(player2, join_room(rid)).await.unwrap();
+    drain(&mut events);
+    // not player 2's turn yet
+    harness.request(player2, play((0, 0))).await.unwrap();
+    assert_eq!(drain(&mut events), vec![]);
+}
+
+#[tokio::test]
+async fn leaving_and_timeouts_are_published() {
+    let mut harness = Harness::new().await;
+    let mut events = harness.executor.event_bus().subscribe();
+    let player1 = harness.connect_player().await;
+    harness.request(player1, create_room()).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(player1).unwrap();
+    drain(&mut events);
+
+    harness.executor.clean_player_existence(player1).await;
+    assert_eq!(drain(&mut events), vec![ServerEvent::PlayerLeft { pid: player1, rid }]);
+}
+
+#[tokio::test]
+async fn every_subscriber_gets_every_event() {
+    let bus = EventBus::new();
+    let mut first = bus.subscribe();
+    let mut second = bus.subscribe();
+    bus.publish(ServerEvent::PlayerDisconnected { pid: 3 });
+    assert_eq!(drain(&mut first), vec![ServerEvent::PlayerDisconnected { pid: 3 }]);
+    assert_eq!(drain(&mut second), vec![ServerEvent::PlayerDisconnected { pid: 3 }]);
+}
+
+#[tokio::test]
+async fn spawned_subscribers_are_called() {
+    let bus = EventBus::new();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    bus.spawn_subscriber(Box::new(move |event| {
+        let _ = sender.send(event);
+    }));
+    bus.publish(ServerEvent::PlayerDisconnected { pid: 1 });
+    assert_eq!(receiver.recv().await, Some(ServerEvent::PlayerDisconnected { pid: 1 }));
+}
+
+// another task looking at the managers must never catch a room halfway through a change
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn changes_are_never_seen_half_applied() {
+    let mut harness = Harness::new().await;
+    let mut pids = Vec::new();
+    for _ in 0..4 {
+        pids.push(harness.connect_player().await);
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let observer = {
+        let stop = stop.clone();
+        let player_manager = harness.player_manager.clone();
+        let room_manager = harness.room_manager.clone();
+        let game_manager = harness.game_manager.clone();
+        tokio::spawn(async move {
+            let mut checks = 0;
+            while !stop.load(Ordering::Relaxed) {
+                {
+                    let player_manager = player_manager.read().await;
+                    let room_manager = room_manager.read().await;
+                    let game_manager = game_manager.read().await;
+                    check_containers(&player_manager, &room_manager, &game_manager)?;
+                }
+                checks += 1;
+                tokio::task::yield_now().await;
+            }
+            Ok::<usize, String>(checks)
+        })
+    };
+
+    let leave = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom);
+    for _ in 0..20 {
+        harness.request(pids[0], create_room()).await.unwrap();
+        let rid = harness.room_manager.read().await.find_room_contain_player(pids[0]).unwrap();
+        harness.request(pids[1], join_room(rid)).await.unwrap();
+        harness.request(pids[0], play((1, 1))).await.unwrap();
+        harness.request(pids[0], leave.clone()).await.unwrap();
+        harness.request(pids[1], leave.clone()).await.unwrap();
+    }
+    stop.store(true, Ordering::Relaxed);
+    let checks = observer.await.unwrap().unwrap();
+    assert!(checks > 0);
+}
+
+#[tokio::test]
+async fn a_refused_join_changes_and_publishes_nothing() {
+    let mut harness = Harness::new().await;
+    let player1 = harness.connect_player().await;
+    let player2 = harness.connect_player().await;
+    harness.request(player1, create_room()).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(player1).unwrap();
+    // the game goes away behind the executor's back, so the room cannot start once full
+    let gid = harness.game_manager.read().await.find_game_contain_room(rid).unwrap();
+    harness.game_manager.write().await.remove_game(gid).unwrap();
+    let mut events = harness.executor.event_bus().subscribe();
+
+    assert!(harness.request(player2, join_room(rid)).await.is_err());
+    assert_eq!(harness.room_manager.read().await.get_pids_in_room(rid), Ok((player1, -1)));
+    assert!(harness.room_manager.read().await.find_room_contain_player(player2).is_err());
+    assert_eq!(
+        harness.player_manager.read().await.get_player_state(player2),
+        Ok(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected))
+    );
+    assert_eq!(drain(&mut events), vec![]);
+}
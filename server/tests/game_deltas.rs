@@ -0,0 +1,134 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::caro_protocol;
+
+use common::{request, start_server, Connection};
+
+fn context(player1_moves: Vec<caro_protocol::Coordinate>, player2_moves: Vec<caro_protocol::Coordinate>, game_state: caro_protocol::GameState) -> caro_protocol::GameContext {
+    caro_protocol::GameContext {
+        seq: 0,
+        board_height: 3,
+        board_width: 3,
+        player1_move_history: player1_moves,
+        player2_move_history: player2_moves,
+        player1_undone_moves: Vec::new(),
+        player2_undone_moves: Vec::new(),
+        game_state,
+        player1_connection_state: caro_protocol::ConnectState::Connected,
+        player2_connection_state: caro_protocol::ConnectState::Connected,
+        receiver_order: caro_protocol::PlayerOrder::Player1,
+    }
+}
+
+fn in_game(code: &caro_protocol::ServerCode) -> bool {
+    matches!(code, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Context(_)) |
+                   caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Delta(_)))
+}
+
+#[test]
+fn deltas_carry_only_what_changed() {
+    let before = context(vec![(0, 0), (1, 1)], vec![(2, 2)], caro_protocol::GameState::Player2Turn);
+    assert_eq!(caro_protocol::GameDelta::between(1, &before, &before), None);
+
+    // player1 takes its last move back and the turn passes back to it
+    let mut after = context(vec![(0, 0)], vec![(2, 2)], caro_protocol::GameState::Player1Turn);
+    after.player1_undone_moves = vec![(1, 1)];
+    after.player2_connection_state = caro_protocol::ConnectState::Disconnected;
+    let delta = caro_protocol::GameDelta::between(1, &before, &after).unwrap();
+    assert_eq!(delta.removed_moves, vec![(caro_protocol::PlayerOrder::Player1, (1, 1))]);
+    assert!(delta.added_moves.is_empty());
+    assert_eq!(delta.player1_undone_moves, Some(vec![(1, 1)]));
+    assert_eq!(delta.player2_undone_moves, None);
+    assert_eq!(delta.game_state, Some(caro_protocol::GameState::Player1Turn));
+    assert_eq!(delta.player1_connection_state, None);
+    assert_eq!(delta.player2_connection_state, Some(caro_protocol::ConnectState::Disconnected));
+
+    let mut local = before.clone();
+    assert!(local.apply_delta(&delta));
+    after.seq = 1;
+    assert_eq!(local, after);
+    // a delta seen twice changes nothing
+    assert!(local.apply_delta(&delta));
+    assert_eq!(local, after);
+}
+
+#[test]
+fn a_missed_delta_is_noticed() {
+    let first = context(Vec::new(), Vec::new(), caro_protocol::GameState::Player1Turn);
+    let second = context(vec![(1, 1)], Vec::new(), caro_protocol::GameState::Player2Turn);
+    let third = context(vec![(1, 1)], vec![(0, 0)], caro_protocol::GameState::Player1Turn);
+    let missed = caro_protocol::GameDelta::between(1, &first, &second).unwrap();
+    let next = caro_protocol::GameDelta::between(2, &second, &third).unwrap();
+
+    let mut local = first.clone();
+    assert!(!local.apply_delta(&next));
+    assert_eq!(local, first);
+    assert!(local.apply_delta(&missed));
+    assert!(local.apply_delta(&next));
+    assert_eq!((local.player1_move_history, local.player2_move_history, local.seq), (vec![(1, 1)], vec![(0, 0)], 2));
+}
+
+#[tokio::test]
+async fn moves_are_pushed_as_deltas() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+
+    let create = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ));
+    player1.stream.write_all(&request(1, create)).await.unwrap();
+    let rid = match player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    let join = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+    player2.stream.write_all(&request(1, join)).await.unwrap();
+
+    // both start from the whole context
+    for (player, order) in [(&mut player1, caro_protocol::PlayerOrder::Player1), (&mut player2, caro_protocol::PlayerOrder::Player2)] {
+        match player.next_matching(in_game).await.1 {
+            caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Context(game_context)) => {
+                assert_eq!(game_context.seq, 0);
+                assert_eq!(game_context.receiver_order, order);
+                assert!(game_context.player1_move_history.is_empty());
+            },
+            other => panic!("expected the game context, got {:?}", other),
+        }
+    }
+
+    let player_move = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((1, 1)));
+    player1.stream.write_all(&request(2, player_move)).await.unwrap();
+    for player in [&mut player1, &mut player2] {
+        match player.next_matching(in_game).await.1 {
+            caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Delta(delta)) => {
+                assert_eq!(delta.seq, 1);
+                assert_eq!(delta.added_moves, vec![(caro_protocol::PlayerOrder::Player1, (1, 1))]);
+                assert!(delta.removed_moves.is_empty());
+                assert_eq!(delta.game_state, Some(caro_protocol::GameState::Player2Turn));
+            },
+            other => panic!("expected a delta, got {:?}", other),
+        }
+    }
+
+    // a resync only goes to the one asking
+    let resync = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRequestContext);
+    player2.stream.write_all(&request(2, resync)).await.unwrap();
+    match player2.next_matching(in_game).await {
+        (Some(2), caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Context(game_context))) => {
+            assert_eq!(game_context.seq, 1);
+            assert_eq!(game_context.player1_move_history, vec![(1, 1)]);
+        },
+        other => panic!("expected the game context, got {:?}", other),
+    }
+    let nothing = tokio::time::timeout(Duration::from_millis(200), player1.next_matching(in_game)).await;
+    assert!(nothing.is_err(), "{:?}", nothing);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
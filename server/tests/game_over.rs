@@ -0,0 +1,68 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::caro_protocol;
+
+use common::{in_game, next_ingame, request, send_ingame, start_server, Connection};
+
+#[tokio::test]
+async fn the_winning_line_is_announced_and_both_can_play_again() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+
+    let create = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ));
+    player1.stream.write_all(&request(1, create)).await.unwrap();
+    let rid = match player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    let join = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+    player2.stream.write_all(&request(1, join)).await.unwrap();
+    player2.next_matching(in_game).await;
+
+    // too early to ask for another game
+    let rematch = caro_protocol::InGameRequest::RequestRematch;
+    assert_eq!(send_ingame(&mut player1, 2, rematch).await,
+               caro_protocol::InGameResponse::MoveRejected(caro_protocol::MoveRejection::GameStillInProgress));
+
+    // player1 takes the anti diagonal
+    let moves = [(3, (0, 2)), (2, (0, 0)), (4, (1, 1)), (3, (0, 1)), (5, (2, 0))];
+    for (turn, (id, coordinate)) in moves.into_iter().enumerate() {
+        let player = if turn % 2 == 0 { &mut player1 } else { &mut player2 };
+        assert_eq!(send_ingame(player, id, caro_protocol::InGameRequest::PlayerMove(coordinate)).await, caro_protocol::InGameResponse::MoveSuccess);
+    }
+    let is_game_over = |response: &caro_protocol::InGameResponse| matches!(response, caro_protocol::InGameResponse::GameOver(_));
+    for player in [&mut player1, &mut player2] {
+        assert_eq!(next_ingame(player, is_game_over).await, caro_protocol::InGameResponse::GameOver(caro_protocol::GameResult {
+            game_state: caro_protocol::GameState::Player1Won,
+            reason: caro_protocol::GameEndReason::LineCompleted,
+            winning_line: vec![(0, 2), (1, 1), (2, 0)],
+        }));
+    }
+
+    // the board stays as it is until both asked
+    player2.stream.write_all(&request(4, caro_protocol::PlayerCode::InGame(rematch))).await.unwrap();
+    next_ingame(&mut player1, |response| *response == caro_protocol::InGameResponse::RematchOffered).await;
+    player1.stream.write_all(&request(6, caro_protocol::PlayerCode::InGame(rematch))).await.unwrap();
+    for player in [&mut player1, &mut player2] {
+        next_ingame(player, |response| *response == caro_protocol::InGameResponse::RematchStarted).await;
+        match next_ingame(player, |response| matches!(response, caro_protocol::InGameResponse::Delta(_))).await {
+            caro_protocol::InGameResponse::Delta(delta) => {
+                assert_eq!(delta.game_state, Some(caro_protocol::GameState::Player1Turn));
+                assert_eq!(delta.removed_moves.len(), 5);
+            },
+            other => panic!("expected a delta, got {:?}", other),
+        }
+    }
+    assert_eq!(send_ingame(&mut player1, 7, caro_protocol::InGameRequest::PlayerMove((1, 1))).await, caro_protocol::InGameResponse::MoveSuccess);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
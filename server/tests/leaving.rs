@@ -0,0 +1,137 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::caro_protocol;
+
+use common::{create_room, join_room, next_ingame, play, request, start_server, Connection};
+
+async fn next_state(player: &mut Connection) -> (Option<caro_protocol::RequestId>, caro_protocol::ServerCode) {
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        player.next_matching(|code| matches!(code, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(_))))
+    ).await.unwrap()
+}
+
+// player 1 has made the first move when it returns
+async fn start_game(addr: std::net::SocketAddr) -> (Connection, Connection, caro_protocol::RoomId) {
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+    player1.stream.write_all(&request(1, create_room())).await.unwrap();
+    let rid = match player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    player2.stream.write_all(&request(1, join_room(rid))).await.unwrap();
+    next_ingame(&mut player2, |response| matches!(response, caro_protocol::InGameResponse::Context(_))).await;
+    player1.stream.write_all(&request(2, play((1, 1)))).await.unwrap();
+    next_ingame(&mut player2, |response| matches!(response, caro_protocol::InGameResponse::Delta(_))).await;
+    (player1, player2, rid)
+}
+
+#[tokio::test]
+async fn leaving_a_waiting_room_goes_back_to_the_lobby() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player = Connection::open(addr).await;
+
+    player.stream.write_all(&request(1, create_room())).await.unwrap();
+    player.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await;
+    let leave = caro_protocol::PlayerCode::InRoom(caro_protocol::InRoomRequest::PlayerLeaveRoom);
+    player.stream.write_all(&request(2, leave)).await.unwrap();
+    let (id, code) = next_state(&mut player).await;
+    assert_eq!(id, Some(2));
+    assert!(matches!(code, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(
+        caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected)))), "{:?}", code);
+
+    // the room went away with its only player
+    player.stream.write_all(&request(3, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms))).await.unwrap();
+    match player.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::RoomList(rooms)) => assert!(rooms.is_empty(), "{:?}", rooms),
+        other => panic!("expected the room list, got {:?}", other),
+    }
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn leaving_a_game_in_progress_forfeits_it() {
+    let (addr, stop_server, serving) = start_server().await;
+    let (mut player1, mut player2, _rid) = start_game(addr).await;
+
+    let leave = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom);
+    player1.stream.write_all(&request(3, leave.clone())).await.unwrap();
+    let (id, code) = next_state(&mut player1).await;
+    assert_eq!(id, Some(3));
+    assert!(matches!(code, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::State(
+        caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected)))), "{:?}", code);
+
+    // the one who stays is told, then wins by forfeit
+    next_ingame(&mut player2, |response| *response == caro_protocol::InGameResponse::OpponentLeft).await;
+    match next_ingame(&mut player2, |response| matches!(response, caro_protocol::InGameResponse::Delta(_))).await {
+        caro_protocol::InGameResponse::Delta(delta) => {
+            assert_eq!(delta.game_state, Some(caro_protocol::GameState::Player2Won));
+            assert_eq!(delta.player1_connection_state, Some(caro_protocol::ConnectState::Disconnected));
+        },
+        other => panic!("expected a delta, got {:?}", other),
+    }
+    let is_game_over = |response: &caro_protocol::InGameResponse| matches!(response, caro_protocol::InGameResponse::GameOver(_));
+    assert_eq!(next_ingame(&mut player2, is_game_over).await, caro_protocol::InGameResponse::GameOver(caro_protocol::GameResult {
+        game_state: caro_protocol::GameState::Player2Won,
+        reason: caro_protocol::GameEndReason::Forfeit,
+        winning_line: Vec::new(),
+    }));
+
+    // nobody is there to accept a rematch, and leaving a finished game just leaves
+    let rematch = caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::RequestRematch);
+    player2.stream.write_all(&request(2, rematch)).await.unwrap();
+    let (id, code) = player2.next_matching(|code| matches!(code, caro_protocol::ServerCode::InGame(_))).await;
+    assert_eq!(id, Some(2));
+    assert!(matches!(code, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::OpponentLeft)), "{:?}", code);
+    player2.stream.write_all(&request(3, leave)).await.unwrap();
+    let (id, _) = next_state(&mut player2).await;
+    assert_eq!(id, Some(3));
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn exiting_a_game_in_progress_forfeits_it_and_frees_the_room() {
+    let (addr, stop_server, serving) = start_server().await;
+    let (mut player1, mut player2, rid) = start_game(addr).await;
+
+    let exit = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerExitApplication);
+    player1.stream.write_all(&request(3, exit)).await.unwrap();
+    next_ingame(&mut player2, |response| *response == caro_protocol::InGameResponse::OpponentLeft).await;
+    let is_game_over = |response: &caro_protocol::InGameResponse| matches!(response, caro_protocol::InGameResponse::GameOver(_));
+    assert_eq!(next_ingame(&mut player2, is_game_over).await, caro_protocol::InGameResponse::GameOver(caro_protocol::GameResult {
+        game_state: caro_protocol::GameState::Player2Won,
+        reason: caro_protocol::GameEndReason::Forfeit,
+        winning_line: Vec::new(),
+    }));
+
+    // the free seat can be taken and a new game starts on an empty board
+    let mut player3 = Connection::open(addr).await;
+    player3.stream.write_all(&request(1, join_room(rid))).await.unwrap();
+    match next_ingame(&mut player3, |response| matches!(response, caro_protocol::InGameResponse::Context(_))).await {
+        caro_protocol::InGameResponse::Context(context) => {
+            assert_eq!(context.game_state, caro_protocol::GameState::Player1Turn);
+            assert!(context.player1_move_history.is_empty() && context.player2_move_history.is_empty(), "{:?}", context);
+        },
+        other => panic!("expected a context, got {:?}", other),
+    }
+    // the one who stayed already has the old board and is told what changed
+    match next_ingame(&mut player2, |response| matches!(response, caro_protocol::InGameResponse::Delta(_))).await {
+        caro_protocol::InGameResponse::Delta(delta) => {
+            assert_eq!(delta.game_state, Some(caro_protocol::GameState::Player1Turn));
+            assert_eq!(delta.removed_moves, vec![(caro_protocol::PlayerOrder::Player1, (1, 1))]);
+        },
+        other => panic!("expected a delta, got {:?}", other),
+    }
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
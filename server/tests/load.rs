@@ -0,0 +1,76 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use simple_caro_app::caro_protocol;
+
+use common::{check_consistency, create_room, join_room, play, Harness};
+
+const GAMES_PER_ROOM: usize = 3;
+
+fn leave() -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom)
+}
+
+// every room plays the same short games at the same time, returns requests per second
+async fn run_rooms(rooms: usize) -> f64 {
+    let mut harness = Harness::with_capacity(rooms * 2).await;
+    let mut pairs = Vec::new();
+    for _ in 0..rooms {
+        let player1 = harness.connect_player().await;
+        let player2 = harness.connect_player().await;
+        pairs.push((player1, player2));
+    }
+
+    let started = Instant::now();
+    let mut tasks = Vec::new();
+    for (player1, player2) in pairs {
+        let executor = harness.executor.clone();
+        let room_manager = harness.room_manager.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut requests = 0;
+            for _ in 0..GAMES_PER_ROOM {
+                executor.execute_request(player1, create_room()).await.unwrap();
+                let rid = room_manager.read().await.find_room_contain_player(player1).unwrap();
+                executor.execute_request(player2, join_room(rid)).await.unwrap();
+                // player 1 fills the top row
+                let moves = [(player1, (0, 0)), (player2, (1, 0)), (player1, (0, 1)), (player2, (1, 1)), (player1, (0, 2))];
+                for (pid, coordinate) in moves {
+                    executor.execute_request(pid, play(coordinate)).await.unwrap();
+                }
+                executor.execute_request(player1, leave()).await.unwrap();
+                executor.execute_request(player2, leave()).await.unwrap();
+                requests += 4 + moves.len();
+            }
+            requests
+        }));
+    }
+
+    let mut requests = 0;
+    for task in tasks {
+        requests += task.await.unwrap();
+    }
+    let elapsed = started.elapsed();
+    check_consistency(&harness).await.unwrap();
+    assert!(harness.room_manager.read().await.get_rids().is_empty());
+
+    requests as f64 / elapsed.max(Duration::from_millis(1)).as_secs_f64()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_rooms_stay_consistent() {
+    for rooms in [1, 8, 64] {
+        run_rooms(rooms).await;
+    }
+}
+
+// timing depends on the machine and whatever else runs on it, so this only runs when asked:
+// cargo test --test load -- --ignored
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore]
+async fn throughput_grows_with_concurrent_rooms() {
+    let single = run_rooms(1).await;
+    let many = run_rooms(64).await;
+    // rooms wait on each other while players are seated or leave, not while they play
+    assert!(many > single * 4.0, "{:.0} requests/s with many rooms against {:.0} with one", many, single);
+}
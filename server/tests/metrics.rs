@@ -0,0 +1,136 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
+
+use simple_caro_app::{
+    caro_protocol,
+    metrics::{RoomStatus, SeatStatus, ServerMetrics, ServerStatus},
+    server_app::CaroServer,
+    server_config::{MetricsSettings, ServerSettings}
+};
+
+use common::Harness;
+
+async fn http_get(addr: std::net::SocketAddr, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+    let mut response = String::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut response)).await.unwrap().unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+#[test]
+fn metrics_are_rendered_as_prometheus_text() {
+    let metrics = ServerMetrics::new();
+    metrics.record_message_in();
+    metrics.record_message_in();
+    metrics.record_decode_error();
+    metrics.record_move();
+    let status = ServerStatus {
+        players: 3,
+        rooms: vec![RoomStatus {
+            rid: 1,
+            rule: caro_protocol::GameRule::TicTacToe,
+            private: false,
+            player1: Some(SeatStatus::Player { pid: 1, state: caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected) }),
+            player2: Some(SeatStatus::Reserved),
+            gid: Some(1),
+            game_state: Some(caro_protocol::GameState::Player2Turn),
+        }],
+    };
+
+    let text = metrics.render(&status);
+    for line in [
+        "caro_players_connected 3",
+        "caro_rooms{rule=\"TicTacToe\"} 1",
+        "caro_rooms{rule=\"FiveBlockTwo\"} 0",
+        "caro_games_in_progress 1",
+        "caro_moves_total 1",
+        "caro_messages_received_total 2",
+        "caro_messages_sent_total 0",
+        "caro_decode_errors_total 1",
+        "caro_heartbeat_timeouts_total 0",
+        "# TYPE caro_moves_total counter",
+    ] {
+        assert!(text.lines().any(|rendered| rendered == line), "missing {:?} in\n{}", line, text);
+    }
+    assert!(metrics.moves_per_second() > 0.0);
+}
+
+#[tokio::test]
+async fn status_lists_rooms_and_their_players() {
+    let mut harness = Harness::new().await;
+    let pid1 = harness.connect_player().await;
+    let pid2 = harness.connect_player().await;
+    let host = harness.connect_player().await;
+    harness.request(pid1, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ))).await.unwrap();
+    let running = harness.room_manager.read().await.find_room_contain_player(pid1).unwrap();
+    harness.request(pid2, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(running), None))).await.unwrap();
+    harness.request(host, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::FiveBlockTwo,
+        caro_protocol::RoomOptions { private: true, password: None, board_size: None }
+    ))).await.unwrap();
+    let waiting = harness.room_manager.read().await.find_room_contain_player(host).unwrap();
+
+    let status = harness.executor.status().await;
+    assert_eq!(status.players, 3);
+    assert_eq!(status.rooms.len(), 2);
+    let running = status.rooms.iter().find(|room| room.rid == running).unwrap();
+    assert_eq!(running.player1, Some(SeatStatus::Player { pid: pid1, state: caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected) }));
+    assert_eq!(running.game_state, Some(caro_protocol::GameState::Player1Turn));
+    assert!(running.in_progress());
+    let waiting = status.rooms.iter().find(|room| room.rid == waiting).unwrap();
+    assert!(waiting.private);
+    assert_eq!(waiting.player2, None);
+    assert_eq!(waiting.game_state, None);
+    assert!(!waiting.in_progress());
+}
+
+#[tokio::test]
+async fn server_counts_traffic_on_its_metrics_endpoint() {
+    let settings = ServerSettings {
+        port: 0,
+        metrics: MetricsSettings { address: Some("127.0.0.1:0".to_string()) },
+        ..Default::default()
+    };
+    let server = CaroServer::bind(settings).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let metrics_addr = server.metrics_addr().unwrap();
+    let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server.serve_until(async move {
+        let _ = stopped.await;
+    }));
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 1024];
+    // the session token
+    tokio::time::timeout(Duration::from_secs(5), client.read(&mut buffer)).await.unwrap().unwrap();
+    client.write_all(b"not a message\n").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let request = caro_protocol::MessagePacket::new_player_packet(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms));
+    client.write_all(&request.to_serial()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), client.read(&mut buffer)).await.unwrap().unwrap();
+
+    let (status_line, body) = http_get(metrics_addr, "/metrics").await;
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    for line in ["caro_players_connected 1", "caro_decode_errors_total 1", "caro_messages_received_total 1", "caro_messages_sent_total 2"] {
+        assert!(body.lines().any(|rendered| rendered == line), "missing {:?} in\n{}", line, body);
+    }
+
+    let (status_line, body) = http_get(metrics_addr, "/status").await;
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    let status: ServerStatus = serde_json::from_str(&body).unwrap();
+    assert_eq!(status, ServerStatus { players: 1, rooms: Vec::new() });
+
+    let (status_line, _body) = http_get(metrics_addr, "/nothing").await;
+    assert_eq!(status_line, "HTTP/1.1 404 Not Found");
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::caro_protocol;
+
+use common::{in_game, request, send_ingame, start_server, Connection};
+
+#[tokio::test]
+async fn refused_moves_say_why() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+
+    let create = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ));
+    player1.stream.write_all(&request(1, create)).await.unwrap();
+    let rid = match player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    let join = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+    player2.stream.write_all(&request(1, join)).await.unwrap();
+    player2.next_matching(in_game).await;
+
+    let rejected = |reason| caro_protocol::InGameResponse::MoveRejected(reason);
+    assert_eq!(send_ingame(&mut player2, 2, caro_protocol::InGameRequest::PlayerMove((0, 0))).await, rejected(caro_protocol::MoveRejection::NotYourTurn));
+    assert_eq!(send_ingame(&mut player1, 2, caro_protocol::InGameRequest::PlayerMove((1, 1))).await, caro_protocol::InGameResponse::MoveSuccess);
+    assert_eq!(send_ingame(&mut player2, 3, caro_protocol::InGameRequest::PlayerMove((1, 1))).await, rejected(caro_protocol::MoveRejection::AlreadyOccupied((1, 1))));
+    assert_eq!(send_ingame(&mut player2, 4, caro_protocol::InGameRequest::PlayerMove((7, 7))).await, rejected(caro_protocol::MoveRejection::OutOfBounds((7, 7))));
+    assert_eq!(send_ingame(&mut player2, 5, caro_protocol::InGameRequest::PlayerUndo).await, rejected(caro_protocol::MoveRejection::NothingToUndo));
+    assert_eq!(send_ingame(&mut player2, 6, caro_protocol::InGameRequest::PlayerRedo).await, rejected(caro_protocol::MoveRejection::NothingToRedo));
+
+    // refusals change nothing, so player1 only saw its own move go through
+    let mut deltas = 0;
+    while let Ok((_, code)) = tokio::time::timeout(Duration::from_millis(200), player1.next_matching(in_game)).await {
+        if let caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Delta(_)) = code {
+            deltas += 1;
+        }
+    }
+    assert_eq!(deltas, 1);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
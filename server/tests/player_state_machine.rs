@@ -0,0 +1,142 @@
+mod common;
+
+use proptest::prelude::*;
+
+use simple_caro_app::{
+    caro_protocol::{self, ConnectState::*, PlayerState::*},
+    player_state_machine::{self, StateEvent},
+    server_error::ServerError
+};
+
+use common::{check_consistency, Harness};
+
+#[test]
+fn legal_transitions() {
+    assert_eq!(player_state_machine::next_state(Logged(Connected), StateEvent::EnterRoom), Ok(InRoom(Connected)));
+    assert_eq!(player_state_machine::next_state(InRoom(Connected), StateEvent::GameStarted), Ok(InGame(Connected)));
+    assert_eq!(player_state_machine::next_state(InRoom(Disconnected), StateEvent::GameStarted), Ok(InGame(Disconnected)));
+    assert_eq!(player_state_machine::next_state(InRoom(Connected), StateEvent::LeaveRoom), Ok(Logged(Connected)));
+    assert_eq!(player_state_machine::next_state(InGame(Disconnected), StateEvent::LeaveRoom), Ok(Logged(Disconnected)));
+    assert_eq!(player_state_machine::next_state(InGame(Connected), StateEvent::ConnectionLost), Ok(InGame(Disconnected)));
+    assert_eq!(player_state_machine::next_state(Logged(Disconnected), StateEvent::ConnectionRestored), Ok(Logged(Connected)));
+}
+
+#[test]
+fn illegal_transitions() {
+    let illegal = [
+        (Logged(Connected), StateEvent::GameStarted),
+        (Logged(Connected), StateEvent::LeaveRoom),
+        (Logged(Disconnected), StateEvent::EnterRoom),
+        (InRoom(Connected), StateEvent::EnterRoom),
+        (InGame(Connected), StateEvent::GameStarted),
+        (InGame(Disconnected), StateEvent::ConnectionLost),
+        (InRoom(Connected), StateEvent::ConnectionRestored),
+    ];
+    for (state, event) in illegal {
+        assert_eq!(player_state_machine::next_state(state, event), Err(ServerError::IllegalTransition(state, event)));
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Request(usize, caro_protocol::PlayerCode),
+    ConnectionLost(usize),
+    ConnectionRestored(usize),
+    // what the heartbeat tracker does once a silent player times out
+    Timeout(usize),
+}
+
+const PLAYERS: usize = 4;
+
+fn request_strategy() -> impl Strategy<Value = caro_protocol::PlayerCode> {
+    prop_oneof![
+        Just(caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerRequestState)),
+        Just(caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerExitApplication)),
+        Just(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default()))),
+        Just(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::FiveBlockTwo, caro_protocol::RoomOptions::default()))),
+        Just(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+            caro_protocol::GameRule::TicTacToe,
+            caro_protocol::RoomOptions { private: true, password: Some("pw".to_string()), board_size: None }
+        ))),
+        (0..4i32, proptest::option::of(Just("pw".to_string()))).prop_map(|(rid, password)| {
+            caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), password))
+        }),
+        Just(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms)),
+        Just(caro_protocol::PlayerCode::InRoom(caro_protocol::InRoomRequest::PlayerLeaveRoom)),
+        (0..3i64, 0..3i64).prop_map(|coor| caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove(coor))),
+        Just(caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerUndo)),
+        Just(caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRedo)),
+        Just(caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRequestContext)),
+        Just(caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom)),
+    ]
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        6 => (0..PLAYERS, request_strategy()).prop_map(|(player, request)| Op::Request(player, request)),
+        1 => (0..PLAYERS).prop_map(Op::ConnectionLost),
+        1 => (0..PLAYERS).prop_map(Op::ConnectionRestored),
+        1 => (0..PLAYERS).prop_map(Op::Timeout),
+    ]
+}
+
+async fn run_ops(ops: Vec<Op>) -> Result<(), String> {
+    let mut harness = Harness::new().await;
+    let mut pids = Vec::new();
+    for _ in 0..PLAYERS {
+        pids.push(harness.connect_player().await);
+    }
+
+    for op in ops {
+        match op.clone() {
+            Op::Request(player, request) => {
+                harness.executor.handle_request(pids[player], None, request).await;
+            },
+            Op::ConnectionLost(player) => {
+                let _ = harness.player_manager.write().await.apply_event(pids[player], StateEvent::ConnectionLost);
+            },
+            Op::ConnectionRestored(player) => {
+                let _ = harness.player_manager.write().await.apply_event(pids[player], StateEvent::ConnectionRestored);
+            },
+            Op::Timeout(player) => {
+                harness.executor.clean_player_existence(pids[player]).await;
+            },
+        }
+        check_consistency(&harness).await.map_err(|reason| format!("after {:?}: {}", op, reason))?;
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn state_rooms_and_games_stay_consistent(ops in proptest::collection::vec(op_strategy(), 1..40)) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let result = runtime.block_on(run_ops(ops));
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+}
+
+// the creator dropped before anyone joined, the game still has to start for both seats
+#[tokio::test]
+async fn a_room_fills_while_its_creator_is_disconnected() {
+    let mut harness = Harness::new().await;
+    let creator = harness.connect_player().await;
+    let joiner = harness.connect_player().await;
+    harness.request(creator, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default()))).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(creator).unwrap();
+    harness.player_manager.write().await.apply_event(creator, StateEvent::ConnectionLost).unwrap();
+
+    harness.request(joiner, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(
+        caro_protocol::RoomKey::Id(rid), None))).await.unwrap();
+    check_consistency(&harness).await.unwrap();
+    assert_eq!(harness.player_manager.read().await.get_player_state(joiner).unwrap(), InGame(Connected));
+    assert_eq!(harness.player_manager.read().await.get_player_state(creator).unwrap(), InGame(Disconnected));
+
+    // the joiner can play right away and the creator comes back into the running game
+    harness.request(joiner, caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((1, 1)))).await.unwrap();
+    harness.player_manager.write().await.apply_event(creator, StateEvent::ConnectionRestored).unwrap();
+    assert_eq!(harness.player_manager.read().await.get_player_state(creator).unwrap(), InGame(Connected));
+}
@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
+
+use simple_caro_app::{
+    caro_protocol,
+    rate_limiter::{ConnectionCounter, TokenBucket},
+    server_app::CaroServer,
+    server_config::{LimitSettings, ServerSettings}
+};
+
+fn list_rooms() -> Vec<u8> {
+    caro_protocol::MessagePacket::new_player_packet(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms)).to_serial()
+}
+
+async fn start_server(limits: LimitSettings) -> (std::net::SocketAddr, tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+    let settings = ServerSettings {
+        port: 0,
+        limits,
+        ..Default::default()
+    };
+    let server = CaroServer::bind(settings).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server.serve_until(async move {
+        let _ = stopped.await;
+    }));
+    (addr, stop_server, serving)
+}
+
+// everything the server sends within the window, responses may arrive glued together
+async fn read_for(stream: &mut TcpStream, window: Duration) -> (String, bool) {
+    let mut received = Vec::new();
+    let mut buffer = [0; 1024];
+    let deadline = tokio::time::Instant::now() + window;
+    loop {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+            Ok(Ok(0)) | Ok(Err(_)) => return (String::from_utf8_lossy(&received).into_owned(), true),
+            Ok(Ok(bytesread)) => received.extend_from_slice(&buffer[..bytesread]),
+            Err(_elapsed) => return (String::from_utf8_lossy(&received).into_owned(), false),
+        }
+    }
+}
+
+#[test]
+fn token_bucket_allows_bursts_then_refills() {
+    let mut bucket = TokenBucket::new(2, 4.0);
+    let start = Instant::now();
+    assert!(bucket.try_take_at(start));
+    assert!(bucket.try_take_at(start));
+    assert!(!bucket.try_take_at(start));
+    // a quarter second buys one more request
+    assert!(bucket.try_take_at(start + Duration::from_millis(250)));
+    assert!(!bucket.try_take_at(start + Duration::from_millis(250)));
+    // never more than the burst, however long it was quiet
+    let later = start + Duration::from_secs(60);
+    assert!(bucket.try_take_at(later));
+    assert!(bucket.try_take_at(later));
+    assert!(!bucket.try_take_at(later));
+}
+
+#[test]
+fn connections_are_counted_per_address() {
+    let counter = ConnectionCounter::new(2);
+    let home: std::net::IpAddr = "127.0.0.1".parse().unwrap();
+    let elsewhere: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+    let first = counter.try_open(home).unwrap();
+    let _second = counter.try_open(home).unwrap();
+    assert!(counter.try_open(home).is_none());
+    assert!(counter.try_open(elsewhere).is_some());
+    drop(first);
+    assert_eq!(counter.open_connections(home), 1);
+    assert!(counter.try_open(home).is_some());
+}
+
+#[tokio::test]
+async fn flooding_client_is_rate_limited() {
+    let limits = LimitSettings { requests_per_sec: 0.5, burst: 2, ..Default::default() };
+    let (addr, stop_server, serving) = start_server(limits).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..4 {
+        client.write_all(&list_rooms()).await.unwrap();
+        // keeps the packets apart on the wire
+        tokio::time::sleep(Duration::from_millis(30)).await;
+    }
+    let (received, closed) = read_for(&mut client, Duration::from_millis(500)).await;
+    assert!(!closed);
+    assert_eq!(received.matches("RoomList").count(), 2, "{}", received);
+    assert_eq!(received.matches("RateLimited").count(), 2, "{}", received);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn garbage_closes_the_connection() {
+    let limits = LimitSettings { max_invalid_packets: 2, ..Default::default() };
+    let (addr, stop_server, serving) = start_server(limits).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..2 {
+        client.write_all(b"{ not json\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+    }
+    let (_received, closed) = read_for(&mut client, Duration::from_secs(2)).await;
+    assert!(closed);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn connections_over_the_limit_are_refused() {
+    let limits = LimitSettings { max_connections_per_ip: 1, ..Default::default() };
+    let (addr, stop_server, serving) = start_server(limits).await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    let (received, closed) = read_for(&mut first, Duration::from_millis(300)).await;
+    assert!(received.contains("Session") && !closed, "{}", received);
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    let (received, closed) = read_for(&mut second, Duration::from_secs(2)).await;
+    assert!(received.is_empty() && closed, "{}", received);
+
+    // the slot is free again once the first connection is gone
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut third = TcpStream::connect(addr).await.unwrap();
+    let (received, _closed) = read_for(&mut third, Duration::from_millis(300)).await;
+    assert!(received.contains("Session"), "{}", received);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
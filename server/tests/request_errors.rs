@@ -0,0 +1,179 @@
+mod common;
+
+use simple_caro_app::{
+    caro_protocol,
+    player_state_machine::StateEvent,
+    server_error::ServerError
+};
+
+use common::Harness;
+
+#[derive(Debug, Clone, Copy)]
+enum Setup {
+    // the player really went through the requests that lead to the state
+    Reached(caro_protocol::PlayerState),
+    // as above, but the player was then taken out of its room behind the executor's back
+    Detached(caro_protocol::PlayerState),
+}
+
+fn all_states() -> Vec<caro_protocol::PlayerState> {
+    let mut states = Vec::new();
+    for conn_state in [caro_protocol::ConnectState::Connected, caro_protocol::ConnectState::Disconnected] {
+        states.push(caro_protocol::PlayerState::Logged(conn_state));
+        states.push(caro_protocol::PlayerState::InRoom(conn_state));
+        states.push(caro_protocol::PlayerState::InGame(conn_state));
+    }
+    states
+}
+
+fn all_requests() -> Vec<caro_protocol::PlayerCode> {
+    vec![
+        caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerRequestState),
+        caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerExitApplication),
+        caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::IAmAlive),
+        caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::ResumeSession("unknown".to_string())),
+        caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say("hello".to_string())),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default())),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::FourBlockOne, caro_protocol::RoomOptions::default())),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+            caro_protocol::GameRule::FiveBlockTwo,
+            caro_protocol::RoomOptions { private: true, password: Some("secret".to_string()), board_size: None }
+        )),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(1), None)),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(99), None)),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Code("NOPE".to_string()), Some("secret".to_string()))),
+        caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms),
+        caro_protocol::PlayerCode::InRoom(caro_protocol::InRoomRequest::PlayerLeaveRoom),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((0, 0))),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((-1, 5000))),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerUndo),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRedo),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerRequestContext),
+        caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerLeaveRoom),
+    ]
+}
+
+fn connect_state_of(state: caro_protocol::PlayerState) -> caro_protocol::ConnectState {
+    match state {
+        caro_protocol::PlayerState::Logged(conn_state) => conn_state,
+        caro_protocol::PlayerState::InRoom(conn_state) => conn_state,
+        caro_protocol::PlayerState::InGame(conn_state) => conn_state,
+    }
+}
+
+// returns the player under test
+async fn prepare(harness: &mut Harness, setup: Setup) -> caro_protocol::PlayerId {
+    let create_room = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default()));
+    let state = match setup {
+        Setup::Reached(state) => state,
+        Setup::Detached(state) => state,
+    };
+    let pid = harness.connect_player().await;
+    match state {
+        caro_protocol::PlayerState::Logged(_) => {},
+        caro_protocol::PlayerState::InRoom(_) => {
+            harness.request(pid, create_room).await.unwrap();
+        },
+        caro_protocol::PlayerState::InGame(_) => {
+            harness.request(pid, create_room.clone()).await.unwrap();
+            let rid = harness.room_manager.read().await.find_room_contain_player(pid).unwrap();
+            let opponent = harness.connect_player().await;
+            let join_room = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+            harness.request(opponent, join_room).await.unwrap();
+        },
+    }
+    if connect_state_of(state) == caro_protocol::ConnectState::Disconnected {
+        harness.player_manager.write().await.apply_event(pid, StateEvent::ConnectionLost).unwrap();
+    }
+    assert_eq!(harness.player_manager.read().await.get_player_state(pid), Ok(state));
+
+    if let Setup::Detached(_) = setup {
+        let rid = harness.room_manager.read().await.find_room_contain_player(pid);
+        if let Ok(rid) = rid {
+            harness.room_manager.write().await.remove_player_from_room(rid, pid).unwrap();
+        }
+    }
+    pid
+}
+
+#[tokio::test]
+async fn every_request_in_every_state_is_answered_without_panicking() {
+    let mut setups = Vec::new();
+    for state in all_states() {
+        setups.push(Setup::Reached(state));
+        setups.push(Setup::Detached(state));
+    }
+
+    for setup in setups {
+        for request in all_requests() {
+            let mut harness = Harness::new().await;
+            let pid = prepare(&mut harness, setup).await;
+            let state = harness.player_manager.read().await.get_player_state(pid).unwrap();
+            let result = harness.request(pid, request.clone()).await;
+
+            let allowed = match request {
+                caro_protocol::PlayerCode::General(_) => true,
+                caro_protocol::PlayerCode::Logged(_) => state == caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected),
+                caro_protocol::PlayerCode::InRoom(_) => state == caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected),
+                caro_protocol::PlayerCode::InGame(_) => state == caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected),
+            };
+            if !allowed {
+                assert_eq!(result, Err(ServerError::NotAllowedInState(state)), "{:?} {:?}", setup, &request);
+            }
+            // the error path must not panic either
+            harness.executor.handle_request(pid, Some(7), request).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn unknown_player_is_reported() {
+    let mut harness = Harness::new().await;
+    let request = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerRequestState);
+    assert_eq!(harness.request(42, request.clone()).await, Err(ServerError::PlayerNotFound(42)));
+    harness.executor.handle_request(42, Some(1), request).await;
+}
+
+#[tokio::test]
+async fn exit_without_room_removes_the_player() {
+    let mut harness = Harness::new().await;
+    let pid = harness.connect_player().await;
+    let request = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::PlayerExitApplication);
+    assert_eq!(harness.request(pid, request).await, Ok(()));
+    assert!(!harness.player_manager.read().await.player_exist(pid));
+}
+
+#[tokio::test]
+async fn joining_a_missing_room_fails() {
+    let mut harness = Harness::new().await;
+    let pid = harness.connect_player().await;
+    let request = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(7), None));
+    assert_eq!(
+        harness.request(pid, request).await,
+        Err(ServerError::JoinRefused(caro_protocol::RoomKey::Id(7), caro_protocol::JoinFailure::NoSuchRoom))
+    );
+    assert_eq!(
+        harness.player_manager.read().await.get_player_state(pid),
+        Ok(caro_protocol::PlayerState::Logged(caro_protocol::ConnectState::Connected))
+    );
+}
+
+#[tokio::test]
+async fn errors_map_to_protocol_responses() {
+    assert!(matches!(
+        ServerError::RoomNotFound(3).to_response(),
+        caro_protocol::ServerCode::Error(caro_protocol::ErrorResponse::NoSuchRoom(3))
+    ));
+    assert!(matches!(
+        ServerError::RoomLimitReached.to_response(),
+        caro_protocol::ServerCode::Error(caro_protocol::ErrorResponse::ServerFull)
+    ));
+    assert!(matches!(
+        ServerError::PlayerNotInRoom(1).to_response(),
+        caro_protocol::ServerCode::Error(caro_protocol::ErrorResponse::NotInRoom)
+    ));
+    assert!(matches!(
+        ServerError::JoinRefused(caro_protocol::RoomKey::Id(2), caro_protocol::JoinFailure::RoomFull).to_response(),
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::FailedToJoinRoom(caro_protocol::RoomKey::Id(2), caro_protocol::JoinFailure::RoomFull))
+    ));
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::caro_protocol;
+
+use common::{request, start_server, Connection};
+
+#[tokio::test]
+async fn responses_come_back_in_request_order() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut client = Connection::open(addr).await;
+
+    // all in one write, the server has to split them up itself
+    let mut burst = Vec::new();
+    for id in 1..=5 {
+        burst.extend(request(id, caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms)));
+    }
+    client.stream.write_all(&burst).await.unwrap();
+    let mut ids = Vec::new();
+    for _ in 1..=5 {
+        let (id, code) = client.next().await;
+        assert!(matches!(code, caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::RoomList(_))), "{:?}", code);
+        ids.push(id);
+    }
+    assert_eq!(ids, (1..=5).map(Some).collect::<Vec<_>>());
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn move_results_carry_the_id_of_the_move() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+
+    let create = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        caro_protocol::GameRule::TicTacToe,
+        caro_protocol::RoomOptions::default()
+    ));
+    player1.stream.write_all(&request(1, create)).await.unwrap();
+    let (id, joined) = player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await;
+    let rid = match joined {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    assert_eq!(id, Some(1));
+    let join = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+    player2.stream.write_all(&request(1, join)).await.unwrap();
+    player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::InRoom(caro_protocol::InRoomResponse::YourRoomIsFull(_)))).await;
+
+    // the second move comes before the opponent's, it is refused and must not be taken for the first one
+    let mut moves = request(20, caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((1, 1))));
+    moves.extend(request(21, caro_protocol::PlayerCode::InGame(caro_protocol::InGameRequest::PlayerMove((1, 1)))));
+    player1.stream.write_all(&moves).await.unwrap();
+    let is_move_result = |code: &caro_protocol::ServerCode| matches!(code,
+        caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveSuccess) |
+        caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveRejected(_)) |
+        caro_protocol::ServerCode::Error(_));
+    let (id, code) = player1.next_matching(is_move_result).await;
+    assert_eq!(id, Some(20));
+    assert!(matches!(code, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveSuccess)), "{:?}", code);
+    let (id, code) = player1.next_matching(is_move_result).await;
+    assert_eq!(id, Some(21));
+    assert!(matches!(code, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::MoveRejected(caro_protocol::MoveRejection::NotYourTurn))), "{:?}", code);
+
+    // the move the opponent is sent is not an answer to anything it asked
+    let (id, _) = player2.next_matching(|code| matches!(code, caro_protocol::ServerCode::InGame(caro_protocol::InGameResponse::Delta(_)))).await;
+    assert_eq!(id, None);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
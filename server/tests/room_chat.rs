@@ -0,0 +1,76 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use simple_caro_app::{caro_protocol, server_error::ServerError};
+
+use common::{request, start_server, Connection, Harness};
+
+fn create_room(rule: caro_protocol::GameRule, board_size: Option<usize>) -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::RequestRoomAsPlayer1(
+        rule,
+        caro_protocol::RoomOptions { board_size, ..Default::default() }
+    ))
+}
+
+async fn next_chat(player: &mut Connection) -> (caro_protocol::PlayerOrder, String) {
+    let (_, code) = tokio::time::timeout(
+        Duration::from_secs(5),
+        player.next_matching(|code| matches!(code, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Chat(_, _))))
+    ).await.unwrap();
+    match code {
+        caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Chat(order, text)) => (order, text),
+        other => panic!("expected a chat line, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn rooms_are_created_with_the_asked_board_size() {
+    let mut harness = Harness::new().await;
+    let pid = harness.connect_player().await;
+    assert_eq!(harness.request(pid, create_room(caro_protocol::GameRule::FiveBlockTwo, Some(5000))).await,
+               Err(ServerError::BoardSizeNotAllowed(5000)));
+    assert!(harness.room_manager.read().await.find_room_contain_player(pid).is_err());
+
+    harness.request(pid, create_room(caro_protocol::GameRule::FiveBlockTwo, Some(19))).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(pid).unwrap();
+    let games = harness.game_manager.read().await;
+    let game = games.get_handle(games.find_game_contain_room(rid).unwrap()).unwrap();
+    let context = game.get_context().await.unwrap();
+    assert_eq!((context.board_height, context.board_width), (19, 19));
+}
+
+#[tokio::test]
+async fn chat_lines_reach_everyone_in_the_room() {
+    let (addr, stop_server, serving) = start_server().await;
+    let mut player1 = Connection::open(addr).await;
+    let mut player2 = Connection::open(addr).await;
+
+    player1.stream.write_all(&request(1, create_room(caro_protocol::GameRule::TicTacToe, None))).await.unwrap();
+    let rid = match player1.next_matching(|code| matches!(code, caro_protocol::ServerCode::Logged(_))).await.1 {
+        caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::JoinedRoomAsPlayer1(rid)) => rid,
+        other => panic!("cannot create a room: {:?}", other),
+    };
+    let join = caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::JoinRoom(caro_protocol::RoomKey::Id(rid), None));
+    player2.stream.write_all(&request(1, join)).await.unwrap();
+    player2.next_matching(|code| matches!(code, caro_protocol::ServerCode::InGame(_))).await;
+
+    let say = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say("  good luck  ".to_string()));
+    player2.stream.write_all(&request(2, say)).await.unwrap();
+    for player in [&mut player1, &mut player2] {
+        assert_eq!(next_chat(player).await, (caro_protocol::PlayerOrder::Player2, "good luck".to_string()));
+    }
+
+    // nobody is there to hear it outside a room
+    let mut lonely = Connection::open(addr).await;
+    let say = caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::Say("anyone?".to_string()));
+    lonely.stream.write_all(&request(1, say)).await.unwrap();
+    let (id, code) = lonely.next_matching(|code| matches!(code, caro_protocol::ServerCode::Error(_))).await;
+    assert_eq!(id, Some(1));
+    assert!(matches!(code, caro_protocol::ServerCode::Error(caro_protocol::ErrorResponse::NotInRoom)), "{:?}", code);
+
+    stop_server.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+}
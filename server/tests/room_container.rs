@@ -0,0 +1,257 @@
+use std::{net::{IpAddr, Ipv4Addr}, time::Duration};
+
+use simple_caro_app::{
+    caro_protocol::{GameRule, JoinFailure, RoomKey, RoomOptions},
+    id_pool::IdPool,
+    room_manager::{PlayerOrder, RoomContainer},
+    server_error::ServerError
+};
+
+const LOCAL: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const ELSEWHERE: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7));
+
+fn container() -> RoomContainer {
+    RoomContainer::new(4, IdPool::<i32>::new())
+}
+
+fn room_with_creator(rooms: &mut RoomContainer, creator: i32) -> i32 {
+    let rid = rooms.add_room(GameRule::FiveBlockTwo).unwrap();
+    rooms.add_player_to_room(rid, PlayerOrder::Player1(creator)).unwrap();
+    rid
+}
+
+#[test]
+fn joiner_takes_the_second_seat() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None), Ok((rid, PlayerOrder::Player2(2))));
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((1, 2)));
+    assert!(rooms.room_full(rid));
+}
+
+#[test]
+fn joiner_takes_the_first_seat_once_the_creator_left() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None).unwrap();
+    rooms.remove_player_from_room(rid, 1).unwrap();
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 3, LOCAL, None), Ok((rid, PlayerOrder::Player1(3))));
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((3, 2)));
+}
+
+#[test]
+fn joining_a_full_room_is_refused() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None).unwrap();
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 3, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::RoomFull)));
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((1, 2)));
+}
+
+#[test]
+fn joining_a_room_in_game_is_refused() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None).unwrap();
+    rooms.mark_game_started(rid).unwrap();
+    rooms.remove_player_from_room(rid, 2).unwrap();
+    assert!(rooms.game_started(rid));
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 3, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::RoomFull)));
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((1, -1)));
+}
+
+#[test]
+fn joining_a_missing_room_is_refused() {
+    let mut rooms = container();
+    assert_eq!(rooms.join_room(RoomKey::Id(9), 1, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(9), JoinFailure::NoSuchRoom)));
+}
+
+#[test]
+fn joining_your_own_room_is_refused() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 1, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::AlreadyInRoom)));
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((1, -1)));
+}
+
+#[test]
+fn joining_a_second_room_is_refused() {
+    let mut rooms = container();
+    let first = room_with_creator(&mut rooms, 1);
+    let second = room_with_creator(&mut rooms, 2);
+    assert_eq!(rooms.join_room(RoomKey::Id(second), 1, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(second), JoinFailure::AlreadyInRoom)));
+    assert_eq!(rooms.find_room_contain_player(1), Ok(first));
+}
+
+#[test]
+fn seats_are_never_overwritten() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    assert_eq!(
+        rooms.add_player_to_room(rid, PlayerOrder::Player1(2)),
+        Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::RoomFull))
+    );
+    assert_eq!(rooms.get_pids_in_room(rid), Ok((1, -1)));
+}
+
+#[test]
+fn room_limit_and_rules_are_enforced() {
+    let mut rooms = container();
+    rooms.set_allowed_rules(vec![GameRule::TicTacToe]);
+    assert_eq!(rooms.add_room(GameRule::FiveBlockTwo), Err(ServerError::RuleNotAllowed(GameRule::FiveBlockTwo)));
+    for _ in 0..4 {
+        rooms.add_room(GameRule::TicTacToe).unwrap();
+    }
+    assert_eq!(rooms.add_room(GameRule::TicTacToe), Err(ServerError::RoomLimitReached));
+}
+
+#[test]
+fn removed_rooms_free_their_id() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    rooms.remove_room(rid).unwrap();
+    assert!(!rooms.room_exist(rid));
+    assert_eq!(rooms.remove_room(rid), Err(ServerError::RoomNotFound(rid)));
+    assert_eq!(rooms.add_room(GameRule::TicTacToe), Ok(rid));
+}
+
+fn private_room(rooms: &mut RoomContainer, creator: i32, password: Option<&str>) -> (i32, String) {
+    let options = RoomOptions { private: true, password: password.map(|password| password.to_string()), board_size: None };
+    let rid = rooms.add_room_with_options(GameRule::FiveBlockTwo, options).unwrap();
+    rooms.add_player_to_room(rid, PlayerOrder::Player1(creator)).unwrap();
+    (rid, rooms.get_join_code(rid).unwrap())
+}
+
+#[test]
+fn private_rooms_are_joined_by_code_only() {
+    let mut rooms = container();
+    let (rid, code) = private_room(&mut rooms, 1, None);
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::NoSuchRoom)));
+    assert_eq!(rooms.join_room(RoomKey::Code(code), 2, LOCAL, None), Ok((rid, PlayerOrder::Player2(2))));
+}
+
+#[test]
+fn join_codes_are_unique_and_not_sequential() {
+    let mut rooms = RoomContainer::new(64, IdPool::<i32>::new());
+    let mut codes = Vec::new();
+    for creator in 0..64 {
+        let (rid, code) = private_room(&mut rooms, creator, None);
+        assert_ne!(code, rid.to_string());
+        assert!(code.len() >= 8);
+        codes.push(code);
+    }
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), 64);
+}
+
+#[test]
+fn private_rooms_are_not_listed() {
+    let mut rooms = container();
+    let public = room_with_creator(&mut rooms, 1);
+    private_room(&mut rooms, 2, None);
+    let listed: Vec<i32> = rooms.list_public_rooms().iter().map(|summary| summary.rid).collect();
+    assert_eq!(listed, vec![public]);
+}
+
+#[test]
+fn full_rooms_are_not_listed() {
+    let mut rooms = container();
+    let full = room_with_creator(&mut rooms, 1);
+    rooms.join_room(RoomKey::Id(full), 2, LOCAL, None).unwrap();
+    let open = room_with_creator(&mut rooms, 3);
+    let listed = rooms.list_public_rooms();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].rid, open);
+    assert_eq!(listed[0].players, 1);
+    assert!(!listed[0].has_password);
+}
+
+#[test]
+fn password_is_checked() {
+    let mut rooms = container();
+    let options = RoomOptions { private: false, password: Some("hunter2".to_string()), board_size: None };
+    let rid = rooms.add_room_with_options(GameRule::TicTacToe, options).unwrap();
+    rooms.add_player_to_room(rid, PlayerOrder::Player1(1)).unwrap();
+    assert!(rooms.list_public_rooms()[0].has_password);
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::WrongPassword)));
+    assert_eq!(
+        rooms.join_room(RoomKey::Id(rid), 2, LOCAL, Some("hunter3".to_string())),
+        Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::WrongPassword))
+    );
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, LOCAL, Some("hunter2".to_string())), Ok((rid, PlayerOrder::Player2(2))));
+}
+
+#[test]
+fn private_room_with_password_needs_both() {
+    let mut rooms = container();
+    let (rid, code) = private_room(&mut rooms, 1, Some("pw"));
+    assert_eq!(
+        rooms.join_room(RoomKey::Code(code.clone()), 2, LOCAL, None),
+        Err(ServerError::JoinRefused(RoomKey::Code(code.clone()), JoinFailure::WrongPassword))
+    );
+    assert_eq!(rooms.join_room(RoomKey::Code(code), 2, LOCAL, Some("pw".to_string())), Ok((rid, PlayerOrder::Player2(2))));
+}
+
+#[test]
+fn failed_joins_are_rate_limited() {
+    let mut rooms = container();
+    rooms.set_join_limit(3, Duration::from_millis(200));
+    let (rid, code) = private_room(&mut rooms, 1, None);
+    for guess in ["AAAAAAAA", "BBBBBBBB", "CCCCCCCC"] {
+        let key = RoomKey::Code(guess.to_string());
+        assert_eq!(rooms.join_room(key.clone(), 2, LOCAL, None), Err(ServerError::JoinRefused(key, JoinFailure::NoSuchRoom)));
+    }
+    // even the right code is refused while the address is locked out
+    assert_eq!(
+        rooms.join_room(RoomKey::Code(code.clone()), 2, LOCAL, None),
+        Err(ServerError::JoinRefused(RoomKey::Code(code.clone()), JoinFailure::TooManyAttempts))
+    );
+    // reconnecting as a new player does not lift it
+    assert_eq!(
+        rooms.join_room(RoomKey::Code(code.clone()), 4, LOCAL, None),
+        Err(ServerError::JoinRefused(RoomKey::Code(code.clone()), JoinFailure::TooManyAttempts))
+    );
+    // other addresses are not affected
+    assert_eq!(rooms.join_room(RoomKey::Code(code.clone()), 3, ELSEWHERE, None), Ok((rid, PlayerOrder::Player2(3))));
+
+    std::thread::sleep(Duration::from_millis(250));
+    rooms.remove_player_from_room(rid, 3).unwrap();
+    assert_eq!(rooms.join_room(RoomKey::Code(code), 2, LOCAL, None), Ok((rid, PlayerOrder::Player2(2))));
+}
+
+#[test]
+fn a_reused_pid_does_not_inherit_failures() {
+    let mut rooms = container();
+    rooms.set_join_limit(1, Duration::from_secs(60));
+    let rid = room_with_creator(&mut rooms, 1);
+    assert!(rooms.join_room(RoomKey::Id(rid + 1), 2, LOCAL, None).is_err());
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Id(rid), JoinFailure::TooManyAttempts)));
+    // the same pid handed to a connection from somewhere else
+    assert_eq!(rooms.join_room(RoomKey::Id(rid), 2, ELSEWHERE, None), Ok((rid, PlayerOrder::Player2(2))));
+}
+
+#[test]
+fn removed_private_room_code_stops_working() {
+    let mut rooms = container();
+    let (rid, code) = private_room(&mut rooms, 1, None);
+    rooms.remove_player_from_room(rid, 1).unwrap();
+    rooms.remove_room(rid).unwrap();
+    assert_eq!(rooms.join_room(RoomKey::Code(code.clone()), 2, LOCAL, None), Err(ServerError::JoinRefused(RoomKey::Code(code), JoinFailure::NoSuchRoom)));
+}
+
+#[test]
+fn player_index_follows_seats() {
+    let mut rooms = container();
+    let rid = room_with_creator(&mut rooms, 1);
+    rooms.join_room(RoomKey::Id(rid), 2, LOCAL, None).unwrap();
+    assert_eq!(rooms.find_room_contain_player(2), Ok(rid));
+    rooms.remove_player_from_room(rid, 2).unwrap();
+    assert_eq!(rooms.find_room_contain_player(2), Err(ServerError::PlayerNotInRoom(2)));
+    // removing someone who sits elsewhere leaves them where they are
+    let other = room_with_creator(&mut rooms, 3);
+    rooms.remove_player_from_room(rid, 3).unwrap();
+    assert_eq!(rooms.find_room_contain_player(3), Ok(other));
+    rooms.remove_room(rid).unwrap();
+    assert_eq!(rooms.find_room_contain_player(1), Err(ServerError::PlayerNotInRoom(1)));
+}
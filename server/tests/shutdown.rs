@@ -0,0 +1,96 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpStream};
+
+use simple_caro_app::{
+    caro_protocol,
+    game_archive,
+    server_app::CaroServer,
+    server_config::ServerSettings
+};
+
+use common::{create_room, join_room, play, Harness};
+
+async fn receive(stream: &mut TcpStream) -> caro_protocol::ServerCode {
+    let mut buffer = [0; 1024];
+    let bytesread = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer)).await.unwrap().unwrap();
+    assert!(bytesread > 0, "connection closed before anything arrived");
+    match caro_protocol::MessagePacket::from_serial(&buffer[..bytesread]).unwrap().code() {
+        caro_protocol::GenericCode::Server(code) => code,
+        caro_protocol::GenericCode::Player(code) => panic!("server sent a player code {:?}", code),
+    }
+}
+
+#[tokio::test]
+async fn only_running_games_are_archived() {
+    let mut harness = Harness::new().await;
+    let pid1 = harness.connect_player().await;
+    let pid2 = harness.connect_player().await;
+    let waiting = harness.connect_player().await;
+    harness.request(pid1, create_room()).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(pid1).unwrap();
+    harness.request(pid2, join_room(rid)).await.unwrap();
+    harness.request(pid1, play((1, 1))).await.unwrap();
+    // a room nobody joined has no game going on yet
+    harness.request(waiting, create_room()).await.unwrap();
+
+    let archived = harness.executor.archive_games().await;
+    assert_eq!(archived.len(), 1);
+    let game = &archived[0];
+    assert_eq!((game.rid, game.player1, game.player2), (rid, pid1, pid2));
+    assert_eq!(game.rule, caro_protocol::GameRule::TicTacToe);
+    assert_eq!(game.player1_move_history, vec![(1, 1)]);
+    assert!(game.player2_move_history.is_empty());
+    assert_eq!(game.game_state, caro_protocol::GameState::Player2Turn);
+
+    let dir = std::env::temp_dir().join(format!("caro-archive-{}", std::process::id()));
+    let path = game_archive::write_archive(&dir, &archived).unwrap();
+    assert_eq!(game_archive::read_archive(&path).unwrap(), archived);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn requests_are_refused_once_shutdown_began() {
+    let mut harness = Harness::new().await;
+    let pid = harness.connect_player().await;
+    harness.executor.begin_shutdown().await;
+
+    harness.executor.handle_request(pid, None, create_room()).await;
+    assert!(harness.room_manager.read().await.find_room_contain_player(pid).is_err());
+    assert!(harness.executor.drain(Duration::from_secs(1)).await);
+}
+
+#[tokio::test]
+async fn server_notifies_players_and_stops_accepting() {
+    let settings = ServerSettings {
+        port: 0,
+        ..Default::default()
+    };
+    let server = CaroServer::bind(settings).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let (stop_server, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server.serve_until(async move {
+        let _ = stopped.await;
+    }));
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    assert!(matches!(receive(&mut client).await, caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::Session(_))));
+    // an answer means the server registered the player
+    let request = caro_protocol::MessagePacket::new_player_packet(caro_protocol::PlayerCode::Logged(caro_protocol::LoggedRequest::ListRooms));
+    client.write_all(&request.to_serial()).await.unwrap();
+    assert!(matches!(receive(&mut client).await, caro_protocol::ServerCode::Logged(caro_protocol::LoggedResponse::RoomList(_))));
+
+    stop_server.send(()).unwrap();
+    assert!(matches!(
+        receive(&mut client).await,
+        caro_protocol::ServerCode::General(caro_protocol::GeneralResponse::ServerShuttingDown)
+    ));
+    tokio::time::timeout(Duration::from_secs(5), serving).await.unwrap().unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+    // the connection is closed by the server, not left open until the client goes away
+    let mut buffer = [0; 1024];
+    let bytesread = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buffer)).await.unwrap().unwrap();
+    assert_eq!(bytesread, 0);
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use std::time::Duration;
+
+use simple_caro_app::{
+    caro_protocol,
+    server_error::ServerError,
+    snapshot::{self, GameSnapshot, RoomSnapshot, ServerSnapshot}
+};
+
+use common::{check_consistency, create_room_with, join_room, play, Harness};
+
+const RESERVED_FOR: Duration = Duration::from_secs(60);
+
+fn resume(session_token: caro_protocol::SessionToken) -> caro_protocol::PlayerCode {
+    caro_protocol::PlayerCode::General(caro_protocol::GeneralRequest::ResumeSession(session_token))
+}
+
+async fn session_of(harness: &Harness, pid: caro_protocol::PlayerId) -> caro_protocol::SessionToken {
+    harness.player_manager.read().await.get_session_token(pid).unwrap()
+}
+
+async fn state_of(harness: &Harness, pid: caro_protocol::PlayerId) -> caro_protocol::PlayerState {
+    harness.player_manager.read().await.get_player_state(pid).unwrap()
+}
+
+async fn moves_in(harness: &Harness, rid: caro_protocol::RoomId) -> (Vec<caro_protocol::Coordinate>, Vec<caro_protocol::Coordinate>, caro_protocol::GameState) {
+    let game = {
+        let games = harness.game_manager.read().await;
+        games.get_handle(games.find_game_contain_room(rid).unwrap()).unwrap()
+    };
+    let context = game.get_context().await.unwrap();
+    (context.player1_move_history, context.player2_move_history, context.game_state)
+}
+
+#[tokio::test]
+async fn restored_rooms_wait_for_their_players() {
+    let mut before = Harness::new().await;
+    let pid1 = before.connect_player().await;
+    let pid2 = before.connect_player().await;
+    let host = before.connect_player().await;
+    before.request(pid1, create_room_with(caro_protocol::GameRule::FourBlockOne, caro_protocol::RoomOptions::default())).await.unwrap();
+    let running = before.room_manager.read().await.find_room_contain_player(pid1).unwrap();
+    before.request(pid2, join_room(running)).await.unwrap();
+    before.request(pid1, play((3, 3))).await.unwrap();
+    before.request(pid2, play((4, 4))).await.unwrap();
+    before.request(pid1, play((3, 4))).await.unwrap();
+    let options = caro_protocol::RoomOptions { private: true, password: Some("secret".to_string()), board_size: None };
+    before.request(host, create_room_with(caro_protocol::GameRule::TicTacToe, options.clone())).await.unwrap();
+    let waiting = before.room_manager.read().await.find_room_contain_player(host).unwrap();
+    let join_code = before.room_manager.read().await.get_join_code(waiting);
+
+    let taken = before.executor.snapshot().await;
+    assert_eq!(taken.rooms.len(), 2);
+    let dir = std::env::temp_dir().join(format!("caro-snapshot-{}", std::process::id()));
+    let path = dir.join("snapshot.json");
+    snapshot::write_snapshot(&path, &taken).unwrap();
+    let read_back = snapshot::read_snapshot(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(read_back, taken);
+
+    let mut after = Harness::new().await;
+    assert!(after.executor.restore(&read_back, RESERVED_FOR).await.is_empty());
+    assert_eq!(moves_in(&after, running).await, (vec![(3, 3), (3, 4)], vec![(4, 4)], caro_protocol::GameState::Player2Turn));
+    assert_eq!(after.room_manager.read().await.get_join_code(waiting), join_code);
+    assert_eq!(after.room_manager.read().await.get_room_options(waiting), Ok(options));
+    // reserved seats are not up for grabs
+    let stranger = after.connect_player().await;
+    assert_eq!(
+        after.request(stranger, join_room(running)).await,
+        Err(ServerError::JoinRefused(caro_protocol::RoomKey::Id(running), caro_protocol::JoinFailure::RoomFull))
+    );
+    check_consistency(&after).await.unwrap();
+
+    let new_pid2 = after.connect_player().await;
+    after.request(new_pid2, resume(session_of(&before, pid2).await)).await.unwrap();
+    assert_eq!(state_of(&after, new_pid2).await, caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
+    assert_eq!(after.room_manager.read().await.get_pids_in_room(running), Ok((-1, new_pid2)));
+    after.request(new_pid2, play((5, 5))).await.unwrap();
+
+    let new_pid1 = after.connect_player().await;
+    after.request(new_pid1, resume(session_of(&before, pid1).await)).await.unwrap();
+    assert_eq!(after.room_manager.read().await.get_pids_in_room(running), Ok((new_pid1, new_pid2)));
+    after.request(new_pid1, play((3, 5))).await.unwrap();
+    assert_eq!(moves_in(&after, running).await.2, caro_protocol::GameState::Player2Turn);
+
+    let new_host = after.connect_player().await;
+    after.request(new_host, resume(session_of(&before, host).await)).await.unwrap();
+    assert_eq!(state_of(&after, new_host).await, caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected));
+    // the resumed session keeps its token for the next restart
+    assert_eq!(session_of(&after, new_host).await, session_of(&before, host).await);
+    check_consistency(&after).await.unwrap();
+}
+
+#[tokio::test]
+async fn a_seat_is_claimed_only_once() {
+    let mut before = Harness::new().await;
+    let pid = before.connect_player().await;
+    before.request(pid, create_room_with(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default())).await.unwrap();
+    let session_token = session_of(&before, pid).await;
+    let mut after = Harness::new().await;
+    after.executor.restore(&before.executor.snapshot().await, RESERVED_FOR).await;
+
+    let first = after.connect_player().await;
+    let second = after.connect_player().await;
+    after.request(first, resume(session_token.clone())).await.unwrap();
+    // the second one now takes the seat over from the first connection
+    after.request(second, resume(session_token.clone())).await.unwrap();
+    assert!(!after.player_manager.read().await.player_exist(first));
+    assert_eq!(after.request(second, resume(session_token)).await, Err(ServerError::NotAllowedInState(caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected))));
+
+    let other = after.connect_player().await;
+    assert_eq!(after.request(other, resume("not a token".to_string())).await, Err(ServerError::SessionNotFound));
+    check_consistency(&after).await.unwrap();
+}
+
+#[tokio::test]
+async fn a_dropped_connection_gets_its_seat_back() {
+    let mut harness = Harness::new().await;
+    let pid1 = harness.connect_player().await;
+    let pid2 = harness.connect_player().await;
+    harness.request(pid1, create_room_with(caro_protocol::GameRule::TicTacToe, caro_protocol::RoomOptions::default())).await.unwrap();
+    let rid = harness.room_manager.read().await.find_room_contain_player(pid1).unwrap();
+    harness.request(pid2, join_room(rid)).await.unwrap();
+    harness.request(pid1, play((1, 1))).await.unwrap();
+
+    let reconnected = harness.connect_player().await;
+    harness.request(reconnected, resume(session_of(&harness, pid1).await)).await.unwrap();
+    assert!(!harness.player_manager.read().await.player_exist(pid1));
+    assert_eq!(harness.room_manager.read().await.get_pids_in_room(rid), Ok((reconnected, pid2)));
+    harness.request(pid2, play((0, 0))).await.unwrap();
+    harness.request(reconnected, play((2, 2))).await.unwrap();
+    check_consistency(&harness).await.unwrap();
+}
+
+#[tokio::test]
+async fn returning_player_starts_a_filled_room() {
+    let snapshot = ServerSnapshot {
+        rooms: vec![RoomSnapshot {
+            rid: 4,
+            rule: caro_protocol::GameRule::TicTacToe,
+            options: caro_protocol::RoomOptions::default(),
+            join_code: None,
+            player1: Some("returning".to_string()),
+            player2: None,
+            game: None,
+        }],
+    };
+    let mut harness = Harness::new().await;
+    assert!(harness.executor.restore(&snapshot, RESERVED_FOR).await.is_empty());
+
+    let joiner = harness.connect_player().await;
+    harness.request(joiner, join_room(4)).await.unwrap();
+    assert_eq!(harness.room_manager.read().await.get_pids_in_room(4), Ok((-1, joiner)));
+    assert_eq!(state_of(&harness, joiner).await, caro_protocol::PlayerState::InRoom(caro_protocol::ConnectState::Connected));
+
+    let returning = harness.connect_player().await;
+    harness.request(returning, resume("returning".to_string())).await.unwrap();
+    for pid in [returning, joiner] {
+        assert_eq!(state_of(&harness, pid).await, caro_protocol::PlayerState::InGame(caro_protocol::ConnectState::Connected));
+    }
+    check_consistency(&harness).await.unwrap();
+}
+
+#[tokio::test]
+async fn unclaimed_rooms_are_closed_after_the_timeout() {
+    let snapshot = ServerSnapshot {
+        rooms: vec![RoomSnapshot {
+            rid: 2,
+            rule: caro_protocol::GameRule::FiveBlockTwo,
+            options: caro_protocol::RoomOptions::default(),
+            join_code: None,
+            player1: Some("gone".to_string()),
+            player2: Some("also gone".to_string()),
+            game: Some(GameSnapshot {
+                board_size: 16,
+                moves: vec![(caro_protocol::PlayerOrder::Player1, (8, 8))],
+                turn: caro_protocol::GameState::Player2Turn,
+            }),
+        }],
+    };
+    let harness = Harness::new().await;
+    assert!(harness.executor.restore(&snapshot, Duration::ZERO).await.is_empty());
+    assert!(harness.room_manager.read().await.room_exist(2));
+    harness.executor.expire_reservations().await;
+    assert!(!harness.room_manager.read().await.room_exist(2));
+    assert!(harness.game_manager.read().await.get_gids().is_empty());
+}
+
+#[tokio::test]
+async fn moves_that_do_not_replay_are_reported() {
+    let game = GameSnapshot {
+        board_size: 16,
+        // the same tile twice
+        moves: snapshot::order_moves(&[(1, 1)], &[(1, 1)]),
+        turn: caro_protocol::GameState::Player1Turn,
+    };
+    let snapshot = ServerSnapshot {
+        rooms: vec![RoomSnapshot {
+            rid: 1,
+            rule: caro_protocol::GameRule::FiveBlockTwo,
+            options: caro_protocol::RoomOptions::default(),
+            join_code: None,
+            player1: Some("a".to_string()),
+            player2: Some("b".to_string()),
+            game: Some(game),
+        }],
+    };
+    let harness = Harness::new().await;
+    assert_eq!(harness.executor.restore(&snapshot, RESERVED_FOR).await, vec![(1, ServerError::RestoreFailed(1))]);
+    assert!(!harness.room_manager.read().await.room_exist(1));
+    check_consistency(&harness).await.unwrap();
+}